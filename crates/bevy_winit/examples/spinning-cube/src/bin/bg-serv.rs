@@ -11,7 +11,7 @@ use bevy::{
     winit::{WakeUp, WinitPlugin},
 };
 // use bevy_window::{PresentMode, WindowLevel, WindowMode, WindowResized, WindowResolution};
-use bevy_wallpaper::WallpaperPlugin;
+use bevy_wallpaper::{PaletteBindings, PaletteSlot, WallpaperPlugin};
 use std::f32::consts::PI;
 
 /// A marker component for our shapes so we can query them separately from the ground plane
@@ -76,6 +76,11 @@ fn main() {
             }
             .into(),
         })
+        // Follow the pywal / Xresources colors, the ones above are used until they are loaded.
+        .insert_resource(PaletteBindings {
+            clear_color: Some(PaletteSlot::Background),
+            wireframe: Some(PaletteSlot::Color(2)),
+        })
         .add_systems(Startup, (camera_setup, spawn_cube))
         .add_systems(
             Update,
@@ -0,0 +1,327 @@
+use core::{num::NonZeroU32, time::Duration};
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+    },
+    thread,
+};
+
+use bevy::{
+    asset::Assets, image::Image, math::UVec2, render::render_resource::TextureFormat, time::Time,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use image::{
+    AnimationDecoder, Frames, ImageFormat, ImageReader,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    metadata::LoopCount,
+};
+use tracing::warn;
+
+use crate::{
+    desktop_2d::{ImageFit, SceneTexture, TextureScene, follow_scene_monitors},
+    file_drop::{CurrentWallpaper, WallpaperSourceKind},
+    monitor_identity::{MonitorIdentity, MonitorMatcher},
+};
+
+/// Frames shorter than this are shown for [`DEFAULT_DELAY`] instead, like browsers do, since
+/// many GIFs leave their delays at zero.
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// How many decoded frames wait ahead of the one on screen.
+const QUEUED_FRAMES: usize = 4;
+
+/// How often an [`AnimatedImage`] plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationLoop {
+    /// As often as the file says, which is usually forever.
+    #[default]
+    File,
+    Forever,
+    Times(NonZeroU32),
+}
+
+/// What the [`AnimatedImagePlugin`] plays, which can be changed while it runs.
+#[derive(Resource, Debug, Clone)]
+pub struct AnimatedImage {
+    /// An animated GIF, APNG or WebP.
+    pub path: PathBuf,
+    pub fit: ImageFit,
+    /// Overrides `fit` on the monitors that match. The first match wins.
+    pub monitor_fit: Vec<(MonitorMatcher, ImageFit)>,
+    pub looping: AnimationLoop,
+    /// Animations whose decoded frames take up to this many bytes are decoded once and replayed
+    /// from memory. Longer ones are decoded again on every loop.
+    pub cache_budget: usize,
+}
+
+impl AnimatedImage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            fit: ImageFit::default(),
+            monitor_fit: Vec::new(),
+            looping: AnimationLoop::default(),
+            cache_budget: 256 * 1024 * 1024,
+        }
+    }
+}
+
+impl TextureScene for AnimatedImage {
+    fn fit_for(&self, identity: Option<&MonitorIdentity>) -> ImageFit {
+        ImageFit::for_monitor(self.fit, &self.monitor_fit, identity)
+    }
+}
+
+/// A built-in scene that plays an animated GIF, APNG or WebP on every monitor.
+///
+/// Frames are decoded on a background thread, a few ahead of playback, and shown for their own
+/// delays on [`Time<Virtual>`](bevy::time::Virtual).
+pub struct AnimatedImagePlugin {
+    pub animation: AnimatedImage,
+}
+
+impl AnimatedImagePlugin {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            animation: AnimatedImage::new(path),
+        }
+    }
+}
+
+impl Plugin for AnimatedImagePlugin {
+    fn build(&self, app: &mut App) {
+        AnimatedImage::add_to(app);
+
+        app.insert_resource(self.animation.clone())
+            .init_resource::<Playback>()
+            .add_systems(
+                Update,
+                (show_current_wallpaper, start_playback, play_animation)
+                    .chain()
+                    .before(follow_scene_monitors::<AnimatedImage>),
+            );
+    }
+}
+
+/// A decoded frame, covering the whole animation.
+#[derive(Clone)]
+struct DecodedFrame {
+    size: UVec2,
+    /// RGBA8 pixels, shared with the cache.
+    pixels: Arc<[u8]>,
+    delay: Duration,
+}
+
+#[derive(Resource, Default)]
+struct Playback {
+    /// Decoded frames, until the decoder is done. Only locked to make the resource `Sync`.
+    frames: Option<Mutex<Receiver<DecodedFrame>>>,
+    /// When the frame on screen is replaced, in [`Time`] seconds.
+    next_frame: f32,
+}
+
+/// Plays animations that become the [`CurrentWallpaper`], e.g. by being dropped onto the desktop.
+fn show_current_wallpaper(
+    current: Option<Res<CurrentWallpaper>>,
+    mut animation: ResMut<AnimatedImage>,
+) {
+    let kinds = [WallpaperSourceKind::Animation];
+    if let Some(path) = CurrentWallpaper::changed_to(&current, &kinds)
+        && animation.path != path
+    {
+        animation.path = path.to_owned();
+    }
+}
+
+/// (Re)starts decoding whenever the [`AnimatedImage`] changes.
+fn start_playback(animation: Res<AnimatedImage>, time: Res<Time>, mut playback: ResMut<Playback>) {
+    if !animation.is_changed() {
+        return;
+    }
+
+    // The previous decoder stops once it notices the receiver is gone.
+    playback.frames = None;
+    playback.next_frame = time.elapsed_secs();
+
+    let (sender, receiver) = mpsc::sync_channel(QUEUED_FRAMES);
+    let animation = animation.clone();
+
+    let decoder = thread::Builder::new()
+        .name("animated image decoder".into())
+        .spawn(move || decode(&animation, &sender));
+
+    match decoder {
+        Ok(_) => playback.frames = Some(Mutex::new(receiver)),
+        Err(err) => warn!("Could not start decoding the animation: {err}"),
+    }
+}
+
+/// Decodes the animation into `frames` for as many loops as it plays.
+fn decode(animation: &AnimatedImage, frames: &SyncSender<DecodedFrame>) {
+    let path = &animation.path;
+    let mut cache = Some(Vec::new());
+    let mut cached_bytes = 0;
+    let mut loops = animation.looping;
+
+    for play in 0.. {
+        match cache.as_ref().filter(|_| play > 0) {
+            Some(cached) => {
+                for frame in cached {
+                    if frames.send(DecodedFrame::clone(frame)).is_err() {
+                        return;
+                    }
+                }
+            }
+            None => {
+                let (decoded, file_loops) = match open_animation(path) {
+                    Ok(opened) => opened,
+                    Err(err) => {
+                        warn!("Could not open the animation {path:?}: {err}");
+                        return;
+                    }
+                };
+
+                if loops == AnimationLoop::File {
+                    loops = match file_loops {
+                        LoopCount::Infinite => AnimationLoop::Forever,
+                        LoopCount::Finite(times) => AnimationLoop::Times(times),
+                    };
+                }
+
+                for frame in decoded {
+                    let frame = match frame {
+                        Ok(frame) => frame,
+                        Err(err) => {
+                            warn!("Could not decode the animation {path:?}: {err}");
+                            return;
+                        }
+                    };
+
+                    let delay = Duration::from(frame.delay());
+                    let buffer = frame.into_buffer();
+                    let frame = DecodedFrame {
+                        size: UVec2::new(buffer.width(), buffer.height()),
+                        pixels: buffer.into_raw().into(),
+                        delay: if delay < MIN_DELAY {
+                            DEFAULT_DELAY
+                        } else {
+                            delay
+                        },
+                    };
+
+                    // Long animations aren't kept, so memory stays bounded.
+                    if let Some(cached) = &mut cache {
+                        cached_bytes += frame.pixels.len();
+                        if cached_bytes <= animation.cache_budget {
+                            cached.push(frame.clone());
+                        } else {
+                            cache = None;
+                        }
+                    }
+
+                    if frames.send(frame).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+
+        // A still image is shown once and stays up.
+        let still = cache.as_ref().is_some_and(|cached| cached.len() <= 1);
+        let done = match loops {
+            AnimationLoop::Times(times) => play + 1 >= times.get(),
+            _ => false,
+        };
+        if still || done {
+            return;
+        }
+    }
+}
+
+/// Opens a GIF, APNG or WebP by its contents, returning its frames and how often it loops.
+fn open_animation(path: &Path) -> io::Result<(Frames<'static>, LoopCount)> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let format = reader.format();
+    let file: BufReader<File> = reader.into_inner();
+
+    let opened = match format {
+        Some(ImageFormat::Gif) => GifDecoder::new(file).map(animation_frames),
+        Some(ImageFormat::Png) => PngDecoder::new(file)
+            .and_then(PngDecoder::apng)
+            .map(animation_frames),
+        Some(ImageFormat::WebP) => WebPDecoder::new(file).map(animation_frames),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a GIF, PNG or WebP",
+            ));
+        }
+    };
+
+    opened.map_err(io::Error::other)
+}
+
+fn animation_frames(decoder: impl AnimationDecoder<'static>) -> (Frames<'static>, LoopCount) {
+    let loops = decoder.loop_count();
+    (decoder.into_frames(), loops)
+}
+
+/// Shows the decoded frames as their delays come up.
+fn play_animation(
+    time: Res<Time>,
+    mut playback: ResMut<Playback>,
+    mut texture: ResMut<SceneTexture<AnimatedImage>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let now = time.elapsed_secs();
+    let playback = &mut *playback;
+    let Some(frames) = playback.frames.as_mut() else {
+        return;
+    };
+    let frames = frames.get_mut().unwrap();
+
+    // Only the last frame that is due gets uploaded.
+    let mut due = None;
+    let mut finished = false;
+
+    while playback.next_frame <= now {
+        match frames.try_recv() {
+            Ok(frame) => {
+                // Don't race through frames to catch up after a long stall.
+                playback.next_frame =
+                    playback.next_frame.max(now - 1.0) + frame.delay.as_secs_f32();
+                due = Some(frame);
+            }
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => {
+                finished = true;
+                break;
+            }
+        }
+    }
+
+    if finished {
+        playback.frames = None;
+    }
+
+    let Some(frame) = due else {
+        return;
+    };
+
+    texture.upload(
+        &mut images,
+        frame.size,
+        TextureFormat::Rgba8UnormSrgb,
+        &frame.pixels,
+    );
+}
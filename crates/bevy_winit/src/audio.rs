@@ -0,0 +1,627 @@
+use core::{f32::consts::PI, time::Duration};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Read},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use bevy::{
+    time::{Real, Time},
+    utils::Instant,
+};
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use tracing::{info, warn};
+
+/// How long the source may stay quiet before the spectrum falls back to silence.
+const SILENCE_AFTER: Duration = Duration::from_millis(250);
+
+/// How long onsets are compared against.
+const BEAT_HISTORY: Duration = Duration::from_secs(1);
+
+/// The shortest time between two beats.
+const MIN_BEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The quietest level the spectrum shows, in dB relative to a full scale sine.
+const FLOOR_DB: f32 = -70.0;
+
+/// Where [`AudioInput`] reads PCM from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioSource {
+    /// A named pipe written in real time, like MPD's `fifo` output or `parec > fifo`. It is
+    /// opened again whenever its writer goes away.
+    Fifo(PathBuf),
+    /// A raw PCM file, played at its sample rate in a loop.
+    File(PathBuf),
+}
+
+/// The encoding of the PCM samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleFormat {
+    /// Signed 16 bit little endian, MPD's `44100:16:2` and `parec`'s default.
+    #[default]
+    S16Le,
+    /// 32 bit float little endian.
+    F32Le,
+}
+
+impl SampleFormat {
+    fn bytes(self) -> usize {
+        match self {
+            Self::S16Le => 2,
+            Self::F32Le => 4,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::S16Le => f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32768.0,
+            Self::F32Le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// What the [`AudioPlugin`] listens to and how it is analyzed, which can be changed while it
+/// runs.
+#[derive(Resource, Debug, Clone)]
+pub struct AudioInput {
+    pub source: AudioSource,
+    pub format: SampleFormat,
+    pub sample_rate: u32,
+    /// Interleaved channels, which are mixed down to one.
+    pub channels: u16,
+    /// Samples per analysis window, rounded up to a power of two.
+    pub fft_size: usize,
+    /// How many bands [`AudioSpectrum::bins`] has, spaced logarithmically from 20 Hz up.
+    pub bins: usize,
+    /// How much of the previous frame's spectrum and level is kept, from 0 (none) to 1 (frozen).
+    pub smoothing: f32,
+    /// How much stronger than the recent average an onset has to be to count as a beat.
+    pub beat_sensitivity: f32,
+}
+
+impl AudioInput {
+    pub fn new(source: AudioSource) -> Self {
+        Self {
+            source,
+            format: SampleFormat::default(),
+            sample_rate: 44100,
+            channels: 2,
+            fft_size: 2048,
+            bins: 64,
+            smoothing: 0.6,
+            beat_sensitivity: 1.5,
+        }
+    }
+
+    fn frame_bytes(&self) -> usize {
+        self.format.bytes() * usize::from(self.channels)
+    }
+}
+
+/// The audio as of this frame, kept up to date by the [`AudioPlugin`].
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct AudioSpectrum {
+    /// The level of each band, from 0 (silent) to 1 (full scale), lowest frequencies first.
+    pub bins: Vec<f32>,
+    /// The loudness of the analysis window, from 0 to 1.
+    pub rms: f32,
+    /// Whether a beat started this frame.
+    pub beat: bool,
+}
+
+/// Turns live PCM into an [`AudioSpectrum`] every frame, to build audio visualizers like glava.
+///
+/// Samples are read on a background thread. The spectrum is a windowed FFT over the most recent
+/// [`AudioInput::fft_size`] samples, and beats are onsets in the spectral flux that stand out
+/// from the last second. When the source goes quiet or fails, the spectrum falls back to silence
+/// and the source is opened again.
+pub struct AudioPlugin {
+    pub input: AudioInput,
+}
+
+impl AudioPlugin {
+    pub fn new(source: AudioSource) -> Self {
+        Self {
+            input: AudioInput::new(source),
+        }
+    }
+}
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.input.clone())
+            .init_resource::<AudioSpectrum>()
+            .init_resource::<AudioAnalysis>()
+            .add_systems(PreUpdate, (start_audio_source, analyze_audio).chain());
+    }
+}
+
+/// Samples read by the reader thread that haven't been analyzed yet.
+struct SampleQueue {
+    samples: Mutex<VecDeque<f32>>,
+    /// Cleared to stop the reader.
+    running: AtomicBool,
+}
+
+impl SampleQueue {
+    fn running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Resource, Default)]
+struct AudioAnalysis {
+    queue: Option<Arc<SampleQueue>>,
+    /// The most recent samples, oldest first.
+    window: Vec<f32>,
+    /// When the last samples arrived.
+    last_samples: Option<Instant>,
+    /// The magnitudes of the previous frame, for the spectral flux.
+    magnitudes: Vec<f32>,
+    /// Recent spectral flux values and when they were measured.
+    flux: VecDeque<(Instant, f32)>,
+    last_beat: Option<Instant>,
+}
+
+/// (Re)starts reading samples whenever the [`AudioInput`] changes.
+fn start_audio_source(input: Res<AudioInput>, mut analysis: ResMut<AudioAnalysis>) {
+    if !input.is_changed() {
+        return;
+    }
+
+    if let Some(queue) = analysis.queue.take() {
+        queue.running.store(false, Ordering::Relaxed);
+    }
+
+    let fft_size = input.fft_size.max(2).next_power_of_two();
+    *analysis = AudioAnalysis {
+        window: vec![0.0; fft_size],
+        ..Default::default()
+    };
+
+    if input.sample_rate == 0 || input.channels == 0 {
+        warn!("Not reading audio: the sample rate and channels have to be positive");
+        return;
+    }
+
+    let queue = Arc::new(SampleQueue {
+        samples: Mutex::new(VecDeque::with_capacity(fft_size)),
+        running: AtomicBool::new(true),
+    });
+    let input = input.clone();
+    let reader_queue = queue.clone();
+
+    let reader = thread::Builder::new()
+        .name("audio reader".into())
+        .spawn(move || read_audio(&input, fft_size, &reader_queue));
+
+    match reader {
+        Ok(_) => analysis.queue = Some(queue),
+        Err(err) => warn!("Could not start reading audio: {err}"),
+    }
+}
+
+/// Reads samples into `queue` until the input changes, opening the source again when it ends.
+///
+/// Opening a FIFO waits for its writer, so a reader stopped in the meantime only exits once
+/// there is one.
+fn read_audio(input: &AudioInput, fft_size: usize, queue: &SampleQueue) {
+    while queue.running() {
+        let result = File::open(match &input.source {
+            AudioSource::Fifo(path) | AudioSource::File(path) => path,
+        })
+        .and_then(|mut file| read_stream(&mut file, input, fft_size, queue));
+
+        match result {
+            // An empty file would otherwise be read over and over.
+            Ok(0) => thread::sleep(Duration::from_secs(1)),
+            Ok(_) if matches!(input.source, AudioSource::Fifo(_)) && queue.running() => {
+                info!("Audio source {:?} ended, opening it again", input.source);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                warn!("Audio source {:?} failed: {err}", input.source);
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+    }
+}
+
+/// Reads `stream` to its end, or until the input changes, returning how many frames were read.
+fn read_stream(
+    stream: &mut impl Read,
+    input: &AudioInput,
+    fft_size: usize,
+    queue: &SampleQueue,
+) -> io::Result<u64> {
+    let frame_bytes = input.frame_bytes();
+    let sample_bytes = input.format.bytes();
+    let started = Instant::now();
+    let mut read_frames = 0u64;
+
+    // About 10 ms of audio at a time.
+    let mut chunk = vec![0; frame_bytes * (input.sample_rate as usize / 100).max(1)];
+    let mut filled = 0;
+
+    loop {
+        let read = stream.read(&mut chunk[filled..])?;
+        if read == 0 {
+            return Ok(read_frames);
+        }
+        filled += read;
+
+        let whole = filled - filled % frame_bytes;
+        if !queue.running() {
+            return Ok(read_frames);
+        }
+
+        {
+            let mut samples = queue.samples.lock().unwrap();
+            for frame in chunk[..whole].chunks_exact(frame_bytes) {
+                let sum: f32 = frame
+                    .chunks_exact(sample_bytes)
+                    .map(|sample| input.format.decode(sample))
+                    .sum();
+                samples.push_back(sum / f32::from(input.channels));
+            }
+
+            // Only the latest window is ever analyzed.
+            let excess = samples.len().saturating_sub(fft_size);
+            samples.drain(..excess);
+        }
+
+        chunk.copy_within(whole..filled, 0);
+        filled -= whole;
+        read_frames += (whole / frame_bytes) as u64;
+
+        // Files are read as fast as the disk allows, so play them back in real time.
+        if matches!(input.source, AudioSource::File(_)) {
+            let due = Duration::from_secs_f64(read_frames as f64 / f64::from(input.sample_rate));
+            if let Some(ahead) = due.checked_sub(started.elapsed()) {
+                thread::sleep(ahead);
+            }
+        }
+    }
+}
+
+/// Analyzes the latest samples into the [`AudioSpectrum`].
+fn analyze_audio(
+    input: Res<AudioInput>,
+    time: Res<Time<Real>>,
+    mut analysis: ResMut<AudioAnalysis>,
+    mut spectrum: ResMut<AudioSpectrum>,
+) {
+    let Some(now) = time.last_update() else {
+        return;
+    };
+    let analysis = &mut *analysis;
+    let Some(queue) = &analysis.queue else {
+        return;
+    };
+
+    let samples: Vec<f32> = queue.samples.lock().unwrap().drain(..).collect();
+    let fft_size = analysis.window.len();
+
+    if !samples.is_empty() {
+        analysis.last_samples = Some(now);
+        let kept = fft_size.saturating_sub(samples.len());
+        analysis.window.drain(..fft_size - kept);
+        analysis
+            .window
+            .extend_from_slice(&samples[samples.len() - (fft_size - kept)..]);
+    } else if analysis
+        .last_samples
+        .is_none_or(|at| now.duration_since(at) > SILENCE_AFTER)
+    {
+        analysis.window.fill(0.0);
+    }
+
+    let rms = (analysis.window.iter().map(|s| s * s).sum::<f32>() / fft_size as f32).sqrt();
+    let magnitudes = magnitudes(&analysis.window);
+    let bins = band_levels(&magnitudes, input.bins, input.sample_rate, fft_size);
+
+    // Spectral flux: how much louder the spectrum got since the last frame.
+    let flux: f32 = magnitudes
+        .iter()
+        .zip(&analysis.magnitudes)
+        .map(|(new, old)| (new - old).max(0.0))
+        .sum();
+    analysis.magnitudes = magnitudes;
+
+    analysis
+        .flux
+        .retain(|(at, _)| now.duration_since(*at) <= BEAT_HISTORY);
+    let average =
+        analysis.flux.iter().map(|(_, flux)| flux).sum::<f32>() / analysis.flux.len().max(1) as f32;
+    analysis.flux.push_back((now, flux));
+
+    let beat = flux > average * input.beat_sensitivity
+        && rms > 0.01
+        && analysis
+            .last_beat
+            .is_none_or(|at| now.duration_since(at) >= MIN_BEAT_INTERVAL);
+    if beat {
+        analysis.last_beat = Some(now);
+    }
+
+    let keep = input.smoothing.clamp(0.0, 1.0);
+    let smooth = |old: f32, new: f32| old * keep + new * (1.0 - keep);
+
+    let mut smoothed = spectrum.bins.clone();
+    smoothed.resize(bins.len(), 0.0);
+    for (old, new) in smoothed.iter_mut().zip(bins) {
+        *old = smooth(*old, new);
+    }
+
+    spectrum.set_if_neq(AudioSpectrum {
+        bins: smoothed,
+        rms: smooth(spectrum.rms, rms),
+        beat,
+    });
+}
+
+/// The magnitudes of the positive frequencies of the Hann windowed `samples`, normalized so a
+/// full scale sine peaks at 1.
+fn magnitudes(samples: &[f32]) -> Vec<f32> {
+    let size = samples.len();
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(n, sample)| sample * 0.5 * (1.0 - (2.0 * PI * n as f32 / size as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; size];
+
+    fft(&mut re, &mut im);
+
+    // The Hann window halves the amplitude, and a real sine splits over two bins.
+    let scale = 4.0 / size as f32;
+    re.iter()
+        .zip(&im)
+        .take(size / 2)
+        .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+        .collect()
+}
+
+/// An in-place iterative radix-2 FFT. The length has to be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let size = re.len();
+
+    let bits = size.trailing_zeros();
+    for i in 0..size {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= size {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..size).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let (tr, ti) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Groups `magnitudes` into `bands` logarithmically spaced bands from 20 Hz to the Nyquist
+/// frequency, each from 0 at [`FLOOR_DB`] to 1 at full scale.
+fn band_levels(magnitudes: &[f32], bands: usize, sample_rate: u32, fft_size: usize) -> Vec<f32> {
+    let hz_per_bin = sample_rate as f32 / fft_size as f32;
+    let (low, high) = (20.0f32, sample_rate as f32 / 2.0);
+
+    (0..bands)
+        .map(|band| {
+            let edge = |band: usize| low * (high / low).powf(band as f32 / bands as f32);
+            let first = (edge(band) / hz_per_bin) as usize;
+            let last = ((edge(band + 1) / hz_per_bin) as usize).max(first + 1);
+
+            let peak = magnitudes
+                .get(first.min(magnitudes.len())..last.min(magnitudes.len()))
+                .unwrap_or_default()
+                .iter()
+                .copied()
+                .fold(0.0, f32::max);
+
+            let db = 20.0 * peak.max(f32::MIN_POSITIVE).log10();
+            (1.0 - db / FLOOR_DB).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{system::RunSystemOnce, world::World};
+
+    use super::*;
+
+    fn sine(size: usize, cycles: f32, amplitude: f32) -> Vec<f32> {
+        (0..size)
+            .map(|n| amplitude * (2.0 * PI * cycles * n as f32 / size as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn fft_matches_dft() {
+        let samples: Vec<f32> = (0..16).map(|n| ((n * 7 % 5) as f32 - 2.0) / 3.0).collect();
+        let (mut re, mut im) = (samples.clone(), vec![0.0; 16]);
+        fft(&mut re, &mut im);
+
+        for k in 0..16 {
+            let (mut dft_re, mut dft_im) = (0.0, 0.0);
+            for (n, sample) in samples.iter().enumerate() {
+                let angle = -2.0 * PI * (k * n) as f32 / 16.0;
+                dft_re += sample * angle.cos();
+                dft_im += sample * angle.sin();
+            }
+            assert!(
+                (re[k] - dft_re).abs() < 1e-4,
+                "bin {k}: {} != {dft_re}",
+                re[k]
+            );
+            assert!(
+                (im[k] - dft_im).abs() < 1e-4,
+                "bin {k}: {} != {dft_im}",
+                im[k]
+            );
+        }
+    }
+
+    #[test]
+    fn fft_of_an_impulse_is_flat() {
+        let (mut re, mut im) = (vec![0.0; 8], vec![0.0; 8]);
+        re[0] = 1.0;
+        fft(&mut re, &mut im);
+
+        assert!(re.iter().all(|re| (re - 1.0).abs() < 1e-6));
+        assert!(im.iter().all(|im| im.abs() < 1e-6));
+    }
+
+    #[test]
+    fn full_scale_sine_peaks_at_one() {
+        let magnitudes = magnitudes(&sine(1024, 32.0, 1.0));
+
+        assert_eq!(magnitudes.len(), 512);
+        assert!((magnitudes[32] - 1.0).abs() < 1e-3, "{}", magnitudes[32]);
+        // The Hann window leaks into the neighbours only.
+        assert!((magnitudes[31] - 0.5).abs() < 1e-3);
+        assert!(magnitudes[40].abs() < 1e-3);
+    }
+
+    #[test]
+    fn bands() {
+        // 8 kHz sample rate, 16 samples: 500 Hz per bin, bands from 20 Hz to 4 kHz.
+        let mut magnitudes = vec![0.0; 8];
+        magnitudes[4] = 1.0;
+        magnitudes[1] = 0.1;
+
+        let levels = band_levels(&magnitudes, 4, 8000, 16);
+        // Band edges at 20, 75, 283, 1064 and 4000 Hz.
+        assert_eq!(levels.len(), 4);
+        assert_eq!(levels[0], 0.0);
+        assert_eq!(levels[1], 0.0);
+        // -20 dB between the floor and full scale.
+        assert!((levels[2] - (1.0 - 20.0 / 70.0)).abs() < 1e-5);
+        assert_eq!(levels[3], 1.0);
+
+        assert_eq!(band_levels(&[], 3, 8000, 16), [0.0; 3]);
+    }
+
+    /// Runs [`analyze_audio`] on one chunk of samples, `elapsed` after the previous one.
+    fn analyze(world: &mut World, elapsed: Duration, samples: &[f32]) -> AudioSpectrum {
+        let now = world.resource::<Time<Real>>().last_update().unwrap() + elapsed;
+        world.resource_mut::<Time<Real>>().update_with_instant(now);
+        let analysis = world.resource::<AudioAnalysis>();
+        let queue = analysis.queue.as_ref().unwrap();
+        queue.samples.lock().unwrap().extend(samples);
+
+        world.run_system_once(analyze_audio).unwrap();
+        world.resource::<AudioSpectrum>().clone()
+    }
+
+    fn analysis_world() -> World {
+        let mut input = AudioInput::new(AudioSource::File("/dev/null".into()));
+        input.fft_size = 1024;
+        input.smoothing = 0.0;
+
+        let mut time = Time::<Real>::new(Instant::now());
+        time.update();
+
+        let mut world = World::new();
+        world.insert_resource(input);
+        world.insert_resource(time);
+        world.insert_resource(AudioSpectrum::default());
+        world.insert_resource(AudioAnalysis {
+            queue: Some(Arc::new(SampleQueue {
+                samples: Mutex::default(),
+                running: AtomicBool::new(true),
+            })),
+            window: vec![0.0; 1024],
+            ..Default::default()
+        });
+        world
+    }
+
+    #[test]
+    fn beats_are_onsets() {
+        let mut world = analysis_world();
+        let frame = Duration::from_millis(20);
+
+        for _ in 0..10 {
+            assert!(!analyze(&mut world, frame, &[0.0; 1024]).beat);
+        }
+
+        let loud = analyze(&mut world, frame, &sine(1024, 32.0, 0.8));
+        assert!(loud.beat);
+        assert!((loud.rms - 0.8 / 2f32.sqrt()).abs() < 1e-3);
+
+        // Another onset right away is too soon.
+        assert!(!analyze(&mut world, frame, &sine(1024, 64.0, 1.0)).beat);
+        // Sound that keeps going isn't an onset.
+        assert!(
+            !analyze(
+                &mut world,
+                Duration::from_millis(200),
+                &sine(1024, 64.0, 1.0)
+            )
+            .beat
+        );
+        // A new sound after the minimum interval is.
+        assert!(analyze(&mut world, frame, &sine(1024, 128.0, 1.0)).beat);
+    }
+
+    #[test]
+    fn quiet_onsets_are_not_beats() {
+        let mut world = analysis_world();
+
+        assert!(!analyze(&mut world, Duration::from_millis(20), &[0.0; 1024]).beat);
+        assert!(
+            !analyze(
+                &mut world,
+                Duration::from_millis(20),
+                &sine(1024, 32.0, 0.005)
+            )
+            .beat
+        );
+    }
+
+    #[test]
+    fn falls_silent_without_samples() {
+        let mut world = analysis_world();
+
+        let loud = analyze(
+            &mut world,
+            Duration::from_millis(20),
+            &sine(1024, 32.0, 1.0),
+        );
+        assert!(loud.rms > 0.5);
+
+        // Briefly missing samples keep the last window.
+        let held = analyze(&mut world, Duration::from_millis(100), &[]);
+        assert_eq!(held.rms, loud.rms);
+
+        let silent = analyze(&mut world, SILENCE_AFTER + Duration::from_millis(100), &[]);
+        assert_eq!(silent.rms, 0.0);
+        assert!(silent.bins.iter().all(|&level| level == 0.0));
+    }
+}
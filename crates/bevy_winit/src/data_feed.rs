@@ -0,0 +1,976 @@
+use core::time::Duration;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    net::{SocketAddr, TcpListener},
+    os::unix::{fs::FileTypeExt, net::UnixListener},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+
+use bevy::{ecs::reflect::AppTypeRegistry, reflect::TypeRegistry, utils::Instant};
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    event::{Event, EventWriter},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+    world::{Mut, World},
+};
+use serde_json::{Deserializer, Value};
+use tracing::{info, warn};
+
+use crate::{scene_params::SceneParam, stoppable_read::StoppableRead};
+
+/// How often watched files are checked for changes.
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often readers check whether they should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a socket that can't be bound is tried again.
+const BIND_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a client pushing to a socket may take to send its JSON.
+const SOCKET_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a [`DataFeed`] gets its JSON from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedSource {
+    /// A file, read again whenever it is modified.
+    File(PathBuf),
+    /// A Unix socket the wallpaper listens on. Every client can push one or more JSON documents,
+    /// e.g. `echo '{"temp": 21}' | socat - UNIX-CONNECT:/run/user/1000/weather.sock`.
+    UnixSocket(PathBuf),
+    /// A TCP socket the wallpaper listens on, usually on localhost, that works like
+    /// [`FeedSource::UnixSocket`].
+    Tcp(SocketAddr),
+    /// A shell command run with `sh -c` every `interval`, whose output is the JSON document.
+    Command { command: String, interval: Duration },
+}
+
+/// A named source of JSON documents, e.g. the weather or the state of a CI pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFeed {
+    /// What [`FeedBinding`]s and [`FeedValues`] refer to the feed by.
+    pub name: String,
+    pub source: FeedSource,
+    /// How long a document counts as current. Bindings fall back to their defaults once the
+    /// feed has been quiet for longer.
+    pub stale_after: Option<Duration>,
+}
+
+impl DataFeed {
+    pub fn new(name: impl Into<String>, source: FeedSource) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            stale_after: None,
+        }
+    }
+}
+
+/// Binds a value of a feed to a field of a reflected resource.
+///
+/// The resource has to derive `Reflect`, be registered with `App::register_type` and have
+/// `#[reflect(Resource)]`. The value is deserialized into the field the way `bevy_reflect`
+/// deserializes that field's type, so a number binds to an `f32` and `{"Srgba": {...}}` to a
+/// `Color`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedBinding {
+    pub feed: String,
+    /// A JSON pointer into the feed's document, e.g. `/current/temperature`.
+    pub pointer: String,
+    /// The type path of the resource, e.g. `my_wallpaper::Weather`, or its short path.
+    pub resource: String,
+    /// A reflection path into the resource, e.g. `temperature` or `sky.cloud_cover`.
+    pub field: String,
+    /// What the field is set to while the value is missing or the feed is stale. `Null` leaves
+    /// the field alone.
+    pub default: Value,
+    /// How long `f32` and `f64` fields take to move to a new value. Other fields change at once.
+    pub interpolation: Option<Duration>,
+}
+
+impl FeedBinding {
+    pub fn new(
+        feed: impl Into<String>,
+        pointer: impl Into<String>,
+        resource: impl Into<String>,
+        field: impl Into<String>,
+    ) -> Self {
+        Self {
+            feed: feed.into(),
+            pointer: pointer.into(),
+            resource: resource.into(),
+            field: field.into(),
+            default: Value::Null,
+            interpolation: None,
+        }
+    }
+
+    fn param(&self) -> SceneParam<'_> {
+        SceneParam {
+            resource: &self.resource,
+            field: &self.field,
+        }
+    }
+}
+
+/// The feeds the [`DataFeedPlugin`] reads and the bindings it keeps up to date, which can be
+/// changed while it runs.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct DataFeeds {
+    pub feeds: Vec<DataFeed>,
+    pub bindings: Vec<FeedBinding>,
+}
+
+/// The latest document of every feed.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct FeedValues {
+    values: HashMap<String, FeedValue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedValue {
+    pub document: Value,
+    pub received: Instant,
+    /// Whether the feed has been quiet for longer than its [`DataFeed::stale_after`].
+    pub stale: bool,
+}
+
+impl FeedValues {
+    pub fn get(&self, feed: &str) -> Option<&FeedValue> {
+        self.values.get(feed)
+    }
+
+    /// The value at `pointer` in the document of `feed`, unless the feed is stale.
+    pub fn pointer(&self, feed: &str, pointer: &str) -> Option<&Value> {
+        self.values
+            .get(feed)
+            .filter(|value| !value.stale)?
+            .document
+            .pointer(pointer)
+    }
+}
+
+/// Sent when a feed delivers a new document.
+#[derive(Event, Debug, Clone)]
+pub struct FeedUpdated {
+    pub feed: String,
+}
+
+/// Reads JSON from files, sockets and commands, and binds values from it to scene parameters,
+/// so weather, CI status, calendars or tickers need no integration of their own.
+///
+/// Every feed is read on a background thread. New documents land in [`FeedValues`] and are
+/// announced with [`FeedUpdated`], and the [`FeedBinding`]s copy values out of them into
+/// reflected resources.
+#[derive(Default)]
+pub struct DataFeedPlugin {
+    pub feeds: DataFeeds,
+}
+
+impl Plugin for DataFeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.feeds.clone())
+            .init_resource::<FeedValues>()
+            .init_resource::<FeedReaders>()
+            .init_resource::<BindingStates>()
+            .add_event::<FeedUpdated>()
+            .add_systems(
+                PreUpdate,
+                (start_feeds, receive_feeds, apply_feed_bindings).chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct FeedReaders {
+    /// Documents read by the reader threads. Only locked to make the resource `Sync`.
+    documents: Mutex<Receiver<(String, Value)>>,
+    sender: Sender<(String, Value)>,
+    /// The running readers, so changing the bindings or other feeds keeps them running.
+    readers: Vec<FeedReader>,
+}
+
+impl Default for FeedReaders {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            documents: Mutex::new(receiver),
+            sender,
+            readers: Vec::new(),
+        }
+    }
+}
+
+struct FeedReader {
+    feed: DataFeed,
+    /// Set to stop the reader.
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+/// (Re)starts the readers of the feeds that were added or changed.
+fn start_feeds(feeds: Res<DataFeeds>, mut readers: ResMut<FeedReaders>) {
+    if !feeds.is_changed() {
+        return;
+    }
+
+    let readers = &mut *readers;
+    let (kept, mut stopped): (Vec<_>, Vec<_>) = readers
+        .readers
+        .drain(..)
+        .partition(|reader| feeds.feeds.contains(&reader.feed));
+    readers.readers = kept;
+
+    // Stopped readers wind down off the main thread, as a command or client may take a moment
+    // to notice.
+    for reader in &stopped {
+        reader.stop.store(true, Ordering::Relaxed);
+    }
+
+    for feed in &feeds.feeds {
+        if readers.readers.iter().any(|reader| reader.feed == *feed) {
+            continue;
+        }
+
+        // A changed feed waits for its previous reader, which may still hold its socket.
+        let previous = stopped
+            .iter()
+            .position(|reader| reader.feed.name == feed.name)
+            .map(|index| stopped.swap_remove(index));
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (reader_feed, sender, reader_stop) =
+            (feed.clone(), readers.sender.clone(), stop.clone());
+        let reader = thread::Builder::new()
+            .name(format!("{} feed reader", feed.name))
+            .spawn(move || {
+                if let Some(previous) = previous {
+                    join_reader(previous);
+                }
+                read_feed(&reader_feed, &sender, &reader_stop);
+            });
+
+        match reader {
+            Ok(thread) => readers.readers.push(FeedReader {
+                feed: feed.clone(),
+                stop,
+                thread,
+            }),
+            Err(err) => warn!("Could not start reading feed {:?}: {err}", feed.name),
+        }
+    }
+
+    // Readers of removed feeds are still joined, so their panics are reported.
+    if !stopped.is_empty() {
+        let joined = thread::Builder::new()
+            .name("feed reader joiner".into())
+            .spawn(move || stopped.into_iter().for_each(join_reader));
+
+        if let Err(err) = joined {
+            warn!("Could not wait for stopped feed readers: {err}");
+        }
+    }
+}
+
+fn join_reader(reader: FeedReader) {
+    if reader.thread.join().is_err() {
+        warn!("The reader of feed {:?} panicked", reader.feed.name);
+    }
+}
+
+/// Moves new documents into [`FeedValues`] and marks quiet feeds as stale.
+fn receive_feeds(
+    feeds: Res<DataFeeds>,
+    mut readers: ResMut<FeedReaders>,
+    mut values: ResMut<FeedValues>,
+    mut updated: EventWriter<FeedUpdated>,
+) {
+    let now = Instant::now();
+
+    for (feed, document) in readers.documents.get_mut().unwrap().try_iter() {
+        values.values.insert(
+            feed.clone(),
+            FeedValue {
+                document,
+                received: now,
+                stale: false,
+            },
+        );
+        updated.send(FeedUpdated { feed });
+    }
+
+    for feed in &feeds.feeds {
+        let Some(stale_after) = feed.stale_after else {
+            continue;
+        };
+
+        if let Some(value) = values.bypass_change_detection().values.get_mut(&feed.name) {
+            let stale = now.duration_since(value.received) > stale_after;
+            if value.stale != stale {
+                value.stale = stale;
+                values.set_changed();
+            }
+        }
+    }
+}
+
+/// Sends every document of `feed` until `stop` is set.
+fn read_feed(feed: &DataFeed, documents: &Sender<(String, Value)>, stop: &AtomicBool) {
+    let send = |document: Value| documents.send((feed.name.clone(), document)).is_ok();
+
+    let result = match &feed.source {
+        FeedSource::File(path) => {
+            let (mut modified, mut next_poll) = (None, Instant::now());
+
+            while !stop.load(Ordering::Relaxed) {
+                if next_poll <= Instant::now() {
+                    next_poll = Instant::now() + FILE_POLL_INTERVAL;
+                    let current = fs::metadata(path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok();
+
+                    if current.is_some() && current != modified {
+                        modified = current;
+                        match fs::read(path).and_then(parse) {
+                            Ok(document) => {
+                                if !send(document) {
+                                    return;
+                                }
+                            }
+                            Err(err) => warn!("Could not read feed {:?}: {err}", feed.name),
+                        }
+                    }
+                }
+
+                thread::sleep(STOP_POLL_INTERVAL);
+            }
+
+            Ok(())
+        }
+        FeedSource::UnixSocket(path) => {
+            // A socket left behind by a previous run would make binding fail.
+            remove_socket(path);
+
+            let Some(listener) = bind_until_stopped(feed, stop, || UnixListener::bind(path)) else {
+                return;
+            };
+            let result = listener.set_nonblocking(true).and_then(|()| {
+                accept_loop(feed, stop, &send, || {
+                    let (stream, _) = listener.accept()?;
+                    stream.set_nonblocking(false)?;
+                    Ok(StoppableRead::new(stream, stop).with_timeout(SOCKET_READ_TIMEOUT))
+                })
+            });
+
+            remove_socket(path);
+            result
+        }
+        FeedSource::Tcp(address) => {
+            let Some(listener) = bind_until_stopped(feed, stop, || TcpListener::bind(address))
+            else {
+                return;
+            };
+            listener.set_nonblocking(true).and_then(|()| {
+                accept_loop(feed, stop, &send, || {
+                    let (stream, _) = listener.accept()?;
+                    stream.set_nonblocking(false)?;
+                    Ok(StoppableRead::new(stream, stop).with_timeout(SOCKET_READ_TIMEOUT))
+                })
+            })
+        }
+        FeedSource::Command { command, interval } => {
+            let mut next_run = Instant::now();
+
+            while !stop.load(Ordering::Relaxed) {
+                if next_run <= Instant::now() {
+                    next_run = Instant::now() + *interval;
+
+                    match run_command(command, stop) {
+                        _ if stop.load(Ordering::Relaxed) => break,
+                        Ok(document) => {
+                            if !send(document) {
+                                return;
+                            }
+                        }
+                        Err(err) => warn!("Could not read feed {:?}: {err}", feed.name),
+                    }
+                }
+
+                thread::sleep(STOP_POLL_INTERVAL);
+            }
+
+            Ok(())
+        }
+    };
+
+    if let Err(err) = result {
+        warn!("Feed {:?} stopped: {err}", feed.name);
+    }
+}
+
+/// Binds a listener with `bind`, trying again while it fails, until `stop` is set.
+///
+/// The address may still be held by a reader that is winding down, or by another program.
+fn bind_until_stopped<L>(
+    feed: &DataFeed,
+    stop: &AtomicBool,
+    bind: impl Fn() -> io::Result<L>,
+) -> Option<L> {
+    let (mut next_try, mut warned) = (Instant::now(), false);
+
+    while !stop.load(Ordering::Relaxed) {
+        if next_try <= Instant::now() {
+            match bind() {
+                Ok(listener) => return Some(listener),
+                Err(err) if !warned => {
+                    warn!("Feed {:?} can't listen yet, retrying: {err}", feed.name);
+                    warned = true;
+                }
+                Err(_) => {}
+            }
+            next_try = Instant::now() + BIND_RETRY_INTERVAL;
+        }
+
+        thread::sleep(STOP_POLL_INTERVAL);
+    }
+
+    None
+}
+
+/// Removes the Unix socket at `path`, leaving anything else there alone.
+fn remove_socket(path: &Path) {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Reads the documents pushed by every client `accept` returns until `stop` is set.
+fn accept_loop<S: Read>(
+    feed: &DataFeed,
+    stop: &AtomicBool,
+    send: &dyn Fn(Value) -> bool,
+    accept: impl Fn() -> io::Result<S>,
+) -> io::Result<()> {
+    info!("Feed {:?} is listening on {:?}", feed.name, feed.source);
+
+    while !stop.load(Ordering::Relaxed) {
+        let stream = match accept() {
+            Ok(stream) => stream,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(STOP_POLL_INTERVAL);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Clients are served one at a time, which is plenty for scripts pushing updates.
+        for document in Deserializer::from_reader(stream).into_iter::<Value>() {
+            match document {
+                Ok(document) => {
+                    if !send(document) {
+                        return Ok(());
+                    }
+                }
+                // The client was cut off.
+                Err(_) if stop.load(Ordering::Relaxed) => return Ok(()),
+                Err(err) => {
+                    warn!(
+                        "Ignoring malformed JSON pushed to feed {:?}: {err}",
+                        feed.name
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `command` and parses its output, killing it if `stop` is set before it is done.
+fn run_command(command: &str, stop: &AtomicBool) -> io::Result<Value> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let mut output = Vec::new();
+    let read = match child.stdout.take() {
+        Some(stdout) => StoppableRead::new(stdout, stop).read_to_end(&mut output),
+        None => Ok(0),
+    };
+
+    if read.is_err() || stop.load(Ordering::Relaxed) {
+        // The command may already have exited, which is fine.
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    read?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("the command {status}")));
+    }
+
+    parse(output)
+}
+
+fn parse(json: Vec<u8>) -> io::Result<Value> {
+    serde_json::from_slice(&json).map_err(io::Error::from)
+}
+
+/// What each binding last did, by its index in [`DataFeeds::bindings`].
+#[derive(Resource, Default)]
+struct BindingStates {
+    states: Vec<BindingState>,
+    /// The bindings the states belong to.
+    bindings: Vec<FeedBinding>,
+}
+
+#[derive(Default)]
+struct BindingState {
+    /// The value the field was last set to, or is moving towards.
+    applied: Option<Value>,
+    tween: Option<Tween>,
+}
+
+/// A number field moving to a new value.
+struct Tween {
+    from: f64,
+    to: f64,
+    started: Instant,
+    duration: Duration,
+}
+
+/// Copies the bound values into their fields.
+fn apply_feed_bindings(world: &mut World) {
+    let Some(registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+        return;
+    };
+    let registry = registry.read();
+
+    world.resource_scope(|world, mut states: Mut<BindingStates>| {
+        let feeds = world.resource::<DataFeeds>();
+        if states.bindings != feeds.bindings {
+            states.bindings = feeds.bindings.clone();
+            states.states = feeds
+                .bindings
+                .iter()
+                .map(|_| BindingState::default())
+                .collect();
+        }
+
+        let now = Instant::now();
+        let states = &mut *states;
+
+        for (binding, state) in states.bindings.iter().zip(&mut states.states) {
+            let values = world.resource::<FeedValues>();
+            let target = values
+                .pointer(&binding.feed, &binding.pointer)
+                .unwrap_or(&binding.default)
+                .clone();
+
+            if target.is_null() {
+                continue;
+            }
+
+            if state.applied.as_ref() != Some(&target) {
+                // A value that can't be bound isn't tried again until it changes.
+                if let Err(err) = bind(world, &registry, binding, state, &target, now) {
+                    warn!(
+                        "Could not bind {}{} to {}.{}: {err}",
+                        binding.feed, binding.pointer, binding.resource, binding.field
+                    );
+                }
+                state.applied = Some(target);
+            } else if state.tween.is_some()
+                && let Err(err) = step_tween(world, &registry, binding, state, now)
+            {
+                warn!(
+                    "Could not update {}.{}: {err}",
+                    binding.resource, binding.field
+                );
+                state.tween = None;
+            }
+        }
+    });
+}
+
+/// Sets the field of `binding` to `value`, or starts moving it there.
+fn bind(
+    world: &mut World,
+    registry: &TypeRegistry,
+    binding: &FeedBinding,
+    state: &mut BindingState,
+    value: &Value,
+    now: Instant,
+) -> Result<(), String> {
+    let param = binding.param();
+
+    if let Some(duration) = binding.interpolation
+        && let (Some(from), Some(to)) = (param.number(world, registry), value.as_f64())
+    {
+        state.tween = Some(Tween {
+            from,
+            to,
+            started: now,
+            duration,
+        });
+        return Ok(());
+    }
+
+    state.tween = None;
+    param.set(world, registry, value)
+}
+
+/// Moves a number field along its tween.
+fn step_tween(
+    world: &mut World,
+    registry: &TypeRegistry,
+    binding: &FeedBinding,
+    state: &mut BindingState,
+    now: Instant,
+) -> Result<(), String> {
+    let Some(tween) = &state.tween else {
+        return Ok(());
+    };
+
+    let progress = if tween.duration.is_zero() {
+        1.0
+    } else {
+        (now.duration_since(tween.started).as_secs_f64() / tween.duration.as_secs_f64()).min(1.0)
+    };
+    let value = tween.from + (tween.to - tween.from) * progress;
+    if progress >= 1.0 {
+        state.tween = None;
+    }
+
+    binding.param().set_number(world, registry, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, os::unix::net::UnixStream, process};
+
+    use bevy::{ecs::reflect::ReflectResource, reflect::Reflect};
+    use bevy_ecs::{event::Events, system::RunSystemOnce};
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Resource, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Resource)]
+    struct Weather {
+        temperature: f32,
+        sky: String,
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("data-feed-{}-{name}", process::id()))
+    }
+
+    fn world(feeds: DataFeeds) -> World {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Weather>();
+
+        let mut world = World::new();
+        world.insert_resource(registry);
+        world.insert_resource(feeds);
+        world.init_resource::<Weather>();
+        world.init_resource::<FeedValues>();
+        world.init_resource::<FeedReaders>();
+        world.init_resource::<BindingStates>();
+        world.init_resource::<Events<FeedUpdated>>();
+        world
+    }
+
+    /// Sets `stop` after a moment and checks `reader` returns well before a client or command
+    /// would time out on its own.
+    fn stops_soon(stop: &AtomicBool, reader: JoinHandle<()>) {
+        thread::sleep(STOP_POLL_INTERVAL * 3);
+        stop.store(true, Ordering::Relaxed);
+
+        let deadline = Instant::now() + SOCKET_READ_TIMEOUT / 2;
+        while !reader.is_finished() {
+            assert!(Instant::now() < deadline, "the reader didn't stop");
+            thread::sleep(STOP_POLL_INTERVAL);
+        }
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn socket_path_keeps_other_files() {
+        let path = temp_path("not-a-socket");
+        fs::write(&path, "keep me").unwrap();
+
+        let feed = DataFeed::new("feed", FeedSource::UnixSocket(path.clone()));
+        let (sender, _receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stop = stop.clone();
+            thread::spawn(move || read_feed(&feed, &sender, &stop))
+        };
+
+        thread::sleep(STOP_POLL_INTERVAL * 3);
+        stop.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep me");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn restarts_only_changed_feeds() {
+        let path = temp_path("restart.sock");
+        let command = DataFeed::new(
+            "command",
+            FeedSource::Command {
+                command: "echo 1".into(),
+                interval: Duration::from_secs(3600),
+            },
+        );
+        let socket = DataFeed::new("socket", FeedSource::UnixSocket(path.clone()));
+
+        let mut world = World::new();
+        world.init_resource::<FeedReaders>();
+        world.insert_resource(DataFeeds {
+            feeds: vec![command.clone(), socket],
+            bindings: Vec::new(),
+        });
+        world.run_system_once(start_feeds).unwrap();
+
+        let thread_of = |world: &World, name: &str| {
+            let readers = &world.resource::<FeedReaders>().readers;
+            let reader = readers.iter().find(|reader| reader.feed.name == name);
+            reader.unwrap().thread.thread().id()
+        };
+        let (command_thread, socket_thread) =
+            (thread_of(&world, "command"), thread_of(&world, "socket"));
+
+        // Only the socket changes, and it is bound again at the same path.
+        world.resource_mut::<DataFeeds>().feeds[1].stale_after = Some(Duration::from_secs(1));
+        world.run_system_once(start_feeds).unwrap();
+        assert_eq!(thread_of(&world, "command"), command_thread);
+        assert_ne!(thread_of(&world, "socket"), socket_thread);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut stream = loop {
+            match UnixStream::connect(&path) {
+                Ok(stream) => break stream,
+                Err(_) if Instant::now() < deadline => thread::sleep(STOP_POLL_INTERVAL),
+                Err(err) => panic!("the socket was not bound again: {err}"),
+            }
+        };
+        stream.write_all(br#"{"temp": 21}"#).unwrap();
+        drop(stream);
+
+        let readers = world.resource::<FeedReaders>();
+        let documents = readers.documents.lock().unwrap();
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            received.push(documents.recv_timeout(Duration::from_secs(5)).unwrap());
+        }
+        drop(documents);
+        received.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            received,
+            [
+                ("command".into(), Value::from(1)),
+                ("socket".into(), serde_json::json!({"temp": 21})),
+            ]
+        );
+
+        world.resource_mut::<DataFeeds>().feeds = vec![command];
+        world.run_system_once(start_feeds).unwrap();
+        assert_eq!(world.resource::<FeedReaders>().readers.len(), 1);
+        // The stopped reader removes its socket as it winds down.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while path.exists() && Instant::now() < deadline {
+            thread::sleep(STOP_POLL_INTERVAL);
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn stops_a_running_command() {
+        let feed = DataFeed::new(
+            "slow",
+            FeedSource::Command {
+                command: "sleep 60; echo 1".into(),
+                interval: Duration::from_secs(3600),
+            },
+        );
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stop = stop.clone();
+            thread::spawn(move || read_feed(&feed, &sender, &stop))
+        };
+
+        stops_soon(&stop, reader);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn cuts_off_quiet_clients() {
+        let path = temp_path("quiet.sock");
+        let feed = DataFeed::new("quiet", FeedSource::UnixSocket(path.clone()));
+        let (sender, _receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stop = stop.clone();
+            thread::spawn(move || read_feed(&feed, &sender, &stop))
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        // Connected, but never sends anything.
+        let _client = loop {
+            match UnixStream::connect(&path) {
+                Ok(stream) => break stream,
+                Err(_) if Instant::now() < deadline => thread::sleep(STOP_POLL_INTERVAL),
+                Err(err) => panic!("the socket was not bound: {err}"),
+            }
+        };
+
+        stops_soon(&stop, reader);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn interpolates_numbers() {
+        let mut world = world(DataFeeds::default());
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let binding = FeedBinding {
+            interpolation: Some(Duration::from_secs(2)),
+            ..FeedBinding::new("weather", "/temp", "Weather", "temperature")
+        };
+        let mut state = BindingState::default();
+        let start = Instant::now();
+
+        bind(
+            &mut world,
+            &registry,
+            &binding,
+            &mut state,
+            &json!(10.0),
+            start,
+        )
+        .unwrap();
+        // Moving starts with the next step.
+        assert_eq!(world.resource::<Weather>().temperature, 0.0);
+
+        let step = |world: &mut World, state: &mut BindingState, after: f32| {
+            let now = start + Duration::from_secs_f32(after);
+            step_tween(world, &registry, &binding, state, now).unwrap();
+            world.resource::<Weather>().temperature
+        };
+        assert_eq!(step(&mut world, &mut state, 0.5), 2.5);
+        assert_eq!(step(&mut world, &mut state, 1.0), 5.0);
+        assert_eq!(step(&mut world, &mut state, 3.0), 10.0);
+        assert!(state.tween.is_none());
+
+        // Moving back down starts from where the field is.
+        bind(
+            &mut world,
+            &registry,
+            &binding,
+            &mut state,
+            &json!(6.0),
+            start,
+        )
+        .unwrap();
+        assert_eq!(step(&mut world, &mut state, 1.0), 8.0);
+
+        // Other fields change at once, even with an interpolation.
+        let label = FeedBinding {
+            interpolation: Some(Duration::from_secs(2)),
+            ..FeedBinding::new("weather", "/sky", "Weather", "sky")
+        };
+        let mut state = BindingState::default();
+        bind(
+            &mut world,
+            &registry,
+            &label,
+            &mut state,
+            &json!("clear"),
+            start,
+        )
+        .unwrap();
+        assert!(state.tween.is_none());
+        assert_eq!(world.resource::<Weather>().sky, "clear");
+    }
+
+    #[test]
+    fn stale_values_fall_back_to_defaults() {
+        let mut world = world(DataFeeds {
+            feeds: vec![DataFeed {
+                stale_after: Some(Duration::from_secs(60)),
+                ..DataFeed::new("weather", FeedSource::File("weather.json".into()))
+            }],
+            bindings: vec![
+                FeedBinding {
+                    default: json!(-1.0),
+                    ..FeedBinding::new("weather", "/temp", "Weather", "temperature")
+                },
+                // A `Null` default leaves the field alone.
+                FeedBinding::new("weather", "/sky", "Weather", "sky"),
+            ],
+        });
+
+        // Before the feed delivers anything, the defaults apply.
+        world.run_system_once(apply_feed_bindings).unwrap();
+        assert_eq!(
+            *world.resource::<Weather>(),
+            Weather {
+                temperature: -1.0,
+                sky: String::new(),
+            }
+        );
+
+        world.resource_mut::<FeedValues>().values.insert(
+            "weather".into(),
+            FeedValue {
+                document: json!({"temp": 21.5, "sky": "overcast"}),
+                received: Instant::now(),
+                stale: false,
+            },
+        );
+        world.run_system_once(receive_feeds).unwrap();
+        world.run_system_once(apply_feed_bindings).unwrap();
+        assert_eq!(
+            *world.resource::<Weather>(),
+            Weather {
+                temperature: 21.5,
+                sky: "overcast".into(),
+            }
+        );
+
+        // The feed went quiet for longer than it stays current.
+        let mut values = world.resource_mut::<FeedValues>();
+        let value = values.values.get_mut("weather").unwrap();
+        value.received = Instant::now() - Duration::from_secs(61);
+        world.run_system_once(receive_feeds).unwrap();
+        assert!(world.resource::<FeedValues>().get("weather").unwrap().stale);
+        world.run_system_once(apply_feed_bindings).unwrap();
+        assert_eq!(
+            *world.resource::<Weather>(),
+            Weather {
+                temperature: -1.0,
+                sky: "overcast".into(),
+            }
+        );
+    }
+}
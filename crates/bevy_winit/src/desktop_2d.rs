@@ -0,0 +1,354 @@
+use core::marker::PhantomData;
+
+use bevy::{
+    asset::{Assets, Handle, RenderAssetUsages},
+    core_pipeline::core_2d::Camera2d,
+    image::Image,
+    math::{IVec2, Rect, UVec2, Vec2},
+    render::{
+        camera::{Camera, OrthographicProjection, RenderTarget, ScalingMode},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::Visibility,
+    },
+    sprite::{Sprite, SpriteImageMode},
+    transform::components::Transform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    change_detection::DetectChangesMut,
+    component::Component,
+    entity::Entity,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Commands, Query, Res, Resource},
+};
+use bevy_window::{Monitor, Window, WindowPosition, WindowRef};
+
+use crate::monitor_identity::{MonitorIdentity, MonitorMatcher};
+
+/// How an image is fitted onto a monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFit {
+    /// Fill the monitor, cropping what doesn't fit.
+    #[default]
+    Cover,
+    /// Show the whole image, leaving [`ClearColor`](bevy::render::camera::ClearColor) bars around
+    /// it.
+    Contain,
+    /// Repeat the image at its own size.
+    Tile,
+}
+
+impl ImageFit {
+    /// The fit of the first override matching the monitor, or else `default`.
+    pub(crate) fn for_monitor(
+        default: Self,
+        overrides: &[(MonitorMatcher, Self)],
+        identity: Option<&MonitorIdentity>,
+    ) -> Self {
+        identity
+            .and_then(|identity| {
+                overrides
+                    .iter()
+                    .find(|(matcher, _)| matcher.matches(identity))
+            })
+            .map_or(default, |(_, fit)| *fit)
+    }
+
+    /// Sizes and places `sprite` to show an image of `image_size` on `monitor`.
+    ///
+    /// Within what fits, the image is zoomed in by `zoom` and panned by `pan`, from -1 to 1 on
+    /// each axis. Tiled images are neither zoomed nor panned.
+    pub(crate) fn apply(
+        self,
+        sprite: &mut Sprite,
+        transform: &mut Transform,
+        monitor: &Monitor,
+        image_size: Vec2,
+        pan: Vec2,
+        zoom: f32,
+    ) {
+        let monitor_size = Vec2::new(
+            monitor.physical_width as f32,
+            monitor.physical_height as f32,
+        );
+
+        let (size, crop) = match self {
+            Self::Cover => {
+                let scale = (monitor_size / image_size).max_element();
+                (monitor_size, monitor_size / scale / zoom)
+            }
+            Self::Contain => {
+                let scale = (monitor_size / image_size).min_element();
+                (image_size * scale, image_size / zoom)
+            }
+            Self::Tile => (monitor_size, image_size),
+        };
+        let center = image_size / 2.0 + pan * (image_size - crop) / 2.0;
+
+        sprite.custom_size = Some(size);
+        (sprite.rect, sprite.image_mode) = match self {
+            Self::Tile => (
+                None,
+                SpriteImageMode::Tiled {
+                    tile_x: true,
+                    tile_y: true,
+                    stretch_value: 1.0,
+                },
+            ),
+            _ => (
+                Some(Rect::from_center_size(center, crop)),
+                SpriteImageMode::Auto,
+            ),
+        };
+
+        let position = to_world(monitor.physical_position, monitor_size);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Renders the built-in scenes through a 2D camera per window, in a world laid out like the
+/// desktop: one unit per physical pixel, with `y` pointing up. A window spanning several monitors
+/// sees each of them where they are.
+pub(crate) struct Desktop2dPlugin;
+
+impl Desktop2dPlugin {
+    /// Adds the plugin unless another scene already did.
+    pub(crate) fn add_to(app: &mut App) {
+        if !app.is_plugin_added::<Self>() {
+            app.add_plugins(Self);
+        }
+    }
+}
+
+impl Plugin for Desktop2dPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, follow_desktop_windows);
+    }
+}
+
+/// The 2D camera rendering the scenes into a window.
+#[derive(Component)]
+struct DesktopCamera {
+    window: Entity,
+}
+
+/// Gives every window a camera looking at its part of the desktop.
+fn follow_desktop_windows(
+    mut commands: Commands,
+    windows: Query<(Entity, &Window)>,
+    mut cameras: Query<(
+        Entity,
+        &DesktopCamera,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+) {
+    for (entity, camera, mut transform, mut projection) in &mut cameras {
+        let Ok((_, window)) = windows.get(camera.window) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        let (origin, size) = desktop_rect(window);
+        transform.set_if_neq(Transform::from_translation(
+            to_world(origin, size).extend(0.0),
+        ));
+
+        let fixed = matches!(
+            projection.scaling_mode,
+            ScalingMode::Fixed { width, height } if width == size.x && height == size.y
+        );
+        if !fixed {
+            projection.scaling_mode = ScalingMode::Fixed {
+                width: size.x,
+                height: size.y,
+            };
+        }
+    }
+
+    for (window, _) in &windows {
+        if cameras
+            .iter()
+            .any(|(_, camera, ..)| camera.window == window)
+        {
+            continue;
+        }
+
+        commands.spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Window(WindowRef::Entity(window)),
+                ..Default::default()
+            },
+            DesktopCamera { window },
+        ));
+    }
+}
+
+/// A scene showing a single texture, fitted onto every monitor, like a video or an animation.
+pub(crate) trait TextureScene: Resource {
+    fn fit_for(&self, identity: Option<&MonitorIdentity>) -> ImageFit;
+
+    /// Shows the [`SceneTexture`] of the scene on every monitor, after the systems in `Update`
+    /// that run before [`follow_scene_monitors`].
+    fn add_to(app: &mut App)
+    where
+        Self: Sized,
+    {
+        Desktop2dPlugin::add_to(app);
+
+        app.init_resource::<SceneTexture<Self>>().add_systems(
+            Update,
+            (follow_scene_monitors::<Self>, layout_scene::<Self>).chain(),
+        );
+    }
+}
+
+/// The texture shown by the [`TextureScene`] `S`.
+#[derive(Resource)]
+pub(crate) struct SceneTexture<S> {
+    image: Option<Handle<Image>>,
+    scene: PhantomData<fn() -> S>,
+}
+
+impl<S> Default for SceneTexture<S> {
+    fn default() -> Self {
+        Self {
+            image: None,
+            scene: PhantomData,
+        }
+    }
+}
+
+impl<S> SceneTexture<S> {
+    /// Replaces the pixels of the texture, creating it anew when the size or format changes.
+    pub(crate) fn upload(
+        &mut self,
+        images: &mut Assets<Image>,
+        size: UVec2,
+        format: TextureFormat,
+        pixels: &[u8],
+    ) {
+        let existing = self.image.as_ref().and_then(|image| images.get_mut(image));
+
+        match existing {
+            Some(image) if image.size() == size && image.texture_descriptor.format == format => {
+                image.data.copy_from_slice(pixels);
+            }
+            _ => {
+                let image = Image::new(
+                    Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    pixels.to_vec(),
+                    format,
+                    RenderAssetUsages::default(),
+                );
+
+                match &self.image {
+                    Some(handle) => images.insert(handle, image),
+                    None => self.image = Some(images.add(image)),
+                }
+            }
+        }
+    }
+}
+
+/// The sprite showing the texture of the [`TextureScene`] `S` on a monitor.
+#[derive(Component)]
+pub(crate) struct SceneSprite<S: TextureScene> {
+    monitor: Entity,
+    scene: PhantomData<fn() -> S>,
+}
+
+/// Gives every monitor a sprite to show the scene on.
+pub(crate) fn follow_scene_monitors<S: TextureScene>(
+    mut commands: Commands,
+    monitors: Query<Entity, With<Monitor>>,
+    sprites: Query<(Entity, &SceneSprite<S>)>,
+) {
+    for (entity, sprite) in &sprites {
+        if !monitors.contains(sprite.monitor) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for monitor in &monitors {
+        if !sprites.iter().any(|(_, sprite)| sprite.monitor == monitor) {
+            commands.spawn((
+                Sprite::default(),
+                Visibility::Hidden,
+                SceneSprite::<S> {
+                    monitor,
+                    scene: PhantomData,
+                },
+            ));
+        }
+    }
+}
+
+/// Fits the texture of the scene onto the monitors.
+fn layout_scene<S: TextureScene>(
+    scene: Res<S>,
+    texture: Res<SceneTexture<S>>,
+    images: Res<Assets<Image>>,
+    monitors: Query<(&Monitor, Option<&MonitorIdentity>)>,
+    mut sprites: Query<(
+        &SceneSprite<S>,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let shown = texture
+        .image
+        .as_ref()
+        .and_then(|handle| Some((handle, images.get(handle)?)));
+
+    for (slot, mut sprite, mut transform, mut visibility) in &mut sprites {
+        let (Some((handle, image)), Ok((monitor, identity))) = (shown, monitors.get(slot.monitor))
+        else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+
+        if sprite.image != *handle {
+            sprite.image = handle.clone();
+        }
+
+        scene.fit_for(identity).apply(
+            &mut sprite,
+            &mut transform,
+            monitor,
+            image.size_f32(),
+            Vec2::ZERO,
+            1.0,
+        );
+    }
+}
+
+/// Where a window is on the desktop and its size, in physical pixels.
+fn desktop_rect(window: &Window) -> (IVec2, Vec2) {
+    let origin = match window.position {
+        WindowPosition::At(position) => position,
+        // Windows placed by the window manager are assumed to start at the desktop origin.
+        _ => IVec2::ZERO,
+    };
+    let size = Vec2::new(
+        window.resolution.physical_width() as f32,
+        window.resolution.physical_height() as f32,
+    );
+
+    (origin, size)
+}
+
+/// The world position of the center of a desktop rectangle.
+pub(crate) fn to_world(origin: IVec2, size: Vec2) -> Vec2 {
+    let center = origin.as_vec2() + size / 2.0;
+    Vec2::new(center.x, -center.y)
+}
@@ -0,0 +1,285 @@
+use core::time::Duration;
+
+use bevy::{
+    color::{Color, Srgba},
+    time::{Time, Virtual},
+    utils::Instant,
+};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    event::EventReader,
+    system::{Local, Res, ResMut, Resource},
+};
+use bevy_window::WindowTheme;
+use tracing::warn;
+use winit::platform::x11::{X11EventKinds, XSettings};
+use x11rb::{
+    errors::ReplyError,
+    protocol::{
+        Event as X11Event,
+        xproto::{Atom, ConnectionExt as _, Window as XWindow},
+    },
+};
+
+use crate::{
+    converters::convert_winit_theme,
+    raw_x11::{RawX11Event, RawX11Listeners},
+    wallpaper_command::PauseReasons,
+    x11_desktop::X11Desktop,
+};
+
+/// Desktop-wide preferences published by the XSETTINGS manager, kept up to date while the
+/// wallpaper runs.
+///
+/// Without a settings daemon (`xsettingsd`, `gsd-xsettings`, `xfsettingsd`, ...) this stays at
+/// its default.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct DesktopSettings {
+    /// `Net/EnableAnimations`. Animations count as enabled unless the desktop turns them off.
+    pub enable_animations: bool,
+    /// `Net/ThemeName`, e.g. `Adwaita-dark`.
+    pub theme_name: Option<String>,
+    /// `Gtk/CursorThemeName`.
+    pub cursor_theme_name: Option<String>,
+    /// `Net/AccentColor`, either a color setting or a `#rrggbb` string.
+    pub accent_color: Option<Color>,
+    /// Whether the desktop prefers a dark or a light look, from `Net/ColorScheme` or else the
+    /// theme name.
+    pub color_scheme: Option<WindowTheme>,
+    /// Every published setting, including the ones above.
+    pub all: XSettings,
+}
+
+impl Default for DesktopSettings {
+    fn default() -> Self {
+        Self::from_xsettings(XSettings::default())
+    }
+}
+
+impl DesktopSettings {
+    pub fn from_xsettings(settings: XSettings) -> Self {
+        let accent_color = settings
+            .color("Net/AccentColor")
+            .map(|[red, green, blue, alpha]| {
+                let channel = |value: u16| f32::from(value) / f32::from(u16::MAX);
+                Srgba::new(channel(red), channel(green), channel(blue), channel(alpha)).into()
+            })
+            .or_else(|| {
+                let hex = settings.string("Net/AccentColor")?;
+                Srgba::hex(hex).ok().map(Into::into)
+            });
+
+        Self {
+            enable_animations: settings.integer("Net/EnableAnimations") != Some(0),
+            theme_name: settings.string("Net/ThemeName").map(Into::into),
+            cursor_theme_name: settings.string("Gtk/CursorThemeName").map(Into::into),
+            accent_color,
+            color_scheme: settings.theme().map(convert_winit_theme),
+            all: settings,
+        }
+    }
+}
+
+/// Built-in reduced-motion mode, which scenes opt into by inserting this resource with anything
+/// but [`ReducedMotion::Ignore`].
+///
+/// While [`DesktopSettings::enable_animations`] is `false`, [`Time<Virtual>`] is slowed down or
+/// paused, so everything driven by it calms down without the scene checking the setting itself.
+/// The clock is restored when animations are turned back on.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReducedMotion {
+    /// Keep animating at full speed.
+    #[default]
+    Ignore,
+    /// Run virtual time at this fraction of real time.
+    ///
+    /// Negative fractions are treated as `0.0`, and a non-finite fraction as [`Self::Freeze`].
+    Slow(f32),
+    /// Pause virtual time.
+    Freeze,
+}
+
+/// How often to look for a settings manager while there is none.
+const OWNER_LOOKUP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The XSETTINGS manager window whose settings [`DesktopSettings`] mirrors.
+#[derive(Resource, Debug)]
+pub(crate) struct XSettingsWatch {
+    /// `_XSETTINGS_S<screen>`, the selection the manager owns.
+    selection: Atom,
+    owner: Option<XWindow>,
+    next_lookup: Instant,
+}
+
+impl XSettingsWatch {
+    pub(crate) fn new(desktop: &X11Desktop) -> Result<Self, ReplyError> {
+        let name = format!("_XSETTINGS_S{}", desktop.screen_num());
+        let selection = desktop
+            .connection()
+            .intern_atom(false, name.as_bytes())?
+            .reply()?
+            .atom;
+
+        Ok(Self {
+            selection,
+            owner: None,
+            next_lookup: Instant::now(),
+        })
+    }
+
+    /// Reads the settings of the current manager, if there is one.
+    pub(crate) fn load(
+        &mut self,
+        desktop: &X11Desktop,
+        listeners: &mut RawX11Listeners,
+    ) -> DesktopSettings {
+        let settings = self
+            .find_owner(desktop, listeners)
+            .and_then(|_| self.read(desktop));
+
+        match settings {
+            Ok(settings) => DesktopSettings::from_xsettings(settings),
+            Err(err) => {
+                warn!("Could not read XSETTINGS: {err}");
+                DesktopSettings::default()
+            }
+        }
+    }
+
+    /// Finds the current manager and starts listening to it, returning whether one was found.
+    fn find_owner(
+        &mut self,
+        desktop: &X11Desktop,
+        listeners: &mut RawX11Listeners,
+    ) -> Result<bool, ReplyError> {
+        let owner = desktop
+            .connection()
+            .get_selection_owner(self.selection)?
+            .reply()?
+            .owner;
+
+        self.next_lookup = Instant::now() + OWNER_LOOKUP_INTERVAL;
+        self.owner = (owner != x11rb::NONE).then_some(owner);
+
+        if let Some(owner) = self.owner {
+            listeners.listen(
+                owner,
+                X11EventKinds {
+                    property: true,
+                    structure: true,
+                    ..Default::default()
+                },
+            );
+        }
+
+        Ok(self.owner.is_some())
+    }
+
+    fn read(&self, desktop: &X11Desktop) -> Result<XSettings, ReplyError> {
+        let Some(owner) = self.owner else {
+            return Ok(XSettings::default());
+        };
+
+        let atom = desktop.atoms()._XSETTINGS_SETTINGS;
+        let data = desktop
+            .connection()
+            .get_property(false, owner, atom, atom, 0, u32::MAX / 4)?
+            .reply()?
+            .value;
+
+        Ok(XSettings::parse(&data).unwrap_or_else(|| {
+            warn!("Ignoring malformed XSETTINGS from window {owner:#x}");
+            XSettings::default()
+        }))
+    }
+}
+
+/// Re-reads the [`DesktopSettings`] whenever the settings manager changes them, and finds the
+/// new manager when it is restarted.
+pub(crate) fn follow_desktop_settings(
+    mut raw_events: EventReader<RawX11Event>,
+    desktop: Res<X11Desktop>,
+    mut watch: ResMut<XSettingsWatch>,
+    mut listeners: ResMut<RawX11Listeners>,
+    mut settings: ResMut<DesktopSettings>,
+) {
+    let settings_atom = desktop.atoms()._XSETTINGS_SETTINGS;
+    let mut changed = false;
+
+    for RawX11Event { event } in raw_events.read() {
+        match event {
+            X11Event::PropertyNotify(event)
+                if Some(event.window) == watch.owner && event.atom == settings_atom =>
+            {
+                changed = true;
+            }
+            X11Event::DestroyNotify(event) if Some(event.window) == watch.owner => {
+                listeners.stop(event.window);
+                watch.owner = None;
+                changed = true;
+            }
+            _ => {}
+        }
+    }
+
+    if watch.owner.is_none() && watch.next_lookup <= Instant::now() {
+        match watch.find_owner(&desktop, &mut listeners) {
+            Ok(found) => changed |= found,
+            Err(err) => warn!("Could not look up the XSETTINGS manager: {err}"),
+        }
+    }
+
+    if !changed {
+        return;
+    }
+
+    match watch.read(&desktop) {
+        Ok(xsettings) => {
+            settings.set_if_neq(DesktopSettings::from_xsettings(xsettings));
+        }
+        Err(err) => warn!("Could not read XSETTINGS: {err}"),
+    }
+}
+
+/// Carries out the [`ReducedMotion`] mode on [`Time<Virtual>`].
+pub(crate) fn apply_reduced_motion(
+    settings: Res<DesktopSettings>,
+    mode: Res<ReducedMotion>,
+    mut reasons: ResMut<PauseReasons>,
+    time: Option<ResMut<Time<Virtual>>>,
+    // Whether the clock is currently slowed or paused by this system.
+    mut reducing: Local<bool>,
+) {
+    let Some(mut time) = time else {
+        return;
+    };
+
+    if !settings.is_changed() && !mode.is_changed() {
+        return;
+    }
+
+    let reduce = !settings.enable_animations && *mode != ReducedMotion::Ignore;
+
+    if *reducing || reduce {
+        time.set_relative_speed(1.0);
+    }
+
+    reasons.reduced_motion = false;
+    if reduce {
+        match *mode {
+            ReducedMotion::Slow(speed) if speed.is_finite() => {
+                time.set_relative_speed(speed.max(0.0));
+            }
+            ReducedMotion::Slow(speed) => {
+                warn!("Freezing instead of slowing time by a fraction of {speed}");
+                reasons.reduced_motion = true;
+            }
+            ReducedMotion::Freeze => reasons.reduced_motion = true,
+            ReducedMotion::Ignore => {}
+        }
+    }
+
+    // A pause by a `WallpaperCommand` outlasts animations coming back on.
+    reasons.apply(&mut time);
+    *reducing = reduce;
+}
@@ -0,0 +1,198 @@
+use core::{fmt, time::Duration};
+use std::error::Error;
+
+use bevy::utils::Instant;
+use bevy_app::AppExit;
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    prelude::Component,
+    system::{Query, Res, Resource},
+};
+use bevy_window::MonitorSelection;
+use tracing::{error, warn};
+use winit::error::{EventLoopError, OsError};
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
+
+/// Everything that can go wrong while embedding the wallpaper into the desktop.
+#[derive(Debug)]
+pub enum WallpaperError {
+    /// The X server could not be reached, usually because `DISPLAY` is unset or wrong.
+    NoDisplay(ConnectError),
+    /// The X server does not support an extension the wallpaper depends on.
+    MissingExtension(&'static str),
+    /// A request to the X server failed or the connection was lost.
+    X11(ReplyError),
+    /// The window the wallpaper should be embedded into does not exist.
+    BadParent(u32),
+    /// Another window entity is already drawn onto the root window.
+    RootInUse,
+    /// No monitor matched the selection of a fullscreen window.
+    NoMonitor(MonitorSelection),
+    /// The winit event loop could not be created.
+    EventLoop(EventLoopError),
+    /// The X server refused to create the wallpaper window.
+    WindowCreation(OsError),
+}
+
+impl fmt::Display for WallpaperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDisplay(err) => {
+                write!(
+                    f,
+                    "could not connect to the X server (is DISPLAY set?): {err}"
+                )
+            }
+            Self::MissingExtension(name) => {
+                write!(f, "the X server does not support the {name} extension")
+            }
+            Self::X11(err) => write!(f, "X11 request failed: {err}"),
+            Self::BadParent(xid) => write!(f, "parent window {xid:#x} does not exist"),
+            Self::RootInUse => write!(f, "the root window is already used by another window"),
+            Self::NoMonitor(selection) => write!(f, "no monitor matches {selection:?}"),
+            Self::EventLoop(err) => write!(f, "could not create the event loop: {err}"),
+            Self::WindowCreation(err) => write!(f, "could not create the wallpaper window: {err}"),
+        }
+    }
+}
+
+impl Error for WallpaperError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoDisplay(err) => Some(err),
+            Self::X11(err) => Some(err),
+            Self::EventLoop(err) => Some(err),
+            Self::WindowCreation(err) => Some(err),
+            Self::MissingExtension(_)
+            | Self::BadParent(_)
+            | Self::RootInUse
+            | Self::NoMonitor(_) => None,
+        }
+    }
+}
+
+impl From<ConnectError> for WallpaperError {
+    fn from(err: ConnectError) -> Self {
+        Self::NoDisplay(err)
+    }
+}
+
+impl From<ReplyError> for WallpaperError {
+    fn from(err: ReplyError) -> Self {
+        Self::X11(err)
+    }
+}
+
+impl From<ConnectionError> for WallpaperError {
+    fn from(err: ConnectionError) -> Self {
+        Self::X11(err.into())
+    }
+}
+
+impl From<EventLoopError> for WallpaperError {
+    fn from(err: EventLoopError) -> Self {
+        Self::EventLoop(err)
+    }
+}
+
+impl From<OsError> for WallpaperError {
+    fn from(err: OsError) -> Self {
+        Self::WindowCreation(err)
+    }
+}
+
+/// Sent when a wallpaper window could not be created.
+///
+/// What happens next is decided by the [`FallbackPolicy`].
+#[derive(Event, Debug)]
+pub struct WallpaperFailed {
+    /// The window entity that failed.
+    pub window: Entity,
+    pub error: WallpaperError,
+}
+
+/// What the [`WallpaperPlugin`](crate::WallpaperPlugin) does when the wallpaper can't be embedded.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FallbackPolicy {
+    /// Try again every `interval`, giving up and exiting after `attempts` tries.
+    ///
+    /// If `attempts` is `None`, retry forever.
+    Retry {
+        interval: Duration,
+        attempts: Option<u32>,
+    },
+    /// Keep the app running without any windows.
+    Headless,
+    /// Log the error and exit with an error code.
+    #[default]
+    Exit,
+}
+
+impl FallbackPolicy {
+    /// Retry every two seconds for up to a minute, which covers wallpapers started
+    /// alongside the X server.
+    pub fn retry() -> Self {
+        Self::Retry {
+            interval: Duration::from_secs(2),
+            attempts: Some(30),
+        }
+    }
+}
+
+/// Marks a window entity whose winit window could not be created.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WindowCreationFailed {
+    /// How many times creating the window has failed so far.
+    pub attempts: u32,
+    /// When to try again, or `None` if the [`FallbackPolicy`] gave up on the window.
+    pub retry_at: Option<Instant>,
+}
+
+impl WindowCreationFailed {
+    /// Records one more failed attempt and schedules the next one according to `policy`.
+    pub(crate) fn record(previous: Option<&Self>, policy: FallbackPolicy) -> Self {
+        let attempts = previous.map_or(1, |failed| failed.attempts + 1);
+        let retry_at = match policy {
+            FallbackPolicy::Retry {
+                interval,
+                attempts: max,
+            } if max.is_none_or(|max| attempts < max) => Instant::now().checked_add(interval),
+            _ => None,
+        };
+
+        Self { attempts, retry_at }
+    }
+
+    /// Whether the window should be created again now.
+    pub(crate) fn should_retry(&self) -> bool {
+        self.retry_at.is_some_and(|at| at <= Instant::now())
+    }
+}
+
+/// Carries out the [`FallbackPolicy`] for windows that failed to be created.
+pub(crate) fn apply_fallback_policy(
+    mut failures: EventReader<WallpaperFailed>,
+    failed_windows: Query<&WindowCreationFailed>,
+    policy: Res<FallbackPolicy>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    for WallpaperFailed { window, error } in failures.read() {
+        let will_retry = failed_windows
+            .get(*window)
+            .is_ok_and(|failed| failed.retry_at.is_some());
+
+        match *policy {
+            FallbackPolicy::Retry { interval, .. } if will_retry => {
+                warn!("Window {window}: {error}, retrying in {interval:?}");
+            }
+            FallbackPolicy::Headless => {
+                warn!("Window {window}: {error}, continuing without it");
+            }
+            _ => {
+                error!("Window {window}: {error}, exiting");
+                app_exit.send(AppExit::error());
+            }
+        }
+    }
+}
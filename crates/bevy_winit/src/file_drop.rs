@@ -0,0 +1,306 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    color::{Color, Mix, Srgba},
+    render::camera::ClearColor,
+};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    system::{Local, Res, ResMut, Resource},
+};
+use bevy_window::FileDragAndDrop;
+use tracing::{info, warn};
+
+/// The kind of wallpaper a file holds, as guessed from its name, and the built-in scene that
+/// shows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallpaperSourceKind {
+    /// A PNG or JPEG image, shown by the `SlideshowPlugin`.
+    Image,
+    /// An animated GIF, APNG or WebP, played by the `AnimatedImagePlugin`.
+    Animation,
+    /// A WGSL fragment shader, rendered full screen by the `ShaderWallpaperPlugin`.
+    Shader,
+    /// A directory of images, shown in turn by the `SlideshowPlugin`.
+    Directory,
+}
+
+impl WallpaperSourceKind {
+    /// Classifies `path` by its extension, or as a [`Directory`](Self::Directory).
+    ///
+    /// Returns `None` if the file is not something that can be shown as a wallpaper.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Self::Directory);
+        }
+
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" => Some(Self::Image),
+            "gif" | "apng" | "webp" => Some(Self::Animation),
+            "wgsl" => Some(Self::Shader),
+            _ => None,
+        }
+    }
+}
+
+/// A file the wallpaper can be loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WallpaperSource {
+    pub kind: WallpaperSourceKind,
+    pub path: PathBuf,
+}
+
+impl WallpaperSource {
+    /// Returns `None` if `path` is not a supported wallpaper source.
+    pub fn from_path(path: impl Into<PathBuf>) -> Option<Self> {
+        let path = path.into();
+        let kind = WallpaperSourceKind::from_path(&path)?;
+
+        Some(Self { kind, path })
+    }
+}
+
+/// The source the current scene is loaded from.
+///
+/// The built-in scenes switch to it whenever it changes to a [`WallpaperSourceKind`] they show.
+/// Custom scenes can watch it the same way.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CurrentWallpaper(pub Option<WallpaperSource>);
+
+impl CurrentWallpaper {
+    /// The path of the current wallpaper if it changed since the calling system last ran and is
+    /// one of `kinds`.
+    pub(crate) fn changed_to<'a>(
+        current: &'a Option<Res<Self>>,
+        kinds: &[WallpaperSourceKind],
+    ) -> Option<&'a Path> {
+        let current = current.as_ref().filter(|current| current.is_changed())?;
+        let source = current.0.as_ref()?;
+
+        kinds
+            .contains(&source.kind)
+            .then_some(source.path.as_path())
+    }
+}
+
+/// Sent after a file dropped onto the desktop replaced the [`CurrentWallpaper`].
+#[derive(Event, Debug, Clone)]
+pub struct WallpaperDropped {
+    /// The wallpaper window the file was dropped on.
+    pub window: Entity,
+    pub source: WallpaperSource,
+}
+
+/// How the wallpaper is highlighted while a supported file is dragged over it.
+#[derive(Resource, Debug, Clone)]
+pub struct DropHighlight {
+    /// Set to `false` to leave the wallpaper untouched while hovering.
+    pub enabled: bool,
+    /// The color the [`ClearColor`] is blended towards.
+    pub color: Color,
+    /// How far the [`ClearColor`] is blended towards `color`, from `0.0` to `1.0`.
+    pub strength: f32,
+}
+
+impl Default for DropHighlight {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: Srgba::rgb(137. / 255., 180. / 255., 250. / 255.).into(),
+            strength: 0.35,
+        }
+    }
+}
+
+/// Turns files dropped onto a wallpaper window into the [`CurrentWallpaper`] and highlights the
+/// wallpaper while a supported file hovers over it.
+pub(crate) fn handle_file_drops(
+    mut drag_and_drop: EventReader<FileDragAndDrop>,
+    mut wallpaper_dropped: EventWriter<WallpaperDropped>,
+    mut current: ResMut<CurrentWallpaper>,
+    highlight: Res<DropHighlight>,
+    mut clear_color: Option<ResMut<ClearColor>>,
+    // The clear color from before the highlight was applied.
+    mut unhighlighted: Local<Option<Color>>,
+) {
+    for event in drag_and_drop.read() {
+        match event {
+            FileDragAndDrop::HoveredFile { path_buf, .. } => {
+                if !highlight.enabled
+                    || unhighlighted.is_some()
+                    || WallpaperSourceKind::from_path(path_buf).is_none()
+                {
+                    continue;
+                }
+
+                if let Some(clear_color) = clear_color.as_deref_mut() {
+                    *unhighlighted = Some(clear_color.0);
+                    clear_color.0 = clear_color.0.mix(&highlight.color, highlight.strength);
+                }
+            }
+            FileDragAndDrop::HoveredFileCanceled { .. } => {
+                restore_clear_color(clear_color.as_deref_mut(), &mut unhighlighted);
+            }
+            FileDragAndDrop::DroppedFile { window, path_buf } => {
+                restore_clear_color(clear_color.as_deref_mut(), &mut unhighlighted);
+
+                let Some(source) = WallpaperSource::from_path(path_buf) else {
+                    warn!("Ignoring dropped file {path_buf:?}: not a supported wallpaper source");
+                    continue;
+                };
+
+                info!("Loading dropped {:?} {:?}", source.kind, source.path);
+                current.0 = Some(source.clone());
+                wallpaper_dropped.send(WallpaperDropped {
+                    window: *window,
+                    source,
+                });
+            }
+        }
+    }
+}
+
+fn restore_clear_color(clear_color: Option<&mut ClearColor>, unhighlighted: &mut Option<Color>) {
+    if let (Some(clear_color), Some(color)) = (clear_color, unhighlighted.take()) {
+        clear_color.0 = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use bevy_ecs::{event::Events, world::World};
+
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        let kind = |path: &str| WallpaperSourceKind::from_path(Path::new(path));
+
+        assert_eq!(kind("/walls/beach.png"), Some(WallpaperSourceKind::Image));
+        assert_eq!(kind("/walls/Beach.JPEG"), Some(WallpaperSourceKind::Image));
+        assert_eq!(
+            kind("/walls/rain.gif"),
+            Some(WallpaperSourceKind::Animation)
+        );
+        assert_eq!(
+            kind("/walls/rain.webp"),
+            Some(WallpaperSourceKind::Animation)
+        );
+        assert_eq!(kind("/walls/waves.wgsl"), Some(WallpaperSourceKind::Shader));
+        assert_eq!(
+            WallpaperSourceKind::from_path(&env::temp_dir()),
+            Some(WallpaperSourceKind::Directory)
+        );
+
+        assert_eq!(kind("/walls/notes.txt"), None);
+        assert_eq!(kind("/walls/waves.glsl"), None);
+        assert_eq!(kind("/walls/README"), None);
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Events<FileDragAndDrop>>();
+        world.init_resource::<Events<WallpaperDropped>>();
+        world.init_resource::<CurrentWallpaper>();
+        world.init_resource::<DropHighlight>();
+        world.insert_resource(ClearColor(Color::BLACK));
+        world
+    }
+
+    fn drag(world: &mut World, event: FileDragAndDrop) {
+        world.send_event(event);
+        world.run_system_cached(handle_file_drops).unwrap();
+    }
+
+    #[test]
+    fn highlights_while_hovering() {
+        let mut world = world();
+        let window = world.spawn_empty().id();
+        let hovered = |path: &str| FileDragAndDrop::HoveredFile {
+            window,
+            path_buf: path.into(),
+        };
+
+        drag(&mut world, hovered("/walls/notes.txt"));
+        assert_eq!(world.resource::<ClearColor>().0, Color::BLACK);
+
+        drag(&mut world, hovered("/walls/beach.png"));
+        let highlighted = world.resource::<ClearColor>().0;
+        assert_ne!(highlighted, Color::BLACK);
+
+        // Hovering again doesn't blend the highlight into itself.
+        drag(&mut world, hovered("/walls/beach.png"));
+        assert_eq!(world.resource::<ClearColor>().0, highlighted);
+
+        drag(&mut world, FileDragAndDrop::HoveredFileCanceled { window });
+        assert_eq!(world.resource::<ClearColor>().0, Color::BLACK);
+
+        world.resource_mut::<DropHighlight>().enabled = false;
+        drag(&mut world, hovered("/walls/beach.png"));
+        assert_eq!(world.resource::<ClearColor>().0, Color::BLACK);
+    }
+
+    #[test]
+    fn drops_replace_the_current_wallpaper() {
+        let mut world = world();
+        let window = world.spawn_empty().id();
+        let dropped = |path: &str| FileDragAndDrop::DroppedFile {
+            window,
+            path_buf: path.into(),
+        };
+
+        drag(
+            &mut world,
+            FileDragAndDrop::HoveredFile {
+                window,
+                path_buf: "/walls/waves.wgsl".into(),
+            },
+        );
+        drag(&mut world, dropped("/walls/waves.wgsl"));
+
+        let source = WallpaperSource {
+            kind: WallpaperSourceKind::Shader,
+            path: "/walls/waves.wgsl".into(),
+        };
+        assert_eq!(world.resource::<CurrentWallpaper>().0, Some(source.clone()));
+        assert_eq!(world.resource::<ClearColor>().0, Color::BLACK);
+
+        let events = world.resource::<Events<WallpaperDropped>>();
+        let sent: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(sent.len(), 1);
+        assert_eq!((sent[0].window, &sent[0].source), (window, &source));
+
+        drag(&mut world, dropped("/walls/notes.txt"));
+        assert_eq!(world.resource::<CurrentWallpaper>().0, Some(source));
+        let events = world.resource::<Events<WallpaperDropped>>();
+        assert_eq!(events.iter_current_update_events().count(), 1);
+    }
+
+    #[test]
+    fn scenes_see_changes_of_their_kinds() {
+        let mut world = world();
+        let changed = |world: &mut World| {
+            world
+                .run_system_cached(|current: Option<Res<CurrentWallpaper>>| {
+                    let kinds = [WallpaperSourceKind::Image, WallpaperSourceKind::Directory];
+                    CurrentWallpaper::changed_to(&current, &kinds).map(Path::to_owned)
+                })
+                .unwrap()
+        };
+
+        assert_eq!(changed(&mut world), None);
+
+        world.resource_mut::<CurrentWallpaper>().0 = WallpaperSource::from_path("/walls/a.png");
+        assert_eq!(changed(&mut world), Some(PathBuf::from("/walls/a.png")));
+        assert_eq!(changed(&mut world), None);
+
+        world.resource_mut::<CurrentWallpaper>().0 = WallpaperSource::from_path("/walls/b.gif");
+        assert_eq!(changed(&mut world), None);
+    }
+}
@@ -0,0 +1,575 @@
+use core::{fmt, time::Duration};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{
+    asset::{AssetServer, Assets, Handle, RecursiveDependencyLoadState, UntypedHandle},
+    image::Image,
+    render::{
+        camera::{Camera, CameraUpdateSystem, RenderTarget},
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        renderer::{RenderDevice, RenderInstance, WgpuWrapper, initialize_renderer},
+        settings::{Backends, RenderCreation, WgpuSettings},
+    },
+    time::{Time, TimeSystem, TimeUpdateStrategy, Virtual},
+};
+use bevy_app::{App, AppExit, First, Last, Plugin, PluginsState, PostUpdate, Startup};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::EventWriter,
+    observer::Trigger,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Commands, Query, Res, ResMut, Resource},
+};
+use bevy_math::UVec2;
+use bevy_window::Window;
+use image::{
+    ImageError, RgbaImage,
+    error::{ParameterError, ParameterErrorKind},
+};
+use tracing::{error, info};
+
+use crate::wallpaper_clock::{WallpaperClock, update_wallpaper_clock};
+
+/// How many updates the readback of a frame may lag behind before rendering is given up on.
+const MAX_LATENCY: u32 = 60;
+
+/// What the [`HeadlessPlugin`] renders, and where to.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct HeadlessConfig {
+    /// The size of the frames in pixels, which every window is resized to.
+    pub size: UVec2,
+    /// How many frames to write before exiting.
+    pub frames: u32,
+    /// How far [`Time`] moves between frames. The first frame is at zero.
+    pub step: Duration,
+    /// Frames rendered with time held at zero and thrown away before the first written one.
+    pub warmup: u32,
+    /// Assets to wait for, with time held at zero, before the first written frame. Scenes can
+    /// add the handles they load from their startup systems.
+    pub assets: Vec<UntypedHandle>,
+    /// The directory the frames are written to, as `frame-0000.png`, `frame-0001.png` and so on.
+    pub output: PathBuf,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            size: UVec2::new(1280, 720),
+            frames: 1,
+            step: Duration::from_secs_f64(1.0 / 60.0),
+            warmup: 0,
+            assets: Vec::new(),
+            output: PathBuf::from("frames"),
+        }
+    }
+}
+
+/// Renders [`HeadlessConfig::frames`] frames offscreen to PNG files and exits, without an X
+/// server, for golden-image tests on CI machines. Use it in place of the
+/// [`WallpaperPlugin`](crate::WallpaperPlugin).
+///
+/// [`Time`] moves by exactly [`HeadlessConfig::step`] per frame, and a [`WallpaperClock`] is
+/// held at the same time, so every run renders the same frames. Cameras drawing to a window draw
+/// into the frame instead, and [`software_renderer`] keeps the frames from depending on the GPU.
+#[derive(Default)]
+pub struct HeadlessPlugin {
+    pub config: HeadlessConfig,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .insert_resource(TimeUpdateStrategy::ManualDuration(self.config.step))
+            .init_resource::<HeadlessProgress>()
+            .add_systems(Startup, create_frame_target)
+            .add_systems(
+                First,
+                (
+                    hold_time_until_ready.before(TimeSystem),
+                    sync_wallpaper_clock
+                        .after(TimeSystem)
+                        .before(update_wallpaper_clock),
+                ),
+            )
+            .add_systems(PostUpdate, retarget_windows.before(CameraUpdateSystem))
+            .add_systems(Last, request_readback)
+            .set_runner(headless_runner);
+    }
+}
+
+/// Updates the app as fast as it goes until it exits, with no event loop or windows.
+pub fn headless_runner(mut app: App) -> AppExit {
+    if app.plugins_state() != PluginsState::Cleaned {
+        while app.plugins_state() == PluginsState::Adding {
+            bevy_tasks::tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+    }
+
+    loop {
+        app.update();
+
+        if let Some(exit) = app.should_exit() {
+            return exit;
+        }
+    }
+}
+
+/// A renderer on a CPU adapter, like Mesa's llvmpipe or lavapipe, for
+/// `RenderPlugin::render_creation`, so frames render the same on every machine. The backends can
+/// be narrowed with `WGPU_BACKEND`, e.g. to `gl` for llvmpipe.
+///
+/// Panics if there is no CPU adapter.
+pub fn software_renderer() -> RenderCreation {
+    let settings = WgpuSettings::default();
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: settings.backends.unwrap_or(Backends::all()),
+        flags: settings.instance_flags,
+        dx12_shader_compiler: settings.dx12_shader_compiler.clone(),
+        gles_minor_version: settings.gles3_minor_version,
+    });
+    let (device, queue, adapter_info, adapter) = bevy_tasks::block_on(initialize_renderer(
+        &instance,
+        &settings,
+        &wgpu::RequestAdapterOptions {
+            power_preference: settings.power_preference,
+            force_fallback_adapter: true,
+            compatible_surface: None,
+        },
+    ));
+
+    RenderCreation::manual(
+        device,
+        queue,
+        adapter_info,
+        adapter,
+        RenderInstance(Arc::new(WgpuWrapper::new(instance))),
+    )
+}
+
+/// The image every camera draws into.
+#[derive(Resource)]
+struct FrameTarget(Handle<Image>);
+
+#[derive(Resource, Debug, Default)]
+struct HeadlessProgress {
+    /// Updates run so far, each rendering a frame.
+    updates: u32,
+    /// The update rendering the first written frame, once everything is ready.
+    first: Option<u32>,
+    /// Frames written so far.
+    written: u32,
+}
+
+/// Marks the readback of the frame with this index, so it is written under the right name
+/// however late it completes.
+#[derive(Component)]
+struct FrameReadback(u32);
+
+fn create_frame_target(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut time: ResMut<Time<Virtual>>,
+    config: Res<HeadlessConfig>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Err(err) = fs::create_dir_all(&config.output) {
+        error!("Could not create {}: {err}", config.output.display());
+        exit.send(AppExit::error());
+        return;
+    }
+
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: config.size.x,
+            height: config.size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC;
+    let image = images.add(image);
+
+    // Long steps would otherwise be clamped.
+    let max_delta = time.max_delta().max(config.step);
+    time.set_max_delta(max_delta);
+
+    commands.insert_resource(FrameTarget(image));
+}
+
+/// Holds time at zero until the warmup frames are done and the assets are loaded, then lets it
+/// move by a step each update.
+fn hold_time_until_ready(
+    mut progress: ResMut<HeadlessProgress>,
+    mut time: ResMut<Time<Virtual>>,
+    config: Res<HeadlessConfig>,
+    asset_server: Res<AssetServer>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let updates = progress.updates;
+    progress.updates += 1;
+
+    if let Some(first) = progress.first {
+        time.unpause();
+        if updates > first + config.frames + MAX_LATENCY {
+            error!("Only {} frames were read back, exiting", progress.written);
+            exit.send(AppExit::error());
+        }
+        return;
+    }
+
+    time.pause();
+    if updates < config.warmup {
+        return;
+    }
+
+    for handle in &config.assets {
+        match asset_server.recursive_dependency_load_state(handle) {
+            RecursiveDependencyLoadState::Loaded => {}
+            RecursiveDependencyLoadState::Failed(err) => {
+                error!("{err}, exiting");
+                exit.send(AppExit::error());
+                return;
+            }
+            RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading => {
+                return;
+            }
+        }
+    }
+
+    progress.first = Some(updates);
+}
+
+/// Holds the [`WallpaperClock`] at the elapsed time, so scenes reading it render the same.
+fn sync_wallpaper_clock(clock: Option<ResMut<WallpaperClock>>, time: Res<Time<Virtual>>) {
+    if let Some(mut clock) = clock {
+        clock.freeze();
+        clock.set_elapsed_secs(time.elapsed_secs_f64());
+    }
+}
+
+/// Points cameras drawing to a window at the frame, and sizes windows like the frame so scenes
+/// lay themselves out for it.
+fn retarget_windows(
+    mut cameras: Query<&mut Camera>,
+    mut windows: Query<&mut Window>,
+    target: Option<Res<FrameTarget>>,
+    config: Res<HeadlessConfig>,
+) {
+    let Some(target) = target else {
+        return;
+    };
+
+    for mut camera in &mut cameras {
+        if matches!(camera.target, RenderTarget::Window(_)) {
+            camera.target = RenderTarget::Image(target.0.clone());
+        }
+    }
+
+    for mut window in &mut windows {
+        if window.physical_width() != config.size.x || window.physical_height() != config.size.y {
+            window.resolution.set_scale_factor_override(Some(1.0));
+            window
+                .resolution
+                .set_physical_resolution(config.size.x, config.size.y);
+        }
+    }
+}
+
+/// Reads back the frame this update renders if it is one to write, and stops reading back the
+/// one before, which was copied when it was rendered.
+fn request_readback(
+    mut commands: Commands,
+    progress: Res<HeadlessProgress>,
+    requested: Query<Entity, (With<Readback>, With<FrameReadback>)>,
+    target: Option<Res<FrameTarget>>,
+    config: Res<HeadlessConfig>,
+) {
+    for entity in &requested {
+        commands.entity(entity).remove::<Readback>();
+    }
+
+    let (Some(target), Some(first)) = (target, progress.first) else {
+        return;
+    };
+    let frame = progress.updates - 1 - first;
+    if frame < config.frames {
+        commands
+            .spawn((Readback::texture(target.0.clone()), FrameReadback(frame)))
+            .observe(write_frame);
+    }
+}
+
+/// Writes a frame that was read back, and exits once every frame is written.
+fn write_frame(
+    trigger: Trigger<ReadbackComplete>,
+    mut commands: Commands,
+    frames: Query<&FrameReadback>,
+    mut progress: ResMut<HeadlessProgress>,
+    config: Res<HeadlessConfig>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let entity = trigger.entity();
+    let Ok(&FrameReadback(frame)) = frames.get(entity) else {
+        return;
+    };
+    commands.entity(entity).despawn();
+
+    let path = config.output.join(format!("frame-{frame:04}.png"));
+    match frame_image(&trigger.event().0, config.size).and_then(|image| image.save(&path)) {
+        Ok(()) => info!("Wrote {}", path.display()),
+        Err(err) => {
+            error!("Could not write {}: {err}", path.display());
+            exit.send(AppExit::error());
+            return;
+        }
+    }
+
+    progress.written += 1;
+    if progress.written == config.frames {
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Drops the padding readback adds to the end of each row.
+fn frame_image(data: &[u8], size: UVec2) -> Result<RgbaImage, ImageError> {
+    let row = size.x as usize * 4;
+    let padded_row = RenderDevice::align_copy_bytes_per_row(row);
+    let pixels = data
+        .chunks(padded_row)
+        .take(size.y as usize)
+        .flat_map(|padded| padded.get(..row).unwrap_or(padded))
+        .copied()
+        .collect();
+
+    RgbaImage::from_raw(size.x, size.y, pixels).ok_or_else(|| {
+        ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        ))
+    })
+}
+
+/// How far a frame may be from its golden image, to allow for rounding differences between
+/// drivers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoldenTolerance {
+    /// How far a channel of a pixel may be off, out of 255, before the pixel counts as different.
+    pub channel: u8,
+    /// The fraction of pixels that may differ.
+    pub pixels: f64,
+}
+
+impl Default for GoldenTolerance {
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            pixels: 0.001,
+        }
+    }
+}
+
+/// Why a frame doesn't match its golden image.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// An image could not be read.
+    Read(PathBuf, ImageError),
+    /// The frame and the golden image have different sizes.
+    Size { frame: UVec2, golden: UVec2 },
+    /// More pixels differ than the tolerance allows.
+    Differs {
+        /// How many pixels differ by more than [`GoldenTolerance::channel`].
+        pixels: usize,
+        /// How many pixels the frame has.
+        total: usize,
+        /// The largest difference of any channel.
+        max: u8,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, err) => write!(f, "could not read {}: {err}", path.display()),
+            Self::Size { frame, golden } => {
+                write!(f, "the frame is {frame} but the golden image is {golden}")
+            }
+            Self::Differs { pixels, total, max } => write!(
+                f,
+                "{pixels} of {total} pixels differ from the golden image, by up to {max}"
+            ),
+        }
+    }
+}
+
+impl Error for GoldenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Read(_, err) => Some(err),
+            Self::Size { .. } | Self::Differs { .. } => None,
+        }
+    }
+}
+
+/// Compares a frame written by the [`HeadlessPlugin`] with a stored golden image, e.g.
+/// `compare_golden("frames/frame-0003.png", "tests/golden/sunset-3.png", Default::default())`.
+pub fn compare_golden(
+    frame: impl AsRef<Path>,
+    golden: impl AsRef<Path>,
+    tolerance: GoldenTolerance,
+) -> Result<(), GoldenError> {
+    let frame = read_rgba(frame.as_ref())?;
+    let golden = read_rgba(golden.as_ref())?;
+
+    if frame.dimensions() != golden.dimensions() {
+        return Err(GoldenError::Size {
+            frame: frame.dimensions().into(),
+            golden: golden.dimensions().into(),
+        });
+    }
+
+    let (mut pixels, mut max) = (0, 0);
+    for (a, b) in frame.pixels().zip(golden.pixels()) {
+        let diff =
+            a.0.iter()
+                .zip(b.0)
+                .map(|(a, b)| a.abs_diff(b))
+                .max()
+                .unwrap_or(0);
+        max = max.max(diff);
+        if diff > tolerance.channel {
+            pixels += 1;
+        }
+    }
+
+    let total = frame.pixels().len();
+    if pixels as f64 > total as f64 * tolerance.pixels {
+        return Err(GoldenError::Differs { pixels, total, max });
+    }
+    Ok(())
+}
+
+fn read_rgba(path: &Path) -> Result<RgbaImage, GoldenError> {
+    image::open(path)
+        .map(|image| image.into_rgba8())
+        .map_err(|err| GoldenError::Read(path.to_owned(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use image::Rgba;
+
+    use super::*;
+
+    /// Writes a gray image with one brighter pixel, returning its path.
+    fn write_image(name: &str, size: (u32, u32), bright: u8) -> PathBuf {
+        let path = env::temp_dir().join(format!("headless-{}-{name}.png", process::id()));
+        let mut image = RgbaImage::from_pixel(size.0, size.1, Rgba([128, 128, 128, 255]));
+        image.put_pixel(0, 0, Rgba([128 + bright, 128, 128, 255]));
+        image.save(&path).unwrap();
+        path
+    }
+
+    fn remove(paths: &[PathBuf]) {
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    fn compare(frame: &Path, golden: &Path) -> Result<(), GoldenError> {
+        compare_golden(
+            frame,
+            golden,
+            GoldenTolerance {
+                channel: 2,
+                pixels: 0.01,
+            },
+        )
+    }
+
+    #[test]
+    fn identical() {
+        let golden = write_image("identical-golden", (10, 10), 0);
+        let frame = write_image("identical-frame", (10, 10), 0);
+        assert!(compare(&frame, &golden).is_ok());
+
+        remove(&[golden, frame]);
+    }
+
+    #[test]
+    fn within_tolerance() {
+        let golden = write_image("within-golden", (10, 10), 0);
+        let frame = write_image("within-frame", (10, 10), 2);
+        assert!(compare(&frame, &golden).is_ok());
+
+        // One pixel of a hundred may differ by more.
+        let pixel = write_image("within-frame-pixel", (10, 10), 50);
+        assert!(compare(&pixel, &golden).is_ok());
+
+        remove(&[golden, frame, pixel]);
+    }
+
+    #[test]
+    fn over_tolerance() {
+        let golden = write_image("over-golden", (5, 5), 0);
+        let frame = write_image("over-frame", (5, 5), 50);
+        assert!(matches!(
+            compare(&frame, &golden),
+            Err(GoldenError::Differs {
+                pixels: 1,
+                total: 25,
+                max: 50
+            })
+        ));
+
+        remove(&[golden, frame]);
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let golden = write_image("size-golden", (10, 10), 0);
+        let frame = write_image("size-frame", (10, 5), 0);
+        assert!(matches!(
+            compare(&frame, &golden),
+            Err(GoldenError::Size { frame, golden })
+                if frame == UVec2::new(10, 5) && golden == UVec2::new(10, 10)
+        ));
+
+        let missing = env::temp_dir().join(format!("headless-{}-missing.png", process::id()));
+        assert!(matches!(
+            compare(&missing, &golden),
+            Err(GoldenError::Read(path, _)) if path == missing
+        ));
+
+        remove(&[golden, frame]);
+    }
+
+    #[test]
+    fn unpadded_frames() {
+        let padded_row = RenderDevice::align_copy_bytes_per_row(2 * 4);
+        let mut data = vec![0; padded_row * 2];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[padded_row..padded_row + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+
+        let image = frame_image(&data, UVec2::new(2, 2)).unwrap();
+        assert_eq!(image.into_raw(), (1..=16).collect::<Vec<u8>>());
+
+        // A readback short of the frame is an error rather than a panic.
+        assert!(frame_image(&data[..padded_row + 4], UVec2::new(2, 2)).is_err());
+        assert!(frame_image(&data, UVec2::new(2, 3)).is_err());
+    }
+}
@@ -1,35 +1,178 @@
+#[cfg(feature = "animated")]
+pub use animated::{AnimatedImage, AnimatedImagePlugin, AnimationLoop};
+#[cfg(feature = "audio")]
+pub use audio::{AudioInput, AudioPlugin, AudioSource, AudioSpectrum, SampleFormat};
 use bevy::prelude::*;
-use bevy_window::{RawHandleWrapperHolder, Window, WindowCreated, WindowEvent, exit_on_all_closed};
-use core::marker::PhantomData;
+use bevy_window::{
+    Monitor, PrimaryMonitor, RawHandleWrapperHolder, Window, WindowCreated, WindowEvent,
+    exit_on_all_closed,
+};
+use core::{marker::PhantomData, time::Duration};
+#[cfg(feature = "data-feeds")]
+pub use data_feed::{
+    DataFeed, DataFeedPlugin, DataFeeds, FeedBinding, FeedSource, FeedUpdated, FeedValue,
+    FeedValues,
+};
+#[cfg(any(feature = "slideshow", feature = "animated", feature = "raw-frames"))]
+pub use desktop_2d::ImageFit;
+pub use desktop_settings::{DesktopSettings, ReducedMotion};
+use desktop_settings::{XSettingsWatch, apply_reduced_motion, follow_desktop_settings};
+use error::apply_fallback_policy;
+pub use error::*;
+use file_drop::handle_file_drops;
+pub use file_drop::*;
+#[cfg(feature = "headless")]
+pub use headless::{
+    GoldenError, GoldenTolerance, HeadlessConfig, HeadlessPlugin, compare_golden, headless_runner,
+    software_renderer,
+};
+pub use monitor_identity::*;
+use monitors::follow_monitor_changes;
+pub use monitors::{MonitorAdded, MonitorChanged, MonitorRemoved};
+#[cfg(feature = "mpris")]
+pub use mpris::{MprisConfig, MprisPlugin, NowPlaying, PlaybackStatus, Track, TrackChanged};
+#[cfg(feature = "notifications")]
+pub use notifications::{
+    DesktopNotification, NotificationPlugin, NotificationRule, NotificationWatch, Urgency,
+};
+#[cfg(feature = "osc")]
+pub use osc::{OscArg, OscMessage, OscPlugin, OscServer};
+#[cfg(feature = "pbr")]
+use palette::bind_palette_materials;
+pub use palette::*;
+use palette::{bind_palette_clear_color, follow_desktop_palette};
+pub use palette_export::{PaletteExport, PaletteExtracted};
+use palette_export::{PaletteExtraction, capture_palette_frame, export_palette, extract_palette};
+#[cfg(feature = "persistence")]
+pub use persistence::{PersistenceConfig, PersistencePlugin, ReflectPersistentState};
+#[cfg(feature = "preview")]
+use preview::preview_keys;
+#[cfg(feature = "preview")]
+pub use preview::{PreviewControls, PreviewParam};
+#[cfg(feature = "raw-frames")]
+pub use raw_frames::{FrameSource, ParsePixelFormatError, PixelFormat, RawFrames, RawFramesPlugin};
+use raw_x11::RawX11Queue;
+pub use raw_x11::{RawX11Event, RawX11Listeners};
+#[cfg(feature = "shader")]
+pub use shader_wallpaper::{ShaderWallpaper, ShaderWallpaperPlugin};
+#[cfg(feature = "slideshow")]
+pub use slideshow::{Slideshow, SlideshowPlugin};
 use state::winit_runner;
+#[cfg(all(feature = "sun", feature = "pbr"))]
+pub use sun::SunLight;
+#[cfg(feature = "sun")]
+pub use sun::{SunConfig, SunPhase, SunPhaseChanged, SunPlugin, SunState};
 use system::{changed_windows, despawn_windows};
 pub use system::{create_monitors, create_windows};
+#[cfg(feature = "telemetry")]
+pub use telemetry::{
+    BatteryStats, DiskStats, MemoryStats, NetworkStats, SystemStats, TelemetryConfig,
+    TelemetryPlugin, Temperature,
+};
+pub use wallpaper_clock::{WallpaperClock, WallpaperClockPlugin};
+pub use wallpaper_command::WallpaperCommand;
+use wallpaper_command::{PauseReasons, apply_wallpaper_commands};
+#[cfg(feature = "window-capture")]
+use window_capture::capture_windows;
+#[cfg(feature = "window-capture")]
+pub use window_capture::{CaptureTarget, WindowCapture};
 use winit::{event_loop::EventLoop, window::WindowId};
 pub use winit::{
     event_loop::EventLoopProxy,
+    platform::x11::X11EventKinds,
     window::{CustomCursor as WinitCustomCursor, CustomCursorSource},
 };
 pub use winit_config::*;
 use winit_monitors::WinitMonitors;
 pub use winit_windows::*;
-use x11rb::connection::Connection;
+pub use x11_desktop::*;
 
+#[cfg(feature = "animated")]
+mod animated;
+#[cfg(feature = "audio")]
+mod audio;
 mod converters;
+#[cfg(feature = "data-feeds")]
+mod data_feed;
+#[cfg(any(
+    feature = "slideshow",
+    feature = "animated",
+    feature = "raw-frames",
+    feature = "shader"
+))]
+mod desktop_2d;
+mod desktop_settings;
+mod error;
+mod file_drop;
+#[cfg(feature = "headless")]
+mod headless;
+mod monitor_identity;
+mod monitors;
+#[cfg(feature = "mpris")]
+mod mpris;
+#[cfg(feature = "notifications")]
+mod notifications;
+#[cfg(feature = "osc")]
+mod osc;
+mod palette;
+mod palette_export;
+#[cfg(feature = "persistence")]
+mod persistence;
+#[cfg(feature = "preview")]
+mod preview;
+#[cfg(feature = "raw-frames")]
+mod raw_frames;
+mod raw_x11;
+#[cfg(any(
+    feature = "data-feeds",
+    feature = "osc",
+    feature = "persistence",
+    feature = "preview"
+))]
+mod scene_params;
+#[cfg(feature = "shader")]
+mod shader_wallpaper;
+#[cfg(feature = "slideshow")]
+mod slideshow;
 mod state;
+#[cfg(any(feature = "data-feeds", feature = "raw-frames"))]
+mod stoppable_read;
+#[cfg(feature = "sun")]
+mod sun;
 mod system;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+mod wallpaper_clock;
+mod wallpaper_command;
+#[cfg(feature = "window-capture")]
+mod window_capture;
 mod winit_config;
 mod winit_monitors;
 mod winit_windows;
+mod x11_desktop;
 
-pub fn get_screen_roots() -> u32 {
-    let (conn, screen_num) = x11rb::connect(None).unwrap();
-    // println!(
-    //     "root window id (i think) {:?}",
-    //     &conn.setup().roots[screen_num].root
-    // );
-    // println!("screen_num {screen_num}");
+/// Connects to the X server, retrying as long as `policy` allows.
+fn wait_for_display(policy: FallbackPolicy) -> Result<X11Desktop, WallpaperError> {
+    let mut attempts = 0;
 
-    conn.setup().roots[screen_num].root
+    loop {
+        attempts += 1;
+
+        match (X11Desktop::connect(), policy) {
+            (Ok(desktop), _) => return Ok(desktop),
+            (
+                Err(err),
+                FallbackPolicy::Retry {
+                    interval,
+                    attempts: max,
+                },
+            ) if max.is_none_or(|max| attempts < max) => {
+                warn!("{err}, retrying in {interval:?}");
+                std::thread::sleep(interval);
+            }
+            (Err(err), _) => return Err(err),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -44,40 +187,118 @@ pub struct WallpaperPlugin<T: Event = WakeUp> {
     /// Only works on Linux (X11/Wayland) and Windows.
     /// This field is ignored on other platforms.
     pub run_on_any_thread: bool,
+    /// What to do when the wallpaper can't be embedded into the desktop.
+    ///
+    /// This applies both to reaching the X server at startup and to creating each window.
+    pub fallback: FallbackPolicy,
+    /// Whether windows are embedded into the root window, drawn directly onto it, or previewed
+    /// in a regular window.
+    pub target: WallpaperTarget,
     marker: PhantomData<T>,
 }
 
 impl<T: Event> Plugin for WallpaperPlugin<T> {
     fn build(&self, app: &mut App) {
-        let mut event_loop_builder = EventLoop::<T>::with_user_event();
+        // The public resources and events exist even without a display, so systems using them
+        // keep running when the fallback policy goes headless.
+        app.insert_resource(self.fallback)
+            .init_resource::<DesktopSettings>()
+            .init_resource::<ReducedMotion>()
+            .init_resource::<DesktopPalette>()
+            .init_resource::<CurrentWallpaper>()
+            .init_resource::<DropHighlight>()
+            .init_resource::<PauseReasons>()
+            .add_event::<WallpaperFailed>()
+            .add_event::<WallpaperCommand>()
+            .add_event::<WallpaperDropped>()
+            .add_event::<MonitorAdded>()
+            .add_event::<MonitorChanged>()
+            .add_event::<MonitorRemoved>()
+            .add_event::<PaletteExtracted>()
+            .add_systems(PreUpdate, apply_fallback_policy);
 
-        // linux check is needed because x11 might be enabled on other platforms.
-        #[cfg(all(target_os = "linux", feature = "x11"))]
+        // winit only allows one event loop per process, so the X server is checked (and waited
+        // for) before building it rather than retrying the build itself.
+        let (desktop, event_loop) = match wait_for_display(self.fallback)
+            .and_then(|desktop| Ok((desktop, self.build_event_loop()?)))
         {
-            use winit::platform::x11::EventLoopBuilderExtX11;
+            Ok(connected) => connected,
+            Err(err) if self.fallback == FallbackPolicy::Headless => {
+                warn!("{err}, running without a wallpaper window");
+                app.add_plugins(bevy_app::ScheduleRunnerPlugin::run_loop(
+                    Duration::from_secs_f64(1.0 / 60.0),
+                ));
+                return;
+            }
+            Err(err) => {
+                error!("{err}, exiting");
+                app.set_runner(|_| AppExit::error());
+                return;
+            }
+        };
 
-            // This allows a Bevy app to be started and ran outside the main thread.
-            // A use case for this is to allow external applications to spawn a thread
-            // which runs a Bevy app without requiring the Bevy app to need to reside on
-            // the main thread, which can be problematic.
-            event_loop_builder.with_any_thread(self.run_on_any_thread);
+        let mut raw_x11_listeners = RawX11Listeners::default();
+
+        match XSettingsWatch::new(&desktop) {
+            Ok(mut watch) => {
+                let settings = watch.load(&desktop, &mut raw_x11_listeners);
+                app.insert_resource(settings)
+                    .insert_resource(watch)
+                    .add_systems(
+                        PreUpdate,
+                        (follow_desktop_settings, apply_reduced_motion).chain(),
+                    );
+            }
+            Err(err) => warn!("{err}, desktop settings won't be followed"),
         }
 
-        // // linux check is needed because wayland might be enabled on other platforms.
-        // #[cfg(all(target_os = "linux", feature = "wayland"))]
-        // {
-        //     use winit::platform::wayland::EventLoopBuilderExtWayland;
-        //     event_loop_builder.with_any_thread(self.run_on_any_thread);
-        // }
+        // The palette follows `RESOURCE_MANAGER` changes on the root window.
+        raw_x11_listeners.listen(
+            desktop.root(),
+            X11EventKinds {
+                property: true,
+                ..Default::default()
+            },
+        );
+        let palette_config = app
+            .world_mut()
+            .get_resource_or_init::<PaletteConfig>()
+            .clone();
 
-        let event_loop = event_loop_builder
-            .build()
-            .expect("Failed to build event loop");
+        app.insert_resource(palette_config.load(&desktop))
+            .init_resource::<PaletteBindings>()
+            .add_systems(
+                PreUpdate,
+                (follow_desktop_palette, bind_palette_clear_color).chain(),
+            );
+
+        #[cfg(feature = "pbr")]
+        app.add_systems(
+            PreUpdate,
+            bind_palette_materials.after(follow_desktop_palette),
+        );
+
+        #[cfg(feature = "window-capture")]
+        app.add_systems(PreUpdate, capture_windows);
 
-        app.init_non_send_resource::<WinitWindows>()
+        #[cfg(feature = "preview")]
+        if self.target == WallpaperTarget::Preview {
+            app.init_resource::<PreviewControls>()
+                .add_systems(First, preview_keys.after(bevy::time::TimeSystem));
+        }
+
+        app.insert_resource(desktop)
+            .insert_resource(self.target)
+            .init_resource::<PreviewLayout>()
+            .init_non_send_resource::<WinitWindows>()
             .init_resource::<WinitMonitors>()
             .init_resource::<WinitSettings>()
+            .insert_resource(raw_x11_listeners)
+            .insert_resource(RawX11Queue::register())
             .add_event::<RawWinitWindowEvent>()
+            .add_event::<RawX11Event>()
+            .init_resource::<PaletteExtraction>()
+            .add_observer(extract_palette)
             .set_runner(|app| winit_runner(app, event_loop))
             .add_systems(
                 Last,
@@ -89,6 +310,16 @@ impl<T: Event> Plugin for WallpaperPlugin<T> {
                     // check_keyboard_focus_lost,
                 )
                     .chain(),
+            )
+            .add_systems(
+                PreUpdate,
+                (
+                    handle_file_drops,
+                    follow_monitor_changes,
+                    apply_wallpaper_commands,
+                    (capture_palette_frame, export_palette)
+                        .run_if(resource_exists::<PaletteExport>),
+                ),
             );
 
         // app.add_plugins(AccessKitPlugin);
@@ -96,6 +327,33 @@ impl<T: Event> Plugin for WallpaperPlugin<T> {
     }
 }
 
+impl<T: Event> WallpaperPlugin<T> {
+    fn build_event_loop(&self) -> Result<EventLoop<T>, WallpaperError> {
+        let mut event_loop_builder = EventLoop::<T>::with_user_event();
+
+        // linux check is needed because x11 might be enabled on other platforms.
+        #[cfg(all(target_os = "linux", feature = "x11"))]
+        {
+            use winit::platform::x11::EventLoopBuilderExtX11;
+
+            // This allows a Bevy app to be started and ran outside the main thread.
+            // A use case for this is to allow external applications to spawn a thread
+            // which runs a Bevy app without requiring the Bevy app to need to reside on
+            // the main thread, which can be problematic.
+            event_loop_builder.with_any_thread(self.run_on_any_thread);
+        }
+
+        // // linux check is needed because wayland might be enabled on other platforms.
+        // #[cfg(all(target_os = "linux", feature = "wayland"))]
+        // {
+        //     use winit::platform::wayland::EventLoopBuilderExtWayland;
+        //     event_loop_builder.with_any_thread(self.run_on_any_thread);
+        // }
+
+        Ok(event_loop_builder.build()?)
+    }
+}
+
 /// The default event that can be used to wake the window loop
 /// Wakes up the loop if in wait state
 #[derive(Debug, Default, Clone, Copy, Event, Reflect)]
@@ -146,16 +404,40 @@ pub type CreateWindowParams<'w, 's, F = ()> = (
             Entity,
             &'static mut Window,
             Option<&'static RawHandleWrapperHolder>,
+            Option<&'static WindowCreationFailed>,
         ),
         F,
     >,
     EventWriter<'w, WindowCreated>,
+    EventWriter<'w, WallpaperFailed>,
     NonSendMut<'w, WinitWindows>,
     // NonSendMut<'w, AccessKitAdapters>,
     // ResMut<'w, WinitActionRequestHandler>,
     // Res<'w, AccessibilityRequested>,
     Res<'w, WinitMonitors>,
+    Res<'w, FallbackPolicy>,
+    Res<'w, X11Desktop>,
+    Res<'w, WallpaperTarget>,
+    Res<'w, PreviewLayout>,
 );
 
 /// The parameters of the [`create_monitors`] system.
-pub type CreateMonitorParams<'w, 's> = (Commands<'w, 's>, ResMut<'w, WinitMonitors>);
+pub type CreateMonitorParams<'w, 's> = (
+    Commands<'w, 's>,
+    ResMut<'w, WinitMonitors>,
+    Res<'w, X11Desktop>,
+    Res<'w, WallpaperTarget>,
+    Res<'w, PreviewLayout>,
+    Query<
+        'w,
+        's,
+        (
+            &'static mut Monitor,
+            Has<PrimaryMonitor>,
+            Option<&'static MonitorIdentity>,
+        ),
+    >,
+    EventWriter<'w, MonitorAdded>,
+    EventWriter<'w, MonitorChanged>,
+    EventWriter<'w, MonitorRemoved>,
+);
@@ -0,0 +1,346 @@
+use core::{fmt, str::FromStr};
+
+use bevy_ecs::{component::Component, entity::Entity};
+use bevy_window::MonitorSelection;
+
+/// Identifies a physical monitor independently of the order monitors are enumerated in.
+///
+/// Added next to every [`Monitor`](bevy_window::Monitor) component. `make`, `model` and `serial`
+/// come from the monitor's EDID and are `None` when the output doesn't report one, as is common
+/// for virtual and some laptop panels.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Default)]
+pub struct MonitorIdentity {
+    /// The RandR output name, e.g. `DP-2` or `eDP-1`.
+    pub output: String,
+    /// The three letter PNP manufacturer id, e.g. `DEL`.
+    pub make: Option<String>,
+    /// The display product name, or the product code in hex if the EDID has no name.
+    pub model: Option<String>,
+    /// The serial number string, or the numeric serial if the EDID has no string.
+    pub serial: Option<String>,
+}
+
+impl MonitorIdentity {
+    /// Builds an identity for `output` from its raw EDID blob, which may be empty.
+    pub fn from_edid(output: impl Into<String>, edid: &[u8]) -> Self {
+        let mut identity = Self {
+            output: output.into(),
+            ..Self::default()
+        };
+
+        const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+        if edid.len() < 128 || edid[..8] != HEADER {
+            return identity;
+        }
+
+        let manufacturer = u16::from_be_bytes([edid[8], edid[9]]);
+        identity.make = [10, 5, 0]
+            .into_iter()
+            .map(|shift| char::from(b'@' + ((manufacturer >> shift) & 0x1f) as u8))
+            .collect::<String>()
+            .into();
+
+        let product = u16::from_le_bytes([edid[10], edid[11]]);
+        let serial = u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]);
+
+        // Four 18 byte descriptors, of which the display descriptors start with three zero bytes
+        // followed by a tag.
+        for descriptor in edid[54..126].chunks_exact(18) {
+            if descriptor[..3] != [0, 0, 0] {
+                continue;
+            }
+
+            let text = descriptor_text(&descriptor[5..]);
+
+            match descriptor[3] {
+                0xfc => identity.model = text,
+                0xff => identity.serial = text,
+                _ => {}
+            }
+        }
+
+        identity
+            .model
+            .get_or_insert_with(|| format!("{product:04x}"));
+
+        if identity.serial.is_none() && serial != 0 {
+            identity.serial = Some(serial.to_string());
+        }
+
+        identity
+    }
+
+    /// Whether both identities describe the same physical monitor, regardless of the port it is
+    /// plugged into.
+    ///
+    /// Monitors without a serial number can't be told apart from another unit of the same model,
+    /// so this is `false` unless both have one.
+    pub fn same_device(&self, other: &Self) -> bool {
+        self.serial.is_some()
+            && self.make == other.make
+            && self.model == other.model
+            && self.serial == other.serial
+    }
+}
+
+impl fmt::Display for MonitorIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.output)?;
+
+        for part in [&self.make, &self.model, &self.serial]
+            .into_iter()
+            .flatten()
+        {
+            write!(f, " {part}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// EDID descriptor strings are up to 13 bytes, terminated by a newline and padded with spaces.
+fn descriptor_text(bytes: &[u8]) -> Option<String> {
+    let text = bytes.split(|&b| b == b'\n').next().unwrap_or_default();
+    let text = String::from_utf8_lossy(text).trim().to_owned();
+
+    (!text.is_empty()).then_some(text)
+}
+
+/// Picks a monitor by what it is rather than by the order it was enumerated in.
+///
+/// Every field that is set has to match. Matching is case insensitive. A matcher can be parsed
+/// from config strings:
+///
+/// - `DP-2` matches the output name only.
+/// - `make=DEL, model=DELL U2720Q` matches every monitor of that model.
+/// - `serial=ABC123` matches one specific monitor wherever it is plugged in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MonitorMatcher {
+    pub output: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+impl MonitorMatcher {
+    /// Matches the monitor connected to `output`.
+    pub fn output(output: impl Into<String>) -> Self {
+        Self {
+            output: Some(output.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn matches(&self, identity: &MonitorIdentity) -> bool {
+        fn field_matches(want: &Option<String>, have: Option<&str>) -> bool {
+            want.as_deref()
+                .is_none_or(|want| have.is_some_and(|have| want.eq_ignore_ascii_case(have)))
+        }
+
+        field_matches(&self.output, Some(&identity.output))
+            && field_matches(&self.make, identity.make.as_deref())
+            && field_matches(&self.model, identity.model.as_deref())
+            && field_matches(&self.serial, identity.serial.as_deref())
+    }
+
+    /// Finds the first matching monitor entity.
+    pub fn find<'a>(
+        &self,
+        monitors: impl IntoIterator<Item = (Entity, &'a MonitorIdentity)>,
+    ) -> Option<Entity> {
+        monitors
+            .into_iter()
+            .find(|(_, identity)| self.matches(identity))
+            .map(|(entity, _)| entity)
+    }
+
+    /// Turns the matcher into a [`MonitorSelection`] usable in a [`Window`](bevy_window::Window)'s
+    /// mode, or `None` if no monitor matches right now.
+    pub fn select<'a>(
+        &self,
+        monitors: impl IntoIterator<Item = (Entity, &'a MonitorIdentity)>,
+    ) -> Option<MonitorSelection> {
+        self.find(monitors).map(MonitorSelection::Entity)
+    }
+}
+
+/// Error returned when a [`MonitorMatcher`] string has an unknown key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMonitorMatcherError(pub String);
+
+impl fmt::Display for ParseMonitorMatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown monitor matcher key {:?}, expected output, make, model or serial",
+            self.0
+        )
+    }
+}
+
+impl core::error::Error for ParseMonitorMatcherError {}
+
+impl FromStr for MonitorMatcher {
+    type Err = ParseMonitorMatcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.contains('=') {
+            return Ok(Self::output(s.trim()));
+        }
+
+        let mut matcher = Self::default();
+
+        for pair in s.split(',') {
+            let Some((key, value)) = pair.split_once('=') else {
+                return Err(ParseMonitorMatcherError(pair.trim().to_owned()));
+            };
+
+            let value = Some(value.trim().to_owned());
+
+            match key.trim() {
+                "output" => matcher.output = value,
+                "make" => matcher.make = value,
+                "model" => matcher.model = value,
+                "serial" => matcher.serial = value,
+                key => return Err(ParseMonitorMatcherError(key.to_owned())),
+            }
+        }
+
+        Ok(matcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An EDID of a `DEL` monitor with product code `a0b1`, numeric serial 12345, and the given
+    /// display descriptors.
+    fn edid(descriptors: &[(u8, &str)]) -> Vec<u8> {
+        let mut edid = vec![0; 128];
+        edid[..8].copy_from_slice(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+        // D, E and L as five bit letters.
+        edid[8..10].copy_from_slice(&((4u16 << 10) | (5 << 5) | 12).to_be_bytes());
+        edid[10..12].copy_from_slice(&0xa0b1u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&12345u32.to_le_bytes());
+
+        for (descriptor, &(tag, text)) in edid[54..126].chunks_exact_mut(18).zip(descriptors) {
+            descriptor[3] = tag;
+            let mut padded = format!("{text}\n").into_bytes();
+            padded.resize(13, b' ');
+            descriptor[5..].copy_from_slice(&padded);
+        }
+
+        edid
+    }
+
+    #[test]
+    fn edid_descriptors() {
+        let identity =
+            MonitorIdentity::from_edid("DP-2", &edid(&[(0xfc, "DELL U2720Q"), (0xff, "ABC123")]));
+
+        assert_eq!(
+            identity,
+            MonitorIdentity {
+                output: "DP-2".into(),
+                make: Some("DEL".into()),
+                model: Some("DELL U2720Q".into()),
+                serial: Some("ABC123".into()),
+            }
+        );
+        assert_eq!(identity.to_string(), "DP-2 DEL DELL U2720Q ABC123");
+    }
+
+    #[test]
+    fn edid_without_descriptors() {
+        let identity = MonitorIdentity::from_edid("HDMI-1", &edid(&[]));
+
+        assert_eq!(identity.make.as_deref(), Some("DEL"));
+        assert_eq!(identity.model.as_deref(), Some("a0b1"));
+        assert_eq!(identity.serial.as_deref(), Some("12345"));
+    }
+
+    #[test]
+    fn missing_or_broken_edid() {
+        let mut broken = edid(&[]);
+        broken[0] = 0xff;
+
+        for edid in [&[][..], &broken[..], &broken[..64]] {
+            assert_eq!(
+                MonitorIdentity::from_edid("eDP-1", edid),
+                MonitorIdentity {
+                    output: "eDP-1".into(),
+                    ..Default::default()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn same_device_needs_a_serial() {
+        let a = MonitorIdentity::from_edid("DP-1", &edid(&[(0xff, "ABC123")]));
+        let moved = MonitorIdentity {
+            output: "DP-3".into(),
+            ..a.clone()
+        };
+        assert!(a.same_device(&moved));
+
+        let no_serial = MonitorIdentity {
+            serial: None,
+            ..a.clone()
+        };
+        assert!(!no_serial.same_device(&no_serial));
+    }
+
+    #[test]
+    fn matcher() {
+        let identity =
+            MonitorIdentity::from_edid("DP-2", &edid(&[(0xfc, "DELL U2720Q"), (0xff, "ABC123")]));
+
+        assert!(MonitorMatcher::output("dp-2").matches(&identity));
+        assert!(!MonitorMatcher::output("DP-1").matches(&identity));
+        assert!(MonitorMatcher::default().matches(&identity));
+
+        let model: MonitorMatcher = "make=del, model=DELL U2720Q".parse().unwrap();
+        assert!(model.matches(&identity));
+
+        let serial: MonitorMatcher = "serial=XYZ".parse().unwrap();
+        assert!(!serial.matches(&identity));
+        // A matcher asking for a field the monitor doesn't report never matches.
+        assert!(!model.matches(&MonitorIdentity::from_edid("DP-2", &[])));
+
+        let monitors = [
+            (Entity::from_raw(1), MonitorIdentity::from_edid("DP-1", &[])),
+            (Entity::from_raw(2), identity),
+        ];
+        let monitors = monitors
+            .iter()
+            .map(|(entity, identity)| (*entity, identity));
+        assert_eq!(
+            model.select(monitors),
+            Some(MonitorSelection::Entity(Entity::from_raw(2)))
+        );
+    }
+
+    #[test]
+    fn parse_matcher() {
+        assert_eq!("DP-2".parse(), Ok(MonitorMatcher::output("DP-2")));
+        assert_eq!(
+            "output=DP-2,serial=ABC".parse(),
+            Ok(MonitorMatcher {
+                output: Some("DP-2".into()),
+                serial: Some("ABC".into()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            "size=27".parse::<MonitorMatcher>(),
+            Err(ParseMonitorMatcherError("size".into()))
+        );
+        assert_eq!(
+            "make=DEL,DP-2".parse::<MonitorMatcher>(),
+            Err(ParseMonitorMatcherError("DP-2".into()))
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader},
+    query::With,
+    system::{Query, Res},
+};
+use bevy_math::{IVec2, UVec2};
+use bevy_window::{
+    Monitor, MonitorSelection, PrimaryMonitor, VideoMode, Window, WindowMode, WindowPosition,
+};
+use x11rb::{
+    errors::ReplyError,
+    protocol::{
+        randr::{self, ConnectionExt as _},
+        xproto::AtomEnum,
+    },
+};
+
+use crate::{
+    monitor_identity::MonitorIdentity,
+    winit_config::PreviewLayout,
+    winit_monitors::WinitMonitors,
+    x11_desktop::{X11Desktop, X11EventQueue},
+};
+
+/// Sent when a monitor is plugged in or enabled.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MonitorAdded {
+    pub monitor: Entity,
+}
+
+/// Sent when the mode, rotation, position or primary status of a monitor changes.
+///
+/// The [`Monitor`] component has already been updated when this is sent.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MonitorChanged {
+    pub monitor: Entity,
+}
+
+/// Sent when a monitor is unplugged or disabled. The entity is already despawned.
+#[derive(Event, Debug, Clone)]
+pub struct MonitorRemoved {
+    pub monitor: Entity,
+    /// The RandR output name the monitor had, e.g. `DP-2`.
+    pub name: Option<String>,
+}
+
+/// A monitor as RandR currently reports it.
+pub(crate) struct RandrMonitor {
+    pub(crate) monitor: Monitor,
+    pub(crate) identity: MonitorIdentity,
+    pub(crate) primary: bool,
+}
+
+/// Takes the pending RandR events on the [`X11Desktop`] connection and returns whether there
+/// was a screen, CRTC or output change.
+pub(crate) fn randr_changed(desktop: &X11Desktop) -> bool {
+    !desktop.take_events(X11EventQueue::Randr).is_empty()
+}
+
+/// Lists the active CRTCs, named after their first output the same way winit names its
+/// monitors.
+pub(crate) fn query_monitors(desktop: &X11Desktop) -> Result<Vec<RandrMonitor>, ReplyError> {
+    let conn = desktop.connection();
+    let root = desktop.root();
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+    let primary = conn.randr_get_output_primary(root)?.reply()?.output;
+    let bit_depth = desktop.screen().root_depth.into();
+
+    let refresh_rate = |mode: randr::Mode| {
+        resources
+            .modes
+            .iter()
+            .find(|info| info.id == mode)
+            .and_then(mode_refresh_rate_millihertz)
+    };
+
+    let mut monitors = Vec::new();
+
+    for &crtc in &resources.crtcs {
+        let info = conn
+            .randr_get_crtc_info(crtc, resources.config_timestamp)?
+            .reply()?;
+
+        let Some(&output) = info.outputs.first() else {
+            continue;
+        };
+
+        if info.mode == 0 || info.width == 0 || info.height == 0 {
+            continue;
+        }
+
+        let output_info = conn
+            .randr_get_output_info(output, resources.config_timestamp)?
+            .reply()?;
+
+        let video_modes = output_info
+            .modes
+            .iter()
+            .filter_map(|&mode| {
+                let info = resources.modes.iter().find(|info| info.id == mode)?;
+
+                Some(VideoMode {
+                    physical_size: UVec2::new(info.width.into(), info.height.into()),
+                    bit_depth,
+                    refresh_rate_millihertz: mode_refresh_rate_millihertz(info)?,
+                })
+            })
+            .collect();
+
+        // 128 32-bit units covers a base EDID block and three extension blocks.
+        let edid = conn
+            .randr_get_output_property(
+                output,
+                desktop.atoms().EDID,
+                AtomEnum::ANY,
+                0,
+                128,
+                false,
+                false,
+            )?
+            .reply()?
+            .data;
+        let name = String::from_utf8_lossy(&output_info.name).into_owned();
+
+        monitors.push(RandrMonitor {
+            identity: MonitorIdentity::from_edid(name.clone(), &edid),
+            monitor: Monitor {
+                name: Some(name),
+                physical_height: info.height.into(),
+                physical_width: info.width.into(),
+                physical_position: IVec2::new(info.x.into(), info.y.into()),
+                refresh_rate_millihertz: refresh_rate(info.mode),
+                // Filled in from winit, which knows about `Xft.dpi`.
+                scale_factor: 1.0,
+                video_modes,
+            },
+            primary: info.outputs.contains(&primary),
+        });
+    }
+
+    // Like winit, fall back to the first monitor when no output is marked primary.
+    if !monitors.iter().any(|monitor| monitor.primary)
+        && let Some(first) = monitors.first_mut()
+    {
+        first.primary = true;
+    }
+
+    Ok(monitors)
+}
+
+/// The monitors of a preview layout, described the way RandR would.
+pub(crate) fn preview_monitors(layout: &PreviewLayout, scale_factor: f64) -> Vec<RandrMonitor> {
+    let mut monitors: Vec<_> = layout
+        .monitors
+        .iter()
+        .map(|preview| RandrMonitor {
+            identity: MonitorIdentity {
+                output: preview.name.clone(),
+                ..Default::default()
+            },
+            monitor: Monitor {
+                name: Some(preview.name.clone()),
+                physical_height: preview.size.y,
+                physical_width: preview.size.x,
+                physical_position: preview.position,
+                refresh_rate_millihertz: None,
+                scale_factor,
+                video_modes: Vec::new(),
+            },
+            primary: preview.primary,
+        })
+        .collect();
+
+    if !monitors.iter().any(|monitor| monitor.primary)
+        && let Some(first) = monitors.first_mut()
+    {
+        first.primary = true;
+    }
+
+    monitors
+}
+
+fn mode_refresh_rate_millihertz(mode: &randr::ModeInfo) -> Option<u32> {
+    let dots = u64::from(mode.htotal) * u64::from(mode.vtotal);
+
+    (dots > 0).then(|| (u64::from(mode.dot_clock) * 1000 / dots) as u32)
+}
+
+/// Whether anything a wallpaper cares about differs between two monitor descriptions.
+pub(crate) fn same_monitor(a: &Monitor, b: &Monitor) -> bool {
+    a.name == b.name
+        && a.physical_size() == b.physical_size()
+        && a.physical_position == b.physical_position
+        && a.refresh_rate_millihertz == b.refresh_rate_millihertz
+        && a.scale_factor == b.scale_factor
+        && a.video_modes.len() == b.video_modes.len()
+        && a.video_modes.iter().zip(&b.video_modes).all(|(a, b)| {
+            a.physical_size == b.physical_size
+                && a.bit_depth == b.bit_depth
+                && a.refresh_rate_millihertz == b.refresh_rate_millihertz
+        })
+}
+
+/// Moves and resizes fullscreen wallpaper windows to cover their monitor after it changed.
+pub(crate) fn follow_monitor_changes(
+    mut added: EventReader<MonitorAdded>,
+    mut changed: EventReader<MonitorChanged>,
+    mut removed: EventReader<MonitorRemoved>,
+    mut windows: Query<&mut Window>,
+    monitors: Query<&Monitor>,
+    primary: Query<Entity, With<PrimaryMonitor>>,
+    winit_monitors: Res<WinitMonitors>,
+) {
+    let any_changes = added.read().count() + changed.read().count() + removed.read().count() > 0;
+
+    if !any_changes {
+        return;
+    }
+
+    for mut window in &mut windows {
+        let (WindowMode::BorderlessFullscreen(selection)
+        | WindowMode::Fullscreen(selection)
+        | WindowMode::SizedFullscreen(selection)) = window.mode
+        else {
+            continue;
+        };
+
+        let entity = match selection {
+            MonitorSelection::Primary => primary.get_single().ok(),
+            MonitorSelection::Index(n) => winit_monitors.nth_entity(n),
+            MonitorSelection::Entity(entity) => Some(entity),
+            MonitorSelection::Current => None,
+        };
+
+        let Some(monitor) = entity.and_then(|entity| monitors.get(entity).ok()) else {
+            continue;
+        };
+
+        let size = monitor.physical_size();
+        let position = WindowPosition::At(monitor.physical_position);
+
+        if window.resolution.physical_size() != size {
+            window.resolution.set_physical_resolution(size.x, size.y);
+        }
+
+        if window.position != position {
+            window.position = position;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winit_config::PreviewMonitor;
+
+    #[test]
+    fn preview() {
+        let layout = PreviewLayout::side_by_side([UVec2::new(1920, 1080), UVec2::new(1280, 1024)]);
+        let monitors = preview_monitors(&layout, 1.5);
+
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(monitors[0].identity.output, "PREVIEW-1");
+        assert_eq!(monitors[1].monitor.name.as_deref(), Some("PREVIEW-2"));
+        assert_eq!(monitors[1].monitor.physical_position, IVec2::new(1920, 0));
+        assert_eq!(monitors[1].monitor.physical_size(), UVec2::new(1280, 1024));
+        assert_eq!(monitors[1].monitor.scale_factor, 1.5);
+        assert!(monitors[0].primary);
+        assert!(!monitors[1].primary);
+    }
+
+    #[test]
+    fn preview_primary() {
+        let mut layout = PreviewLayout {
+            monitors: vec![
+                PreviewMonitor::new("A", IVec2::ZERO, UVec2::new(800, 600)),
+                PreviewMonitor::new("B", IVec2::new(800, 0), UVec2::new(800, 600)),
+            ],
+        };
+        let primary = |layout: &PreviewLayout| {
+            preview_monitors(layout, 1.0)
+                .iter()
+                .map(|monitor| monitor.primary)
+                .collect::<Vec<_>>()
+        };
+
+        // The first monitor stands in when none is primary.
+        assert_eq!(primary(&layout), [true, false]);
+
+        layout.monitors[1].primary = true;
+        assert_eq!(primary(&layout), [false, true]);
+    }
+}
@@ -0,0 +1,548 @@
+use core::time::Duration;
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use bevy::{
+    asset::{Assets, Handle, RenderAssetUsages},
+    image::Image,
+};
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    event::{Event, EventWriter},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use tracing::{debug, warn};
+use zbus::{
+    blocking::{Connection, connection, fdo::DBusProxy},
+    zvariant::{Array, Dict, ObjectPath, OwnedValue},
+};
+
+/// The prefix of the bus names MPRIS players own.
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// Which players the [`MprisPlugin`] follows, which can be changed while it runs.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MprisConfig {
+    /// The address of the bus to watch, e.g. `unix:path=/tmp/test-bus`, or `None` for the session
+    /// bus.
+    pub bus: Option<String>,
+    /// Only follow players whose bus name ends in this, e.g. `spotify`. Otherwise a playing player
+    /// is followed, or the one followed last while none is playing.
+    pub player: Option<String>,
+    /// How often the players are polled.
+    pub interval: Duration,
+}
+
+impl Default for MprisConfig {
+    fn default() -> Self {
+        Self {
+            bus: None,
+            player: None,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+/// A track as described by its player's `Metadata`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Track {
+    /// The `mpris:trackid`, which tells apart tracks that share a title.
+    pub id: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub length: Option<Duration>,
+    /// The `mpris:artUrl`, which may be remote.
+    pub art_url: Option<String>,
+}
+
+/// What the followed MPRIS player is playing.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct NowPlaying {
+    /// The player followed, e.g. `spotify`, or `None` while no player runs.
+    pub player: Option<String>,
+    pub status: PlaybackStatus,
+    pub track: Option<Track>,
+    /// How far into the track playback was when the player was last polled.
+    pub position: Duration,
+    /// The cover art of the track, when its `mpris:artUrl` is a local `file://` URL. It is
+    /// decoded by its contents, as players like Chromium save it without an extension.
+    pub art: Option<Handle<Image>>,
+}
+
+/// Sent when the followed player moves to another track, or another player is followed.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct TrackChanged {
+    pub previous: Option<Track>,
+    pub current: Option<Track>,
+}
+
+/// Follows the active `org.mpris.MediaPlayer2` player on the session bus into [`NowPlaying`], so
+/// music-reactive wallpapers can show the current track and its cover art.
+///
+/// Players are polled on a background thread every [`MprisConfig::interval`]. [`TrackChanged`]
+/// is sent as tracks change, so scenes can animate the transition.
+#[derive(Default)]
+pub struct MprisPlugin {
+    pub config: MprisConfig,
+}
+
+impl Plugin for MprisPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .init_resource::<NowPlaying>()
+            .init_resource::<MprisPoller>()
+            .add_event::<TrackChanged>()
+            .add_systems(PreUpdate, (start_mpris, receive_now_playing).chain());
+    }
+}
+
+/// What a player reported when it was polled.
+#[derive(Debug, Clone)]
+struct PlayerState {
+    player: String,
+    status: PlaybackStatus,
+    track: Option<Track>,
+    position: Duration,
+    /// The decoded cover art of `track`, shared by the states polled while it plays.
+    art: Option<Arc<Image>>,
+}
+
+#[derive(Resource, Default)]
+struct MprisPoller {
+    /// The states polled, `None` while no player runs. Only locked to make the resource `Sync`.
+    states: Option<Mutex<Receiver<Option<PlayerState>>>>,
+    /// Set to stop the current poller.
+    stop: Arc<AtomicBool>,
+    /// The config being polled with.
+    config: Option<MprisConfig>,
+}
+
+/// (Re)starts the poller whenever the config changes.
+fn start_mpris(config: Res<MprisConfig>, mut poller: ResMut<MprisPoller>) {
+    if !config.is_changed() || poller.config.as_ref() == Some(&*config) {
+        return;
+    }
+
+    poller.stop.store(true, Ordering::Relaxed);
+    poller.states = None;
+    poller.config = Some(config.clone());
+
+    let (sender, states) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let (config, thread_stop) = (config.clone(), stop.clone());
+
+    let started = thread::Builder::new()
+        .name("MPRIS poller".into())
+        .spawn(move || {
+            if let Err(err) = poll_players(&config, &sender, &thread_stop) {
+                warn!("Stopped following MPRIS players: {err}");
+            }
+        });
+
+    if let Err(err) = started {
+        warn!("Could not start following MPRIS players: {err}");
+        return;
+    }
+
+    poller.states = Some(Mutex::new(states));
+    poller.stop = stop;
+}
+
+/// Moves the latest state into [`NowPlaying`], loading the cover art of new tracks.
+fn receive_now_playing(
+    mut poller: ResMut<MprisPoller>,
+    mut now_playing: ResMut<NowPlaying>,
+    mut images: ResMut<Assets<Image>>,
+    mut changed: EventWriter<TrackChanged>,
+) {
+    let Some(states) = poller.states.as_mut() else {
+        return;
+    };
+    let Some(state) = states.get_mut().unwrap().try_iter().last() else {
+        return;
+    };
+
+    let (player, status, track, position, art) = match state {
+        Some(state) => (
+            Some(state.player),
+            state.status,
+            state.track,
+            state.position,
+            state.art,
+        ),
+        None => (None, PlaybackStatus::Stopped, None, Duration::ZERO, None),
+    };
+
+    if now_playing.track != track || now_playing.player != player {
+        let art_url = track.as_ref().and_then(|track| track.art_url.as_ref());
+        let previous_art_url = now_playing
+            .track
+            .as_ref()
+            .and_then(|track| track.art_url.as_ref());
+
+        if art_url != previous_art_url {
+            now_playing.art = art.map(|art| images.add(Arc::unwrap_or_clone(art)));
+        }
+
+        if now_playing.track != track {
+            changed.send(TrackChanged {
+                previous: now_playing.track.clone(),
+                current: track.clone(),
+            });
+        }
+
+        now_playing.player = player;
+        now_playing.track = track;
+    }
+
+    if now_playing.status != status || now_playing.position != position {
+        now_playing.status = status;
+        now_playing.position = position;
+    }
+}
+
+/// Sends the state of the followed player every interval until `stop` is set.
+fn poll_players(
+    config: &MprisConfig,
+    states: &Sender<Option<PlayerState>>,
+    stop: &AtomicBool,
+) -> zbus::Result<()> {
+    let connection = match &config.bus {
+        Some(address) => connection::Builder::address(address.as_str())?.build()?,
+        None => Connection::session()?,
+    };
+    let bus = DBusProxy::new(&connection)?;
+    let mut followed: Option<String> = None;
+    let mut art = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let players: Vec<_> = bus
+            .list_names()?
+            .into_iter()
+            .map(|name| name.to_string())
+            .filter(|name| {
+                name.strip_prefix(PLAYER_PREFIX).is_some_and(|player| {
+                    config
+                        .player
+                        .as_ref()
+                        .is_none_or(|wanted| player.ends_with(wanted.as_str()))
+                })
+            })
+            .collect();
+
+        // A player can quit between being listed and being asked.
+        let mut polled: Vec<_> = players
+            .iter()
+            .filter_map(|name| player_state(&connection, name).ok())
+            .collect();
+
+        // Prefer a playing player, then the one followed last.
+        polled.sort_by_key(|state| {
+            (
+                state.status != PlaybackStatus::Playing,
+                followed.as_ref() != Some(&state.player),
+            )
+        });
+        let mut state = polled.into_iter().next();
+        followed = state.as_ref().map(|state| state.player.clone());
+
+        if let Some(state) = &mut state {
+            state.art = cover_art(state.track.as_ref(), &mut art);
+        }
+
+        if states.send(state).is_err() {
+            return Ok(());
+        }
+
+        thread::sleep(config.interval);
+    }
+
+    Ok(())
+}
+
+fn player_state(connection: &Connection, name: &str) -> zbus::Result<PlayerState> {
+    let properties: HashMap<String, OwnedValue> = connection
+        .call_method(
+            Some(name),
+            "/org/mpris/MediaPlayer2",
+            Some("org.freedesktop.DBus.Properties"),
+            "GetAll",
+            &("org.mpris.MediaPlayer2.Player",),
+        )?
+        .body()
+        .deserialize()?;
+
+    let status = match properties
+        .get("PlaybackStatus")
+        .and_then(|status| status.downcast_ref::<&str>().ok())
+    {
+        Some("Playing") => PlaybackStatus::Playing,
+        Some("Paused") => PlaybackStatus::Paused,
+        _ => PlaybackStatus::Stopped,
+    };
+
+    let track = properties
+        .get("Metadata")
+        .and_then(|metadata| metadata.downcast_ref::<&Dict>().ok())
+        .map(track)
+        .filter(|track| *track != Track::default());
+
+    // In microseconds.
+    let position = properties
+        .get("Position")
+        .and_then(|position| position.downcast_ref::<i64>().ok())
+        .map_or(Duration::ZERO, |position| {
+            Duration::from_micros(position.max(0) as u64)
+        });
+
+    Ok(PlayerState {
+        player: name.trim_start_matches(PLAYER_PREFIX).to_owned(),
+        status,
+        track,
+        position,
+        art: None,
+    })
+}
+
+/// The cover art of `track`, which is only loaded again when its URL changes.
+fn cover_art(
+    track: Option<&Track>,
+    loaded: &mut Option<(String, Option<Arc<Image>>)>,
+) -> Option<Arc<Image>> {
+    let url = track?.art_url.as_ref()?;
+
+    if loaded.as_ref().is_none_or(|(loaded, _)| loaded != url) {
+        *loaded = Some((url.clone(), load_art(url).map(Arc::new)));
+    }
+
+    loaded.as_ref()?.1.clone()
+}
+
+/// Reads and decodes the cover art at a `file://` URL, telling its format by its contents.
+fn load_art(url: &str) -> Option<Image> {
+    let Some(path) = file_url_path(url) else {
+        debug!("Not loading the cover art at {url}, it isn't a local file");
+        return None;
+    };
+
+    let decoded = fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| image::load_from_memory(&bytes).map_err(|err| err.to_string()));
+
+    match decoded {
+        Ok(art) => Some(Image::from_dynamic(art, true, RenderAssetUsages::default())),
+        Err(err) => {
+            warn!("Could not load the cover art {path:?}: {err}");
+            None
+        }
+    }
+}
+
+/// Reads the `xesam` and `mpris` fields of a player's `Metadata`.
+fn track(metadata: &Dict) -> Track {
+    let string = |key: &str| {
+        metadata
+            .get::<&str, &str>(&key)
+            .ok()
+            .flatten()
+            .map(str::to_owned)
+    };
+
+    // Track ids should be object paths, but some players send strings.
+    let id = metadata
+        .get::<&str, &ObjectPath>(&"mpris:trackid")
+        .ok()
+        .flatten()
+        .map(ToString::to_string)
+        .or_else(|| string("mpris:trackid"));
+
+    let artists = metadata
+        .get::<&str, &Array>(&"xesam:artist")
+        .ok()
+        .flatten()
+        .map(|artists| {
+            artists
+                .iter()
+                .filter_map(|artist| artist.downcast_ref::<&str>().ok())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // In microseconds, as a signed or unsigned integer depending on the player.
+    let length = metadata
+        .get::<&str, i64>(&"mpris:length")
+        .ok()
+        .flatten()
+        .map(|length| length.max(0) as u64)
+        .or_else(|| metadata.get::<&str, u64>(&"mpris:length").ok().flatten())
+        .map(Duration::from_micros);
+
+    Track {
+        id: id.unwrap_or_default(),
+        title: string("xesam:title").unwrap_or_default(),
+        artists,
+        album: string("xesam:album").unwrap_or_default(),
+        length,
+        art_url: string("mpris:artUrl").filter(|url| !url.is_empty()),
+    }
+}
+
+/// The path of a `file://` URL, with its percent-encoding decoded.
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    if !path.starts_with('/') {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(str::from_utf8(hex).ok()?, 16).ok());
+
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use image::{ImageFormat, RgbaImage};
+    use zbus::zvariant::{Str, Value};
+
+    use super::*;
+
+    #[test]
+    fn file_urls() {
+        let path = |url| file_url_path(url).map(PathBuf::into_os_string);
+
+        assert_eq!(
+            path("file:///home/me/Music/cover.jpg"),
+            Some("/home/me/Music/cover.jpg".into())
+        );
+        assert_eq!(
+            path("file://localhost/tmp/a%20b%2Fc.png"),
+            Some("/tmp/a b/c.png".into())
+        );
+        // Bytes that aren't UTF-8 survive decoding.
+        assert_eq!(
+            file_url_path("file:///tmp/%FF")
+                .unwrap()
+                .as_os_str()
+                .as_bytes(),
+            b"/tmp/\xff"
+        );
+        // Broken escapes are kept as they are.
+        assert_eq!(path("file:///tmp/100%zz%2"), Some("/tmp/100%zz%2".into()));
+        assert_eq!(path("file://cover.jpg"), None);
+        assert_eq!(path("file://otherhost/cover.jpg"), None);
+        assert_eq!(path("https://example.com/cover.jpg"), None);
+        assert_eq!(path("/tmp/cover.jpg"), None);
+    }
+
+    #[test]
+    fn metadata() {
+        let metadata = Dict::from(HashMap::from([
+            (
+                "mpris:trackid",
+                Value::from(ObjectPath::try_from("/org/mpris/track/7").unwrap()),
+            ),
+            ("xesam:title", Value::from("Teardrop")),
+            ("xesam:artist", Value::from(vec!["Massive Attack"])),
+            ("xesam:album", Value::from("Mezzanine")),
+            ("mpris:length", Value::from(330_000_000i64)),
+            ("mpris:artUrl", Value::from("")),
+        ]));
+
+        assert_eq!(
+            track(&metadata),
+            Track {
+                id: "/org/mpris/track/7".into(),
+                title: "Teardrop".into(),
+                artists: vec!["Massive Attack".into()],
+                album: "Mezzanine".into(),
+                length: Some(Duration::from_secs(330)),
+                art_url: None,
+            }
+        );
+
+        // Some players send the id as a string and the length unsigned.
+        let metadata = Dict::from(HashMap::from([
+            ("mpris:trackid", Value::from(Str::from("spotify:track:1"))),
+            ("mpris:length", Value::from(1_000_000u64)),
+            ("mpris:artUrl", Value::from("file:///tmp/cover")),
+        ]));
+        let track = track(&metadata);
+        assert_eq!(track.id, "spotify:track:1");
+        assert_eq!(track.length, Some(Duration::from_secs(1)));
+        assert_eq!(track.art_url.as_deref(), Some("file:///tmp/cover"));
+        assert!(track.title.is_empty() && track.artists.is_empty());
+    }
+
+    #[test]
+    fn art_without_an_extension() {
+        let path = env::temp_dir().join(format!("mpris-{}-cover", process::id()));
+        RgbaImage::new(3, 2)
+            .save_with_format(&path, ImageFormat::Png)
+            .unwrap();
+        let url = format!("file://{}", path.display());
+        let track = Track {
+            art_url: Some(url),
+            ..Default::default()
+        };
+
+        let mut loaded = None;
+        let art = cover_art(Some(&track), &mut loaded).unwrap();
+        assert_eq!((art.width(), art.height()), (3, 2));
+
+        // The same URL isn't read again.
+        fs::remove_file(&path).unwrap();
+        assert!(cover_art(Some(&track), &mut loaded).is_some());
+
+        let missing = Track {
+            art_url: Some("file:///nonexistent/cover".into()),
+            ..Default::default()
+        };
+        assert!(cover_art(Some(&missing), &mut loaded).is_none());
+    }
+}
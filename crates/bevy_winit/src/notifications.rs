@@ -0,0 +1,309 @@
+use core::time::Duration;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use bevy::utils::Instant;
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    event::{Event, EventWriter},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use tracing::{info, warn};
+use zbus::{
+    MatchRule,
+    blocking::{Connection, MessageIterator, connection, fdo::MonitoringProxy},
+    message::Type,
+    zvariant::OwnedValue,
+};
+
+/// The arguments of `org.freedesktop.Notifications.Notify`: the app name, the id it replaces,
+/// the icon, the summary, the body, the actions, the hints and the timeout.
+type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, OwnedValue>,
+    i32,
+);
+
+/// How urgent a notification is, as given by its `urgency` hint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+/// Sent when an app shows a desktop notification that passes the [`NotificationWatch`] rules.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct DesktopNotification {
+    /// The name the app gave, e.g. `Firefox`. May be empty.
+    pub app: String,
+    pub summary: String,
+    pub urgency: Urgency,
+}
+
+/// Which notifications the [`NotificationPlugin`] reacts to, which can be changed while it runs.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct NotificationWatch {
+    /// The address of the bus to watch, e.g. `unix:path=/tmp/test-bus`, or `None` for the session
+    /// bus.
+    pub bus: Option<String>,
+    /// Checked in order. The first rule a notification matches decides whether it is sent, and
+    /// notifications no rule matches are sent.
+    pub rules: Vec<NotificationRule>,
+    /// The shortest time between two notifications sent for the same app, so a chatty app doesn't
+    /// keep the wallpaper flashing. Critical notifications are always sent.
+    pub rate_limit: Duration,
+}
+
+impl Default for NotificationWatch {
+    fn default() -> Self {
+        Self {
+            bus: None,
+            rules: Vec::new(),
+            rate_limit: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Matches notifications by app, summary and urgency. Fields left at `None` match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationRule {
+    /// The app name, ignoring case.
+    pub app: Option<String>,
+    /// Text the summary contains, ignoring case.
+    pub summary: Option<String>,
+    /// Matches notifications less urgent than this.
+    pub below: Option<Urgency>,
+    /// Whether matching notifications are sent, or dropped.
+    pub allow: bool,
+}
+
+impl NotificationRule {
+    /// Drops the notifications of `app`.
+    pub fn ignore_app(app: impl Into<String>) -> Self {
+        Self {
+            app: Some(app.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Drops the notifications less urgent than `urgency`.
+    pub fn ignore_below(urgency: Urgency) -> Self {
+        Self {
+            below: Some(urgency),
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, notification: &DesktopNotification) -> bool {
+        self.app
+            .as_ref()
+            .is_none_or(|app| app.eq_ignore_ascii_case(&notification.app))
+            && self.summary.as_ref().is_none_or(|summary| {
+                notification
+                    .summary
+                    .to_lowercase()
+                    .contains(&summary.to_lowercase())
+            })
+            && self.below.is_none_or(|below| notification.urgency < below)
+    }
+}
+
+/// Watches the `org.freedesktop.Notifications.Notify` calls apps make and sends a
+/// [`DesktopNotification`] for each, so the wallpaper can flash, ripple or glow along.
+///
+/// The bus is monitored, so the notification daemon keeps showing the notifications as before.
+/// Pointing [`NotificationWatch::bus`] at a private bus, e.g. one started with
+/// `dbus-run-session`, keeps tests away from the real desktop.
+#[derive(Default)]
+pub struct NotificationPlugin {
+    pub watch: NotificationWatch,
+}
+
+impl Plugin for NotificationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.watch.clone())
+            .init_resource::<NotificationMonitor>()
+            .add_event::<DesktopNotification>()
+            .add_systems(
+                PreUpdate,
+                (start_notification_monitor, receive_notifications).chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct NotificationMonitor {
+    /// Notifications seen by the monitor thread. Only locked to make the resource `Sync`.
+    notifications: Option<Mutex<Receiver<DesktopNotification>>>,
+    /// The monitor's connection once it is up, closed to stop it.
+    connection: Arc<Mutex<Option<Connection>>>,
+    /// Set to stop a monitor that is still connecting.
+    stop: Arc<AtomicBool>,
+    /// The bus being watched, so changing only the rules keeps the monitor running.
+    bus: Option<Option<String>>,
+    /// When a notification was last sent for each app.
+    last_sent: HashMap<String, Instant>,
+}
+
+/// (Re)starts the monitor whenever the bus changes.
+fn start_notification_monitor(
+    watch: Res<NotificationWatch>,
+    mut monitor: ResMut<NotificationMonitor>,
+) {
+    if !watch.is_changed() || monitor.bus.as_ref() == Some(&watch.bus) {
+        return;
+    }
+
+    monitor.stop.store(true, Ordering::Relaxed);
+    if let Some(connection) = monitor.connection.lock().unwrap().take() {
+        let _ = connection.close();
+    }
+    monitor.notifications = None;
+    monitor.bus = Some(watch.bus.clone());
+
+    let (sender, notifications) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let connection = Arc::new(Mutex::new(None));
+    let bus = watch.bus.clone();
+    let (thread_stop, thread_connection) = (stop.clone(), connection.clone());
+
+    let started = thread::Builder::new()
+        .name("notification monitor".into())
+        .spawn(move || {
+            if let Err(err) = monitor_bus(bus, &sender, &thread_connection, &thread_stop) {
+                warn!("Stopped watching notifications: {err}");
+            }
+        });
+
+    if let Err(err) = started {
+        warn!("Could not start watching notifications: {err}");
+        return;
+    }
+
+    monitor.notifications = Some(Mutex::new(notifications));
+    monitor.connection = connection;
+    monitor.stop = stop;
+}
+
+/// Sends the notifications that pass the rules and the rate limit.
+fn receive_notifications(
+    watch: Res<NotificationWatch>,
+    mut monitor: ResMut<NotificationMonitor>,
+    mut sent: EventWriter<DesktopNotification>,
+) {
+    let monitor = &mut *monitor;
+    let Some(notifications) = monitor.notifications.as_mut() else {
+        return;
+    };
+    let now = Instant::now();
+
+    for notification in notifications.get_mut().unwrap().try_iter() {
+        let allowed = watch
+            .rules
+            .iter()
+            .find(|rule| rule.matches(&notification))
+            .is_none_or(|rule| rule.allow);
+        if !allowed {
+            continue;
+        }
+
+        if notification.urgency != Urgency::Critical
+            && monitor
+                .last_sent
+                .get(&notification.app)
+                .is_some_and(|last| now.duration_since(*last) < watch.rate_limit)
+        {
+            continue;
+        }
+
+        monitor.last_sent.insert(notification.app.clone(), now);
+        sent.send(notification);
+    }
+}
+
+/// Turns a connection to `bus` into a monitor of `Notify` calls and sends what it sees until the
+/// connection is closed.
+fn monitor_bus(
+    bus: Option<String>,
+    notifications: &Sender<DesktopNotification>,
+    connection_slot: &Mutex<Option<Connection>>,
+    stop: &AtomicBool,
+) -> zbus::Result<()> {
+    let connection = match bus {
+        Some(address) => connection::Builder::address(address.as_str())?.build()?,
+        None => Connection::session()?,
+    };
+
+    let rule = MatchRule::builder()
+        .msg_type(Type::MethodCall)
+        .interface("org.freedesktop.Notifications")?
+        .member("Notify")?
+        .build();
+    MonitoringProxy::new(&connection)?.become_monitor(&[rule], 0)?;
+
+    *connection_slot.lock().unwrap() = Some(connection.clone());
+    // Stopped while connecting, before the connection could be closed.
+    if stop.load(Ordering::Relaxed) {
+        return connection.close();
+    }
+    info!("Watching desktop notifications");
+
+    for message in MessageIterator::from(connection) {
+        let message = match message {
+            Ok(message) => message,
+            // Closed to stop the monitor.
+            Err(_) if stop.load(Ordering::Relaxed) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let header = message.header();
+        if header.member().is_none_or(|member| member != "Notify") {
+            continue;
+        }
+
+        let (app, _, _, summary, _, _, hints, _) = match message.body().deserialize::<NotifyArgs>()
+        {
+            Ok(args) => args,
+            Err(err) => {
+                warn!("Ignoring a malformed notification: {err}");
+                continue;
+            }
+        };
+
+        let urgency = match hints
+            .get("urgency")
+            .and_then(|urgency| u8::try_from(urgency).ok())
+        {
+            Some(0) => Urgency::Low,
+            Some(2) => Urgency::Critical,
+            _ => Urgency::Normal,
+        };
+
+        let notification = DesktopNotification {
+            app,
+            summary,
+            urgency,
+        };
+        if notifications.send(notification).is_err() {
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
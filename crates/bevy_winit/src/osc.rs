@@ -0,0 +1,663 @@
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    io::{self, ErrorKind},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use bevy::ecs::reflect::AppTypeRegistry;
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    event::{Event, Events},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+    world::World,
+};
+use serde_json::{Number, Value};
+use tracing::{debug, info, warn};
+use winit::event_loop::EventLoopProxy;
+
+use crate::{
+    EventLoopProxyWrapper, WakeUp, scene_params::SceneParam, wallpaper_command::WallpaperCommand,
+};
+
+/// How often the server checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The largest packet read. UDP can't carry more.
+const MAX_PACKET_SIZE: usize = 65536;
+
+/// Where the [`OscPlugin`] listens, which can be changed while it runs.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OscServer {
+    /// Defaults to port 9000 on localhost. Anyone who can reach the port can change the scene.
+    pub address: SocketAddr,
+}
+
+impl Default for OscServer {
+    fn default() -> Self {
+        Self::on_port(9000)
+    }
+}
+
+impl OscServer {
+    /// Listens on `port` on localhost.
+    pub fn on_port(port: u16) -> Self {
+        Self {
+            address: SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        }
+    }
+}
+
+/// An OSC message received by the [`OscPlugin`], sent whether or not it was understood.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    /// The address pattern, e.g. `/scene/Weather/cloud_cover`.
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Blob(Vec<u8>),
+    Long(i64),
+    Double(f64),
+    Bool(bool),
+    Nil,
+    Impulse,
+}
+
+impl OscArg {
+    fn to_json(&self) -> Value {
+        match self {
+            Self::Int(int) => Value::from(*int),
+            Self::Float(float) => {
+                Number::from_f64(f64::from(*float)).map_or(Value::Null, Value::Number)
+            }
+            Self::String(string) => Value::from(string.as_str()),
+            Self::Blob(blob) => Value::from(blob.as_slice()),
+            Self::Long(long) => Value::from(*long),
+            Self::Double(double) => Number::from_f64(*double).map_or(Value::Null, Value::Number),
+            Self::Bool(bool) => Value::from(*bool),
+            Self::Nil => Value::Null,
+            // A trigger without a value, e.g. a button press.
+            Self::Impulse => Value::Bool(true),
+        }
+    }
+
+    /// Whether the argument is a true, nonzero or non-empty value.
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Int(int) => *int != 0,
+            Self::Float(float) => *float != 0.0,
+            Self::String(string) => !string.is_empty(),
+            Self::Blob(blob) => !blob.is_empty(),
+            Self::Long(long) => *long != 0,
+            Self::Double(double) => *double != 0.0,
+            Self::Bool(bool) => *bool,
+            Self::Nil => false,
+            Self::Impulse => true,
+        }
+    }
+}
+
+/// Listens for OSC over UDP, so TouchOSC, SuperCollider or a lighting desk can drive the
+/// wallpaper live.
+///
+/// - `/scene/<resource>/<field>` sets a field of a reflected resource, like a
+///   [`FeedBinding`](crate::FeedBinding) does. Further segments reach into nested fields, so
+///   `/scene/Weather/sky/cloud_cover 0.4` sets `sky.cloud_cover`. Several arguments are set as a
+///   list.
+/// - `/wallpaper/pause`, `/wallpaper/resume` and `/wallpaper/next` send a [`WallpaperCommand`].
+///   `/wallpaper/pause` with a false or zero argument resumes instead, for toggle buttons.
+///
+/// Every message is also sent as an [`OscMessage`]. Messages wake the event loop through the
+/// [`EventLoopProxy`] of `T`, so a `Reactive` [`UpdateMode`](crate::UpdateMode) responds at once.
+#[derive(Default)]
+pub struct OscPlugin<T: Event + Default = WakeUp> {
+    pub server: OscServer,
+    marker: PhantomData<T>,
+}
+
+impl<T: Event + Default> OscPlugin<T> {
+    pub fn new(server: OscServer) -> Self {
+        Self {
+            server,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Event + Default> Plugin for OscPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.server)
+            .init_resource::<OscReceiver>()
+            .add_event::<OscMessage>()
+            .add_event::<WallpaperCommand>()
+            .add_systems(PreUpdate, (start_osc_server::<T>, receive_osc).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+struct OscReceiver {
+    /// Messages read by the server thread. Only locked to make the resource `Sync`.
+    messages: Option<Mutex<Receiver<OscMessage>>>,
+    /// Set to stop the current server.
+    stop: Arc<AtomicBool>,
+    /// Where the current server listens, so setting the same address keeps it running.
+    address: Option<SocketAddr>,
+}
+
+/// (Re)starts the server whenever its address changes.
+fn start_osc_server<T: Event + Default>(
+    server: Res<OscServer>,
+    mut receiver: ResMut<OscReceiver>,
+    proxy: Option<Res<EventLoopProxyWrapper<T>>>,
+) {
+    if !server.is_changed() || receiver.address == Some(server.address) {
+        return;
+    }
+
+    receiver.stop.store(true, Ordering::Relaxed);
+    receiver.messages = None;
+    receiver.address = None;
+
+    let socket = match UdpSocket::bind(server.address) {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!("Could not listen for OSC on {}: {err}", server.address);
+            return;
+        }
+    };
+
+    let (sender, messages) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let proxy = proxy.map(|proxy| EventLoopProxy::clone(&proxy));
+    let thread_stop = stop.clone();
+
+    let started = thread::Builder::new()
+        .name("OSC server".into())
+        .spawn(move || serve(&socket, &sender, proxy.as_ref(), &thread_stop));
+
+    match started {
+        Ok(_) => {
+            info!("Listening for OSC on {}", server.address);
+            receiver.messages = Some(Mutex::new(messages));
+            receiver.stop = stop;
+            receiver.address = Some(server.address);
+        }
+        Err(err) => warn!("Could not start the OSC server: {err}"),
+    }
+}
+
+/// Reads packets until `stop` is set, waking the event loop after each one.
+fn serve<T: Event + Default>(
+    socket: &UdpSocket,
+    messages: &Sender<OscMessage>,
+    proxy: Option<&EventLoopProxy<T>>,
+    stop: &AtomicBool,
+) {
+    let mut packet = vec![0; MAX_PACKET_SIZE];
+
+    if let Err(err) = socket.set_read_timeout(Some(STOP_POLL_INTERVAL)) {
+        warn!("Could not set up the OSC server: {err}");
+        return;
+    }
+
+    while !stop.load(Ordering::Relaxed) {
+        let (len, from) = match socket.recv_from(&mut packet) {
+            Ok(received) => received,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                continue;
+            }
+            Err(err) => {
+                warn!("Could not read OSC: {err}");
+                return;
+            }
+        };
+
+        let mut parsed = Vec::new();
+        if let Err(err) = parse_packet(&packet[..len], &mut parsed) {
+            debug!("Ignoring a malformed OSC packet from {from}: {err}");
+            continue;
+        }
+
+        for message in parsed {
+            if messages.send(message).is_err() {
+                return;
+            }
+        }
+
+        // The event loop may have exited, in which case nobody is left to wake.
+        if let Some(proxy) = proxy {
+            let _ = proxy.send_event(T::default());
+        }
+    }
+}
+
+/// Sends the received messages as events and applies the ones addressed to the wallpaper.
+fn receive_osc(world: &mut World) {
+    let Some(messages) = world
+        .resource_mut::<OscReceiver>()
+        .messages
+        .as_mut()
+        .map(|messages| messages.get_mut().unwrap().try_iter().collect::<Vec<_>>())
+    else {
+        return;
+    };
+
+    for message in messages {
+        let segments: Vec<_> = message.address.split('/').skip(1).collect();
+
+        match segments.as_slice() {
+            ["wallpaper", "pause"] => {
+                let pause = message.args.first().is_none_or(OscArg::is_truthy);
+                world.send_event(if pause {
+                    WallpaperCommand::Pause
+                } else {
+                    WallpaperCommand::Resume
+                });
+            }
+            ["wallpaper", "resume"] => {
+                world.send_event(WallpaperCommand::Resume);
+            }
+            ["wallpaper", "next"] => {
+                world.send_event(WallpaperCommand::Next);
+            }
+            ["scene", resource, field @ ..] if !field.is_empty() => {
+                if let Err(err) = set_scene_param(world, resource, &field.join("."), &message.args)
+                {
+                    warn!("Could not apply OSC message {}: {err}", message.address);
+                }
+            }
+            _ => {}
+        }
+
+        world.resource_mut::<Events<OscMessage>>().send(message);
+    }
+}
+
+fn set_scene_param(
+    world: &mut World,
+    resource: &str,
+    field: &str,
+    args: &[OscArg],
+) -> Result<(), String> {
+    let registry = world
+        .get_resource::<AppTypeRegistry>()
+        .ok_or("there is no type registry")?
+        .clone();
+
+    let value = match args {
+        [] => return Err("it has no arguments".into()),
+        [arg] => arg.to_json(),
+        args => args.iter().map(OscArg::to_json).collect(),
+    };
+
+    SceneParam { resource, field }.set(world, &registry.read(), &value)
+}
+
+/// Parses an OSC message or bundle into `messages`. Bundles are unpacked at once, ignoring their
+/// time tags.
+fn parse_packet(packet: &[u8], messages: &mut Vec<OscMessage>) -> io::Result<()> {
+    let mut reader = OscReader(packet);
+
+    if packet.starts_with(b"#bundle\0") {
+        reader.string()?;
+        // The time tag.
+        reader.take(8)?;
+
+        while !reader.0.is_empty() {
+            let len = reader.int()?;
+            let element = reader.take(usize::try_from(len).map_err(invalid)?)?;
+            parse_packet(element, messages)?;
+        }
+
+        return Ok(());
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(invalid("the address doesn't start with /"));
+    }
+
+    // Very old senders leave out the type tags of messages without arguments.
+    let tags = if reader.0.is_empty() {
+        String::from(",")
+    } else {
+        reader.string()?
+    };
+    let tags = tags
+        .strip_prefix(',')
+        .ok_or_else(|| invalid("the type tags don't start with ,"))?;
+
+    let args = tags
+        .chars()
+        .map(|tag| {
+            Ok(match tag {
+                'i' => OscArg::Int(reader.int()?),
+                'f' => OscArg::Float(f32::from_bits(reader.int()? as u32)),
+                's' | 'S' => OscArg::String(reader.string()?),
+                'b' => {
+                    let len = usize::try_from(reader.int()?).map_err(invalid)?;
+                    let blob = reader.take(len)?.to_vec();
+                    reader.take(padding(len))?;
+                    OscArg::Blob(blob)
+                }
+                'h' => OscArg::Long(reader.long()?),
+                'd' => OscArg::Double(f64::from_bits(reader.long()? as u64)),
+                'T' => OscArg::Bool(true),
+                'F' => OscArg::Bool(false),
+                'N' => OscArg::Nil,
+                'I' => OscArg::Impulse,
+                tag => return Err(invalid(format!("unsupported type tag {tag:?}"))),
+            })
+        })
+        .collect::<io::Result<_>>()?;
+
+    messages.push(OscMessage { address, args });
+    Ok(())
+}
+
+/// Reads big-endian values and 4-byte aligned strings off the front of a packet.
+struct OscReader<'a>(&'a [u8]);
+
+impl<'a> OscReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn int(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn long(&mut self) -> io::Result<i64> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self
+            .0
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| invalid("a string isn't terminated"))?;
+        let string = String::from_utf8(self.take(len)?.to_vec()).map_err(invalid)?;
+        // The terminator is part of the padding.
+        self.take(padding(len + 1) + 1)?;
+        Ok(string)
+    }
+}
+
+/// How many bytes pad `len` bytes to a multiple of 4.
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+fn invalid(err: impl Into<Box<dyn core::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::reflect::ReflectResource, reflect::Reflect};
+
+    use super::*;
+
+    /// A string padded to a multiple of 4 bytes, terminator included.
+    fn string(string: &str) -> Vec<u8> {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize(string.len() + 1 + padding(string.len() + 1), 0);
+        bytes
+    }
+
+    fn message(address: &str, tags: &str, args: &[&[u8]]) -> Vec<u8> {
+        let mut packet = string(address);
+        packet.extend(string(tags));
+        packet.extend(args.concat());
+        packet
+    }
+
+    fn parse(packet: &[u8]) -> io::Result<Vec<OscMessage>> {
+        let mut messages = Vec::new();
+        parse_packet(packet, &mut messages).map(|()| messages)
+    }
+
+    #[test]
+    fn arguments() {
+        let packet = message(
+            "/scene/Weather/wind",
+            ",ifsbhdTFNI",
+            &[
+                &7i32.to_be_bytes(),
+                &0.5f32.to_bits().to_be_bytes(),
+                &string("hello"),
+                &[0, 0, 0, 5, 1, 2, 3, 4, 5, 0, 0, 0],
+                &(-3i64).to_be_bytes(),
+                &0.25f64.to_bits().to_be_bytes(),
+            ],
+        );
+
+        assert_eq!(
+            parse(&packet).unwrap(),
+            [OscMessage {
+                address: "/scene/Weather/wind".into(),
+                args: vec![
+                    OscArg::Int(7),
+                    OscArg::Float(0.5),
+                    OscArg::String("hello".into()),
+                    OscArg::Blob(vec![1, 2, 3, 4, 5]),
+                    OscArg::Long(-3),
+                    OscArg::Double(0.25),
+                    OscArg::Bool(true),
+                    OscArg::Bool(false),
+                    OscArg::Nil,
+                    OscArg::Impulse,
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_type_tags() {
+        assert_eq!(
+            parse(&string("/wallpaper/next")).unwrap(),
+            [OscMessage {
+                address: "/wallpaper/next".into(),
+                args: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn nested_bundles() {
+        let first = message("/a", ",i", &[&1i32.to_be_bytes()]);
+        let second = message("/b", ",", &[]);
+
+        let mut inner = string("#bundle");
+        inner.extend([0; 8]);
+        inner.extend((second.len() as i32).to_be_bytes());
+        inner.extend(&second);
+
+        let mut bundle = string("#bundle");
+        bundle.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        for element in [&first, &inner] {
+            bundle.extend((element.len() as i32).to_be_bytes());
+            bundle.extend(element);
+        }
+
+        let addresses: Vec<_> = parse(&bundle)
+            .unwrap()
+            .into_iter()
+            .map(|message| message.address)
+            .collect();
+        assert_eq!(addresses, ["/a", "/b"]);
+    }
+
+    #[test]
+    fn malformed_packets() {
+        let truncated = message("/a", ",i", &[&[0, 0]]);
+        let unterminated = b"/abc".to_vec();
+        let relative = message("a", ",", &[]);
+        let untagged = message("/a", "i", &[&[0; 4]]);
+        let unknown = message("/a", ",x", &[]);
+        let mut oversized = string("#bundle");
+        oversized.extend([0; 8]);
+        oversized.extend(100i32.to_be_bytes());
+
+        for packet in [
+            truncated,
+            unterminated,
+            relative,
+            untagged,
+            unknown,
+            oversized,
+        ] {
+            assert!(parse(&packet).is_err(), "{packet:?}");
+        }
+    }
+
+    #[derive(Resource, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Resource)]
+    struct Weather {
+        cloud_cover: f32,
+        sky: Sky,
+        wind: Vec<f32>,
+    }
+
+    #[derive(Reflect, Default, Debug, PartialEq)]
+    struct Sky {
+        label: String,
+    }
+
+    /// Applies `messages` like the server received them, returning the commands they sent.
+    fn receive(world: &mut World, messages: Vec<OscMessage>) -> Vec<WallpaperCommand> {
+        let (sender, receiver) = mpsc::channel();
+        for message in messages {
+            sender.send(message).unwrap();
+        }
+        world.resource_mut::<OscReceiver>().messages = Some(Mutex::new(receiver));
+
+        receive_osc(world);
+
+        world
+            .resource::<Events<WallpaperCommand>>()
+            .iter_current_update_events()
+            .copied()
+            .collect()
+    }
+
+    fn world() -> World {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Weather>();
+
+        let mut world = World::new();
+        world.insert_resource(registry);
+        world.init_resource::<Weather>();
+        world.init_resource::<OscReceiver>();
+        world.init_resource::<Events<OscMessage>>();
+        world.init_resource::<Events<WallpaperCommand>>();
+        world
+    }
+
+    fn osc(address: &str, args: Vec<OscArg>) -> OscMessage {
+        OscMessage {
+            address: address.into(),
+            args,
+        }
+    }
+
+    #[test]
+    fn scene_addresses() {
+        let mut world = world();
+
+        receive(
+            &mut world,
+            vec![
+                osc("/scene/Weather/cloud_cover", vec![OscArg::Float(0.4)]),
+                osc(
+                    "/scene/Weather/sky/label",
+                    vec![OscArg::String("dusk".into())],
+                ),
+                osc(
+                    "/scene/Weather/wind",
+                    vec![OscArg::Double(1.0), OscArg::Int(2)],
+                ),
+                // Neither of these names a field.
+                osc("/scene/Weather", vec![OscArg::Float(1.0)]),
+                osc("/scene/Weather/missing", vec![OscArg::Float(1.0)]),
+            ],
+        );
+
+        assert_eq!(
+            *world.resource::<Weather>(),
+            Weather {
+                cloud_cover: 0.4,
+                sky: Sky {
+                    label: "dusk".into(),
+                },
+                wind: vec![1.0, 2.0],
+            }
+        );
+    }
+
+    #[test]
+    fn every_message_is_an_event() {
+        let mut world = world();
+        let messages = vec![
+            osc("/scene/Weather/cloud_cover", vec![OscArg::Float(0.4)]),
+            osc("/unknown", Vec::new()),
+        ];
+
+        receive(&mut world, messages.clone());
+
+        let received: Vec<_> = world
+            .resource::<Events<OscMessage>>()
+            .iter_current_update_events()
+            .cloned()
+            .collect();
+        assert_eq!(received, messages);
+    }
+
+    #[test]
+    fn wallpaper_addresses() {
+        let mut world = world();
+
+        let commands = receive(
+            &mut world,
+            vec![
+                osc("/wallpaper/pause", Vec::new()),
+                osc("/wallpaper/pause", vec![OscArg::Float(0.0)]),
+                osc("/wallpaper/pause", vec![OscArg::Bool(true)]),
+                osc("/wallpaper/resume", Vec::new()),
+                osc("/wallpaper/next", vec![OscArg::Impulse]),
+                osc("/wallpaper/unknown", Vec::new()),
+            ],
+        );
+
+        assert_eq!(
+            commands,
+            [
+                WallpaperCommand::Pause,
+                WallpaperCommand::Resume,
+                WallpaperCommand::Pause,
+                WallpaperCommand::Resume,
+                WallpaperCommand::Next,
+            ]
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use core::time::Duration;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[cfg(feature = "pbr")]
+use bevy::{
+    asset::Assets,
+    pbr::{MeshMaterial3d, StandardMaterial, wireframe::WireframeConfig},
+};
+use bevy::{
+    color::{Color, ColorToPacked, Srgba},
+    render::camera::ClearColor,
+    utils::Instant,
+};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    event::EventReader,
+    system::{Local, Res, ResMut, Resource},
+};
+#[cfg(feature = "pbr")]
+use bevy_ecs::{component::Component, system::Query, world::Ref};
+use serde_json::{Map, Value, json};
+use tracing::warn;
+use x11rb::{
+    errors::ReplyError,
+    protocol::{
+        Event as X11Event,
+        xproto::{AtomEnum, ConnectionExt as _},
+    },
+};
+
+use crate::{raw_x11::RawX11Event, x11_desktop::X11Desktop};
+
+/// Catppuccin Mocha, used until a desktop palette is found.
+const DEFAULT_COLORS: [u32; 16] = [
+    0x45475a, 0xf38ba8, 0xa6e3a1, 0xf9e2af, 0x89b4fa, 0xf5c2e7, 0x94e2d5, 0xbac2de, 0x585b70,
+    0xf38ba8, 0xa6e3a1, 0xf9e2af, 0x89b4fa, 0xf5c2e7, 0x94e2d5, 0xa6adc8,
+];
+const DEFAULT_BACKGROUND: u32 = 0x1e1e2e;
+const DEFAULT_FOREGROUND: u32 = 0xcdd6f4;
+
+/// Where a [`DesktopPalette`] was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteSource {
+    /// A pywal `colors.json`.
+    Pywal(PathBuf),
+    /// The Xresources in the `RESOURCE_MANAGER` property of the root window.
+    Xresources,
+}
+
+/// A color of the [`DesktopPalette`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteSlot {
+    /// One of the sixteen terminal colors, `color0` to `color15`.
+    Color(u8),
+    Background,
+    Foreground,
+}
+
+/// The colors the desktop is themed with, kept up to date while the wallpaper runs.
+///
+/// Loaded from pywal's `colors.json` if it exists, or else from the `*color0` to `*color15`,
+/// `*background` and `*foreground` Xresources. See [`PaletteConfig`].
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct DesktopPalette {
+    pub colors: [Color; 16],
+    pub background: Color,
+    pub foreground: Color,
+    /// `None` while the built-in palette is used.
+    pub source: Option<PaletteSource>,
+}
+
+impl Default for DesktopPalette {
+    fn default() -> Self {
+        Self {
+            colors: DEFAULT_COLORS.map(rgb),
+            background: rgb(DEFAULT_BACKGROUND),
+            foreground: rgb(DEFAULT_FOREGROUND),
+            source: None,
+        }
+    }
+}
+
+impl DesktopPalette {
+    /// The color in `slot`. Terminal colors past `color15` wrap around.
+    pub fn get(&self, slot: PaletteSlot) -> Color {
+        match slot {
+            PaletteSlot::Color(n) => self.colors[usize::from(n) % 16],
+            PaletteSlot::Background => self.background,
+            PaletteSlot::Foreground => self.foreground,
+        }
+    }
+
+    /// Reads a pywal `colors.json`. Colors missing from it keep their default.
+    ///
+    /// Returns `None` if the file isn't JSON or has no colors at all.
+    pub fn from_pywal(json: &str) -> Option<Self> {
+        let json: Value = serde_json::from_str(json).ok()?;
+        let color_at = |pointer: &str| json.pointer(pointer)?.as_str().and_then(parse_color);
+
+        let mut palette = Self::default();
+        let mut found = false;
+
+        for (n, color) in palette.colors.iter_mut().enumerate() {
+            if let Some(value) = color_at(&format!("/colors/color{n}")) {
+                *color = value;
+                found = true;
+            }
+        }
+
+        for (pointer, color) in [
+            ("/special/background", &mut palette.background),
+            ("/special/foreground", &mut palette.foreground),
+        ] {
+            if let Some(value) = color_at(pointer) {
+                *color = value;
+                found = true;
+            }
+        }
+
+        found.then_some(palette)
+    }
+
+    /// Reads the colors from an Xresources database like the `RESOURCE_MANAGER` property.
+    ///
+    /// Only resources for every program (`*color1`, `*.background`) are used, not those of one
+    /// program like `URxvt.background`. Returns `None` if there are no color resources.
+    pub fn from_xresources(resources: &str) -> Option<Self> {
+        let mut palette = Self::default();
+        let mut found = false;
+
+        for line in resources.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim();
+            let Some(name) = key.strip_prefix("*.").or_else(|| key.strip_prefix('*')) else {
+                continue;
+            };
+            let Some(value) = parse_color(value) else {
+                continue;
+            };
+
+            let slot = match name {
+                "background" => &mut palette.background,
+                "foreground" => &mut palette.foreground,
+                name => match name
+                    .strip_prefix("color")
+                    .and_then(|n| n.parse::<usize>().ok())
+                {
+                    Some(n) if n < 16 => &mut palette.colors[n],
+                    _ => continue,
+                },
+            };
+
+            *slot = value;
+            found = true;
+        }
+
+        found.then_some(palette)
+    }
+
+    /// The palette as a pywal `colors.json`.
+    pub fn to_pywal(&self) -> String {
+        let colors: Map<String, Value> = self
+            .colors
+            .iter()
+            .enumerate()
+            .map(|(n, color)| (format!("color{n}"), to_hex(*color).into()))
+            .collect();
+
+        let json = json!({
+            "special": {
+                "background": to_hex(self.background),
+                "foreground": to_hex(self.foreground),
+                "cursor": to_hex(self.foreground),
+            },
+            "colors": colors,
+        });
+
+        format!("{json:#}\n")
+    }
+
+    /// The palette as Xresources, ready for `xrdb -merge`.
+    pub fn to_xresources(&self) -> String {
+        let mut resources = format!(
+            "*background: {}\n*foreground: {}\n",
+            to_hex(self.background),
+            to_hex(self.foreground)
+        );
+
+        for (n, color) in self.colors.iter().enumerate() {
+            resources += &format!("*color{n}: {}\n", to_hex(*color));
+        }
+
+        resources
+    }
+
+    /// The palette as plain JSON, with the terminal colors in an array.
+    pub fn to_json(&self) -> String {
+        let json = json!({
+            "background": to_hex(self.background),
+            "foreground": to_hex(self.foreground),
+            "colors": self.colors.map(to_hex),
+        });
+
+        format!("{json:#}\n")
+    }
+}
+
+fn rgb(hex: u32) -> Color {
+    let [_, red, green, blue] = hex.to_be_bytes();
+    Srgba::rgb_u8(red, green, blue).into()
+}
+
+fn to_hex(color: Color) -> String {
+    let [red, green, blue] = color.to_srgba().to_u8_array_no_alpha();
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+/// Parses `#rrggbb`-style hex colors and X11 `rgb:r/g/b` colors.
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+
+    let Some(channels) = value.strip_prefix("rgb:") else {
+        return Srgba::hex(value).ok().map(Into::into);
+    };
+
+    // Each channel has one to four hex digits, scaled to its own maximum.
+    let channel = |digits: &str| {
+        if !(1..=4).contains(&digits.len()) {
+            return None;
+        }
+
+        let max = (1u32 << (4 * digits.len())) - 1;
+        let value = u32::from_str_radix(digits, 16).ok()?;
+
+        Some(value as f32 / max as f32)
+    };
+
+    let mut channels = channels.split('/').map(channel);
+    let (Some(red), Some(green), Some(blue), None) = (
+        channels.next()?,
+        channels.next()?,
+        channels.next()?,
+        channels.next(),
+    ) else {
+        return None;
+    };
+
+    Some(Srgba::rgb(red, green, blue).into())
+}
+
+/// Where the [`DesktopPalette`] is loaded from.
+#[derive(Resource, Debug, Clone)]
+pub struct PaletteConfig {
+    /// pywal's `colors.json`, which is preferred while it exists. `None` ignores pywal.
+    pub pywal: Option<PathBuf>,
+    /// Fall back to the Xresources colors.
+    pub xresources: bool,
+    /// How often the pywal file is checked for changes. Xresources changes are seen right away.
+    pub poll_interval: Duration,
+}
+
+impl Default for PaletteConfig {
+    fn default() -> Self {
+        let cache = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")));
+
+        Self {
+            pywal: cache.map(|cache| cache.join("wal/colors.json")),
+            xresources: true,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl PaletteConfig {
+    /// Loads the palette from the first source that has one, or the built-in palette.
+    pub(crate) fn load(&self, desktop: &X11Desktop) -> DesktopPalette {
+        if let Some(path) = &self.pywal {
+            match fs::read_to_string(path) {
+                Ok(json) => match DesktopPalette::from_pywal(&json) {
+                    Some(palette) => {
+                        return DesktopPalette {
+                            source: Some(PaletteSource::Pywal(path.clone())),
+                            ..palette
+                        };
+                    }
+                    None => warn!("Ignoring {path:?}: not a pywal color scheme"),
+                },
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => warn!("Could not read {path:?}: {err}"),
+            }
+        }
+
+        if self.xresources {
+            match read_xresources(desktop) {
+                Ok(resources) => {
+                    if let Some(palette) = DesktopPalette::from_xresources(&resources) {
+                        return DesktopPalette {
+                            source: Some(PaletteSource::Xresources),
+                            ..palette
+                        };
+                    }
+                }
+                Err(err) => warn!("Could not read Xresources: {err}"),
+            }
+        }
+
+        DesktopPalette::default()
+    }
+}
+
+fn read_xresources(desktop: &X11Desktop) -> Result<String, ReplyError> {
+    let value = desktop
+        .connection()
+        .get_property(
+            false,
+            desktop.root(),
+            desktop.atoms().RESOURCE_MANAGER,
+            AtomEnum::STRING,
+            0,
+            u32::MAX / 4,
+        )?
+        .reply()?
+        .value;
+
+    Ok(String::from_utf8_lossy(&value).into_owned())
+}
+
+/// Reloads the [`DesktopPalette`] when the pywal file or the Xresources change.
+pub(crate) fn follow_desktop_palette(
+    mut raw_events: EventReader<RawX11Event>,
+    desktop: Res<X11Desktop>,
+    config: Res<PaletteConfig>,
+    mut palette: ResMut<DesktopPalette>,
+    mut pywal_modified: Local<Option<SystemTime>>,
+    mut next_poll: Local<Option<Instant>>,
+) {
+    let root = desktop.root();
+    let resource_manager = desktop.atoms().RESOURCE_MANAGER;
+
+    let mut reload = config.is_changed();
+
+    for RawX11Event { event } in raw_events.read() {
+        if let X11Event::PropertyNotify(event) = event {
+            reload |= event.window == root && event.atom == resource_manager;
+        }
+    }
+
+    let now = Instant::now();
+    if next_poll.is_none_or(|at| at <= now) {
+        *next_poll = now.checked_add(config.poll_interval);
+
+        let modified = config
+            .pywal
+            .as_deref()
+            .and_then(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok());
+
+        if modified != *pywal_modified {
+            *pywal_modified = modified;
+            reload = true;
+        }
+    }
+
+    if reload {
+        palette.set_if_neq(config.load(&desktop));
+    }
+}
+
+/// Which palette colors are kept applied to the scene, following every palette change.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PaletteBindings {
+    /// Sets the [`ClearColor`].
+    pub clear_color: Option<PaletteSlot>,
+    /// Sets [`WireframeConfig::default_color`].
+    #[cfg(feature = "pbr")]
+    pub wireframe: Option<PaletteSlot>,
+}
+
+/// Keeps the base color of the entity's [`StandardMaterial`] at a palette color.
+///
+/// Entities bound to different slots need their own materials, since the color is written to
+/// the material asset.
+#[cfg(feature = "pbr")]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteMaterial(pub PaletteSlot);
+
+pub(crate) fn bind_palette_clear_color(
+    palette: Res<DesktopPalette>,
+    bindings: Res<PaletteBindings>,
+    clear_color: Option<ResMut<ClearColor>>,
+) {
+    let (Some(slot), Some(mut clear_color)) = (bindings.clear_color, clear_color) else {
+        return;
+    };
+
+    let color = palette.get(slot);
+    if (palette.is_changed() || bindings.is_changed()) && clear_color.0 != color {
+        clear_color.0 = color;
+    }
+}
+
+#[cfg(feature = "pbr")]
+pub(crate) fn bind_palette_materials(
+    palette: Res<DesktopPalette>,
+    bindings: Res<PaletteBindings>,
+    wireframe: Option<ResMut<WireframeConfig>>,
+    bound: Query<(Ref<PaletteMaterial>, &MeshMaterial3d<StandardMaterial>)>,
+    materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    let rebind_all = palette.is_changed() || bindings.is_changed();
+
+    if let (true, Some(slot), Some(mut wireframe)) = (rebind_all, bindings.wireframe, wireframe) {
+        let color = palette.get(slot);
+        if wireframe.default_color != color {
+            wireframe.default_color = color;
+        }
+    }
+
+    let Some(mut materials) = materials else {
+        return;
+    };
+
+    for (binding, material) in &bound {
+        if !rebind_all && !binding.is_changed() {
+            continue;
+        }
+
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.base_color = palette.get(binding.0);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use core::{mem, time::Duration};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use bevy::{
+    color::{Color, ColorToPacked, Luminance, Srgba},
+    image::Image,
+    render::view::screenshot::{Screenshot, ScreenshotCaptured},
+    utils::Instant,
+};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::{Event, EventWriter},
+    observer::Trigger,
+    query::With,
+    system::{Commands, Local, Query, Res, ResMut, Resource},
+};
+use bevy_window::{PrimaryWindow, Window};
+use tracing::warn;
+
+use crate::palette::DesktopPalette;
+
+/// The longest side of the frame the palette is extracted from, in samples.
+const SAMPLE_SIZE: u32 = 128;
+
+/// Extracts a palette from what the wallpaper shows, for terminals and bars to follow.
+///
+/// Inserting this resource turns extraction on. Every `interval` a frame of the window is read
+/// back and reduced to sixteen colors, sorted from dark to light, with the darkest as the
+/// background and the lightest as the foreground. Each new palette is sent as a
+/// [`PaletteExtracted`] event and written to `directory` as `colors.json` (pywal),
+/// `colors.Xresources` and `palette.json`.
+#[derive(Resource, Debug, Clone)]
+pub struct PaletteExport {
+    /// The window to read back, or `None` for the primary window.
+    pub window: Option<Entity>,
+    pub interval: Duration,
+    /// Where the palette files are written, or `None` to only send the event.
+    ///
+    /// Defaults to `$XDG_CACHE_HOME/bevy-linux-wallpaper`. Pointing this at `~/.cache/wal` while
+    /// [`PaletteConfig`](crate::PaletteConfig) reads pywal there feeds the palette back into the
+    /// wallpaper.
+    pub directory: Option<PathBuf>,
+    /// A shell command run with `sh -c` after the files are written, e.g.
+    /// `xrdb -merge ~/.cache/bevy-linux-wallpaper/colors.Xresources`.
+    pub hook: Option<String>,
+}
+
+impl Default for PaletteExport {
+    fn default() -> Self {
+        let cache = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")));
+
+        Self {
+            window: None,
+            interval: Duration::from_secs(30),
+            directory: cache.map(|cache| cache.join("bevy-linux-wallpaper")),
+            hook: None,
+        }
+    }
+}
+
+/// Sent when [`PaletteExport`] extracted a palette that differs from the previous one.
+#[derive(Event, Debug, Clone)]
+pub struct PaletteExtracted {
+    /// The window the palette was read from.
+    pub window: Entity,
+    pub palette: DesktopPalette,
+}
+
+/// Marks the screenshot taken for [`PaletteExport`], so screenshots taken by the app itself are
+/// left alone.
+#[derive(Component)]
+pub(crate) struct PaletteCapture {
+    window: Entity,
+}
+
+/// Palettes extracted off the main thread, waiting to be exported.
+#[derive(Resource)]
+pub(crate) struct PaletteExtraction {
+    /// Only locked to make the resource `Sync`.
+    palettes: Mutex<Receiver<(Entity, DesktopPalette)>>,
+    sender: Sender<(Entity, DesktopPalette)>,
+    /// The last palette exported, so unchanged ones aren't exported again.
+    previous: Option<DesktopPalette>,
+}
+
+impl Default for PaletteExtraction {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            palettes: Mutex::new(receiver),
+            sender,
+            previous: None,
+        }
+    }
+}
+
+/// Reads back a frame of the exported window every [`PaletteExport::interval`].
+pub(crate) fn capture_palette_frame(
+    mut commands: Commands,
+    export: Res<PaletteExport>,
+    windows: Query<Entity, With<Window>>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    pending: Query<(), With<PaletteCapture>>,
+    mut next_capture: Local<Option<Instant>>,
+) {
+    let now = Instant::now();
+    if next_capture.is_some_and(|at| at > now) || !pending.is_empty() {
+        return;
+    }
+
+    let window = match export.window {
+        Some(window) => windows.get(window).ok(),
+        None => primary_window.get_single().ok(),
+    };
+    // The screenshot would be retried every frame if its window doesn't exist.
+    let Some(window) = window else {
+        return;
+    };
+
+    *next_capture = now.checked_add(export.interval);
+    commands.spawn((Screenshot::window(window), PaletteCapture { window }));
+}
+
+/// Turns a frame read back by [`capture_palette_frame`] into a palette on another thread, so a
+/// full resolution frame doesn't hold up the main thread.
+pub(crate) fn extract_palette(
+    mut trigger: Trigger<ScreenshotCaptured>,
+    captures: Query<&PaletteCapture>,
+    extraction: Res<PaletteExtraction>,
+) {
+    let Ok(&PaletteCapture { window }) = captures.get(trigger.entity()) else {
+        return;
+    };
+
+    // The screenshot was only taken for the palette, so nothing else needs the frame.
+    let frame = mem::take(&mut trigger.event_mut().0);
+    let sender = extraction.sender.clone();
+
+    let started = thread::Builder::new()
+        .name("palette extractor".into())
+        .spawn(move || {
+            if let Some(palette) = quantize(&frame) {
+                let _ = sender.send((window, palette));
+            }
+        });
+
+    if let Err(err) = started {
+        warn!("Could not start extracting the palette: {err}");
+    }
+}
+
+/// Exports the palettes [`extract_palette`] extracted that differ from the previous one.
+pub(crate) fn export_palette(
+    export: Res<PaletteExport>,
+    mut extraction: ResMut<PaletteExtraction>,
+    mut extracted: EventWriter<PaletteExtracted>,
+) {
+    let extraction = &mut *extraction;
+    let palettes: Vec<_> = extraction.palettes.lock().unwrap().try_iter().collect();
+
+    for (window, palette) in palettes {
+        if extraction.previous.as_ref() == Some(&palette) {
+            continue;
+        }
+        extraction.previous = Some(palette.clone());
+
+        if let Some(directory) = &export.directory {
+            if let Err(err) = write_palette(directory, &palette) {
+                warn!("Could not write the palette to {directory:?}: {err}");
+            } else if let Some(hook) = &export.hook {
+                run_hook(hook);
+            }
+        }
+
+        extracted.send(PaletteExtracted { window, palette });
+    }
+}
+
+/// Reduces a downscaled copy of `image` to sixteen colors with median cut.
+///
+/// Returns `None` if the pixels can't be read.
+fn quantize(image: &Image) -> Option<DesktopPalette> {
+    let (width, height) = (image.width(), image.height());
+    let step = (width.max(height) / SAMPLE_SIZE).max(1);
+
+    let mut pixels = Vec::new();
+    for y in (0..height).step_by(step as usize) {
+        for x in (0..width).step_by(step as usize) {
+            pixels.push(
+                image
+                    .get_color_at(x, y)
+                    .ok()?
+                    .to_srgba()
+                    .to_u8_array_no_alpha(),
+            );
+        }
+    }
+
+    if pixels.is_empty() {
+        return None;
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < 16 {
+        // Split the box with the widest channel, across that channel.
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, pixels)| pixels.len() > 1)
+            .map(|(index, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (index, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range)
+        else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(index);
+        pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.extend([pixels, upper]);
+    }
+
+    let mut colors: Vec<Srgba> = boxes.iter().map(|pixels| average(pixels)).collect();
+    colors.sort_by(|a, b| a.luminance().total_cmp(&b.luminance()));
+
+    // A frame with fewer than sixteen distinct colors repeats them.
+    let colors: [Color; 16] = core::array::from_fn(|n| colors[n * colors.len() / 16].into());
+
+    Some(DesktopPalette {
+        background: colors[0],
+        foreground: colors[15],
+        colors,
+        source: None,
+    })
+}
+
+/// The channel whose values spread the most, and how much they spread.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = pixels.iter().map(|pixel| pixel[channel]);
+            let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+            (channel, range)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> Srgba {
+    let mut sum = [0u64; 3];
+    for pixel in pixels {
+        for (sum, value) in sum.iter_mut().zip(pixel) {
+            *sum += u64::from(*value);
+        }
+    }
+
+    let count = pixels.len().max(1) as u64;
+    let [red, green, blue] = sum.map(|sum| (sum / count) as u8);
+    Srgba::rgb_u8(red, green, blue)
+}
+
+fn write_palette(directory: &Path, palette: &DesktopPalette) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+
+    for (name, contents) in [
+        ("colors.json", palette.to_pywal()),
+        ("colors.Xresources", palette.to_xresources()),
+        ("palette.json", palette.to_json()),
+    ] {
+        // Readers watching the files never see them half written.
+        let path = directory.join(name);
+        let partial = directory.join(format!(".{name}.partial"));
+        fs::write(&partial, contents)?;
+        fs::rename(&partial, &path)?;
+    }
+
+    Ok(())
+}
+
+fn run_hook(hook: &str) {
+    match Command::new("sh").arg("-c").arg(hook).spawn() {
+        Ok(mut child) => {
+            // Reap the hook without holding up the frame.
+            thread::spawn(move || child.wait());
+        }
+        Err(err) => warn!("Could not run the palette hook {hook:?}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use bevy::{
+        asset::RenderAssetUsages,
+        render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    };
+    use bevy_ecs::{event::Events, world::World};
+
+    use super::*;
+
+    fn image(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 3]) -> Image {
+        let data = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let [red, green, blue] = pixel(x, y);
+                [red, green, blue, 255]
+            })
+            .collect();
+
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    fn rgb(color: Color) -> [u8; 3] {
+        color.to_srgba().to_u8_array_no_alpha()
+    }
+
+    #[test]
+    fn single_color_repeats() {
+        let palette = quantize(&image(8, 8, |_, _| [10, 200, 30])).unwrap();
+
+        assert!(
+            palette
+                .colors
+                .iter()
+                .all(|&color| rgb(color) == [10, 200, 30])
+        );
+        assert_eq!(rgb(palette.background), [10, 200, 30]);
+        assert_eq!(rgb(palette.foreground), [10, 200, 30]);
+    }
+
+    #[test]
+    fn sorted_dark_to_light() {
+        // Sixteen columns of distinct grays, in shuffled order.
+        let palette = quantize(&image(16, 4, |x, _| [(x * 7 % 16 * 16) as u8; 3])).unwrap();
+
+        let grays: Vec<u8> = palette.colors.iter().map(|&color| rgb(color)[0]).collect();
+        assert_eq!(grays, (0..16).map(|n| n * 16).collect::<Vec<u8>>());
+        assert_eq!(rgb(palette.background), [0; 3]);
+        assert_eq!(rgb(palette.foreground), [240; 3]);
+    }
+
+    #[test]
+    fn splits_across_the_widest_channel() {
+        // Red spreads the most, so the first cut separates dark from bright red.
+        let palette = quantize(&image(2, 1, |x, _| [x as u8 * 250, 10, 20])).unwrap();
+
+        assert_eq!(rgb(palette.background), [0, 10, 20]);
+        assert_eq!(rgb(palette.foreground), [250, 10, 20]);
+        assert_eq!(widest_channel(&[[0, 10, 20], [250, 12, 0]]), (0, 250));
+    }
+
+    #[test]
+    fn downscales_large_frames() {
+        // Only every fourth pixel of a 512 wide frame is sampled, which skips the odd columns.
+        let palette = quantize(&image(512, 2, |x, _| [if x % 2 == 0 { 0 } else { 255 }; 3]));
+
+        assert!(
+            palette
+                .unwrap()
+                .colors
+                .iter()
+                .all(|&color| rgb(color) == [0; 3])
+        );
+    }
+
+    #[test]
+    fn unreadable_frames() {
+        let mut depth = image(4, 4, |_, _| [0; 3]);
+        depth.texture_descriptor.format = TextureFormat::Depth32Float;
+        assert!(quantize(&depth).is_none());
+
+        assert!(quantize(&image(0, 0, |_, _| [0; 3])).is_none());
+    }
+
+    #[test]
+    fn average_rounds_down() {
+        assert_eq!(
+            average(&[[0, 10, 255], [1, 20, 255]]).to_u8_array_no_alpha(),
+            [0, 15, 255]
+        );
+    }
+
+    #[test]
+    fn exports_changed_palettes() {
+        let directory = env::temp_dir().join(format!("palette-export-{}", process::id()));
+        let mut world = World::new();
+        world.insert_resource(PaletteExport {
+            directory: Some(directory.clone()),
+            ..Default::default()
+        });
+        world.init_resource::<PaletteExtraction>();
+        world.init_resource::<Events<PaletteExtracted>>();
+        world.add_observer(extract_palette);
+
+        let window = world.spawn_empty().id();
+        let capture = |world: &mut World, color: [u8; 3]| {
+            let screenshot = world.spawn(PaletteCapture { window }).id();
+            world.trigger_targets(ScreenshotCaptured(image(4, 4, |_, _| color)), screenshot);
+
+            // Wait for the extractor thread rather than racing it.
+            let palette = world
+                .resource::<PaletteExtraction>()
+                .palettes
+                .lock()
+                .unwrap()
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap();
+            world
+                .resource::<PaletteExtraction>()
+                .sender
+                .send(palette)
+                .unwrap();
+
+            world.run_system_cached(export_palette).unwrap();
+            let events = world.resource::<Events<PaletteExtracted>>();
+            let colors: Vec<_> = events
+                .iter_current_update_events()
+                .map(|event| (event.window, rgb(event.palette.background)))
+                .collect();
+            world.resource_mut::<Events<PaletteExtracted>>().update();
+            colors
+        };
+
+        assert_eq!(capture(&mut world, [10, 20, 30]), [(window, [10, 20, 30])]);
+        assert!(fs::read_to_string(directory.join("colors.Xresources")).is_ok());
+        // The same palette again isn't exported twice.
+        assert_eq!(capture(&mut world, [10, 20, 30]), []);
+        assert_eq!(capture(&mut world, [90, 0, 0]), [(window, [90, 0, 0])]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
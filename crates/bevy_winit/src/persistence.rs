@@ -0,0 +1,688 @@
+use core::{
+    mem, ptr,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+    time::Duration,
+};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::{self, Read},
+    os::fd::FromRawFd,
+    path::{Path, PathBuf},
+    sync::{Mutex, Once},
+    thread,
+};
+
+use bevy::{
+    core::Name,
+    ecs::reflect::{AppTypeRegistry, ReflectComponent, ReflectResource},
+    reflect::{FromType, PartialReflect, ReflectMut, TypeRegistry, serde::TypedReflectSerializer},
+    utils::Instant,
+};
+use bevy_app::{App, AppExit, First, Last, Plugin, PreUpdate, Startup};
+use bevy_ecs::{
+    entity::Entity,
+    event::{EventWriter, Events},
+    schedule::{IntoSystemConfigs, common_conditions::resource_exists},
+    system::{Res, Resource},
+    world::World,
+};
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+use winit::event_loop::EventLoopProxy;
+
+use crate::{EventLoopProxyWrapper, WakeUp, scene_params::apply_json};
+
+/// The version of the state file's layout. Files written by a newer version are left alone.
+const FORMAT_VERSION: u64 = 1;
+
+/// Set by the signal watcher when SIGTERM or SIGINT arrives.
+static EXIT_SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// The write end of the pipe the signal handler wakes the signal watcher through.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Wakes the event loop once a signal arrives, if the app uses the default event type.
+static SIGNAL_WAKE: Mutex<Option<EventLoopProxy<WakeUp>>> = Mutex::new(None);
+
+/// Marks a component or resource type whose data is kept across restarts by the
+/// [`PersistencePlugin`], with `#[reflect(PersistentState)]` next to `#[reflect(Component)]` or
+/// `#[reflect(Resource)]`. The type also needs to be registered with `App::register_type`.
+///
+/// Components are only kept on entities with a [`Name`], which identifies the entity to restore
+/// them onto, so persistent entities need names that are unique.
+#[derive(Debug, Clone, Copy)]
+pub struct ReflectPersistentState;
+
+impl<T> FromType<T> for ReflectPersistentState {
+    fn from_type() -> Self {
+        Self
+    }
+}
+
+/// Where and how often the [`PersistencePlugin`] saves.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PersistenceConfig {
+    /// Defaults to `scene-state.json` in a directory named after the executable in
+    /// `$XDG_STATE_HOME`, or `~/.local/state`.
+    pub path: PathBuf,
+    /// How often the state is saved while running, besides on exit. `None` only saves on exit.
+    pub interval: Option<Duration>,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        let state_home = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .unwrap_or_else(|| {
+                let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+                home.join(".local/state")
+            });
+        let app = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.file_stem().map(|stem| stem.to_owned()))
+            .unwrap_or_else(|| "bevy-linux-wallpaper".into());
+
+        Self {
+            path: state_home.join(app).join("scene-state.json"),
+            interval: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+/// Saves the components and resources marked with [`ReflectPersistentState`] on exit and every
+/// [`PersistenceConfig::interval`], and restores them on the next start, so procedural scenes
+/// keep evolving across restarts and updates.
+///
+/// SIGTERM and SIGINT, as sent when the session ends or on Ctrl-C, exit the app cleanly so the
+/// state is saved. A second signal kills it as usual. The event loop is woken for the exit if
+/// the app uses [`WakeUp`] events, otherwise it exits on its next update.
+///
+/// Saved data is restored as soon as its resource, or a named entity with its component,
+/// exists. Struct fields are restored one by one, so fields added since leave their current
+/// value, fields removed since are dropped, and a field that changed type is skipped with a
+/// warning.
+#[derive(Default)]
+pub struct PersistencePlugin {
+    pub config: PersistenceConfig,
+}
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        let pending = match load_state(&self.config.path) {
+            Ok(pending) if pending.resources.is_empty() && pending.entities.is_empty() => pending,
+            Ok(pending) => {
+                info!("Restoring the scene state saved in {:?}", self.config.path);
+                pending
+            }
+            Err(err) => {
+                warn!("Could not restore {:?}: {err}", self.config.path);
+                PendingState::default()
+            }
+        };
+
+        app.insert_resource(self.config.clone())
+            .insert_resource(pending)
+            .init_resource::<SaveTimer>()
+            .add_systems(Startup, watch_exit_signals)
+            .add_systems(First, exit_on_signal)
+            .add_systems(
+                PreUpdate,
+                restore_persistent_state.run_if(resource_exists::<PendingState>),
+            )
+            .add_systems(Last, save_persistent_state);
+    }
+}
+
+/// Saved data that hasn't found its resource or entity yet, by type path.
+#[derive(Resource, Debug, Default)]
+struct PendingState {
+    resources: HashMap<String, Value>,
+    /// By entity name, then type path.
+    entities: HashMap<String, HashMap<String, Value>>,
+}
+
+fn load_state(path: &Path) -> io::Result<PendingState> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(PendingState::default()),
+        Err(err) => return Err(err),
+    };
+    let mut file: Map<String, Value> = serde_json::from_slice(&json)?;
+
+    let version = file.get("format").and_then(Value::as_u64).unwrap_or(0);
+    if version > FORMAT_VERSION {
+        return Err(io::Error::other(format!(
+            "it was written in a newer format, {version}"
+        )));
+    }
+
+    let mut take_object = |key: &str| match file.remove(key) {
+        Some(Value::Object(object)) => object.into_iter().collect(),
+        _ => HashMap::new(),
+    };
+    let resources = take_object("resources");
+    let entities = take_object("entities")
+        .into_iter()
+        .map(|(name, components)| {
+            let components = match components {
+                Value::Object(components) => components.into_iter().collect(),
+                _ => HashMap::new(),
+            };
+            (name, components)
+        })
+        .collect();
+
+    Ok(PendingState {
+        resources,
+        entities,
+    })
+}
+
+/// Restores the saved data whose resource or entity exists by now.
+fn restore_persistent_state(world: &mut World) {
+    let Some(registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+        return;
+    };
+    let registry = registry.read();
+    let Some(mut pending) = world.remove_resource::<PendingState>() else {
+        return;
+    };
+
+    pending.resources.retain(|type_path, saved| {
+        let Some(resource) = registry
+            .get_with_type_path(type_path)
+            .and_then(|registration| registration.data::<ReflectResource>())
+        else {
+            return true;
+        };
+        let Some(mut value) = resource.reflect_mut(world) else {
+            return true;
+        };
+
+        apply_leniently(value.as_partial_reflect_mut(), &registry, saved, type_path);
+        false
+    });
+
+    if !pending.entities.is_empty() {
+        let named: Vec<(Entity, String)> = world
+            .query::<(Entity, &Name)>()
+            .iter(world)
+            .map(|(entity, name)| (entity, name.as_str().to_owned()))
+            .collect();
+
+        for (entity, name) in named {
+            let Some(components) = pending.entities.get_mut(&name) else {
+                continue;
+            };
+
+            components.retain(|type_path, saved| {
+                let Some(component) = registry
+                    .get_with_type_path(type_path)
+                    .and_then(|registration| registration.data::<ReflectComponent>())
+                else {
+                    return true;
+                };
+                let Some(mut value) = component.reflect_mut(world.entity_mut(entity)) else {
+                    return true;
+                };
+
+                let context = format!("{type_path} of {name}");
+                apply_leniently(value.as_partial_reflect_mut(), &registry, saved, &context);
+                false
+            });
+        }
+
+        pending
+            .entities
+            .retain(|_, components| !components.is_empty());
+    }
+
+    if !pending.resources.is_empty() || !pending.entities.is_empty() {
+        world.insert_resource(pending);
+    }
+}
+
+/// Applies `saved` to `value`, field by field for structs so the saved data survives fields being
+/// added or removed.
+fn apply_leniently(
+    value: &mut dyn PartialReflect,
+    registry: &TypeRegistry,
+    saved: &Value,
+    context: &str,
+) {
+    if let (ReflectMut::Struct(fields), Value::Object(saved)) = (value.reflect_mut(), saved) {
+        for (name, saved) in saved {
+            if let Some(field) = fields.field_mut(name) {
+                apply_leniently(field, registry, saved, &format!("{context}.{name}"));
+            }
+        }
+        return;
+    }
+
+    if let Err(err) = apply_json(value, registry, saved) {
+        warn!("Could not restore {context}: {err}");
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct SaveTimer {
+    /// When the state was last saved, or the first frame ran.
+    saved: Option<Instant>,
+    /// Whether the state was saved for an exit already.
+    exited: bool,
+}
+
+/// Saves on exit and every interval.
+fn save_persistent_state(world: &mut World) {
+    let now = Instant::now();
+    let interval = world.resource::<PersistenceConfig>().interval;
+    let exiting = world
+        .get_resource::<Events<AppExit>>()
+        .is_some_and(|exits| !exits.is_empty());
+
+    let mut timer = world.resource_mut::<SaveTimer>();
+    let due = match (timer.saved, interval) {
+        (None, _) => {
+            // Counting starts at the first frame, there is nothing worth saving before it.
+            timer.saved = Some(now);
+            false
+        }
+        (Some(saved), Some(interval)) => now.duration_since(saved) >= interval,
+        (Some(_), None) => false,
+    };
+    if !due && !exiting {
+        return;
+    }
+    timer.saved = Some(now);
+    timer.exited |= exiting;
+
+    save(world);
+}
+
+/// Saves the state if the app stopped without saving it on an [`AppExit`], e.g. because the
+/// event loop failed.
+pub(crate) fn save_on_exit(world: &mut World) {
+    let Some(mut timer) = world.get_resource_mut::<SaveTimer>() else {
+        return;
+    };
+    if mem::replace(&mut timer.exited, true) {
+        return;
+    }
+
+    save(world);
+}
+
+fn save(world: &mut World) {
+    let path = world.resource::<PersistenceConfig>().path.clone();
+    let state = collect_state(world);
+    if let Err(err) = write_state(&path, &state) {
+        warn!("Could not save the persistent scene state to {path:?}: {err}");
+    }
+}
+
+/// Sends an [`AppExit`] once SIGTERM or SIGINT arrived.
+fn exit_on_signal(mut exit: EventWriter<AppExit>) {
+    if EXIT_SIGNALLED.swap(false, Ordering::Relaxed) {
+        info!("Exiting on a signal");
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Catches SIGTERM and SIGINT from now on.
+fn watch_exit_signals(proxy: Option<Res<EventLoopProxyWrapper<WakeUp>>>) {
+    *SIGNAL_WAKE.lock().unwrap() = proxy.map(|proxy| EventLoopProxy::clone(&proxy));
+
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        if let Err(err) = install_signal_handlers() {
+            warn!(
+                "Could not handle SIGTERM and SIGINT, the state won't be saved when killed: {err}"
+            );
+        }
+    });
+}
+
+/// Installs handlers that pass SIGTERM and SIGINT on to a watcher thread through a pipe, which is
+/// all a handler can safely do.
+fn install_signal_handlers() -> io::Result<()> {
+    let mut fds = [0; 2];
+    // SAFETY: `pipe2` only writes the two descriptors.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: The read end was just created and nothing else owns it.
+    let mut signals = unsafe { File::from_raw_fd(fds[0]) };
+    SIGNAL_PIPE.store(fds[1], Ordering::Relaxed);
+
+    thread::Builder::new()
+        .name("exit signal watcher".into())
+        .spawn(move || {
+            let mut signal = [0];
+            while signals.read_exact(&mut signal).is_ok() {
+                EXIT_SIGNALLED.store(true, Ordering::Relaxed);
+                if let Some(proxy) = &*SIGNAL_WAKE.lock().unwrap() {
+                    let _ = proxy.send_event(WakeUp);
+                }
+            }
+        })?;
+
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        // SAFETY: The handler only calls `write`, which is async-signal-safe.
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction =
+                on_exit_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            // A second signal while exiting gets the default action.
+            action.sa_flags = libc::SA_RESETHAND | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(signal, &action, ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+extern "C" fn on_exit_signal(_: libc::c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    // SAFETY: The byte written is valid, and a failed write only loses the signal.
+    unsafe {
+        libc::write(fd, [0u8].as_ptr().cast(), 1);
+    }
+}
+
+fn collect_state(world: &mut World) -> Value {
+    let mut resources = Map::new();
+    let mut entities: Map<String, Value> = Map::new();
+
+    // Data that hasn't been restored yet is kept for when its resource or entity shows up.
+    if let Some(pending) = world.get_resource::<PendingState>() {
+        resources.extend(pending.resources.clone());
+        for (name, components) in &pending.entities {
+            entities.insert(
+                name.clone(),
+                Value::Object(components.clone().into_iter().collect()),
+            );
+        }
+    }
+
+    let Some(registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+        return state_file(resources, entities);
+    };
+    let registry = registry.read();
+
+    let persistent: Vec<_> = registry
+        .iter_with_data::<ReflectPersistentState>()
+        .map(|(registration, _)| registration)
+        .collect();
+
+    for registration in &persistent {
+        let type_path = registration.type_info().type_path();
+
+        if let Some(resource) = registration.data::<ReflectResource>()
+            && let Some(value) = resource.reflect(world)
+        {
+            match serialize(value.as_partial_reflect(), &registry) {
+                Ok(value) => {
+                    resources.insert(type_path.to_owned(), value);
+                }
+                Err(err) => warn!("Could not save {type_path}: {err}"),
+            }
+        }
+    }
+
+    let components: Vec<(&str, &ReflectComponent)> = persistent
+        .iter()
+        .filter_map(|registration| {
+            let component = registration.data::<ReflectComponent>()?;
+            Some((registration.type_info().type_path(), component))
+        })
+        .collect();
+
+    if !components.is_empty() {
+        let mut named = world.query::<(Entity, &Name)>();
+
+        for (entity, name) in named.iter(world) {
+            let entity = world.entity(entity);
+
+            for (type_path, component) in &components {
+                let Some(value) = component.reflect(entity) else {
+                    continue;
+                };
+
+                match serialize(value.as_partial_reflect(), &registry) {
+                    Ok(value) => {
+                        let saved = entities
+                            .entry(name.as_str())
+                            .or_insert_with(|| Value::Object(Map::new()));
+                        if let Value::Object(saved) = saved {
+                            saved.insert((*type_path).to_owned(), value);
+                        }
+                    }
+                    Err(err) => warn!("Could not save {type_path} of {name}: {err}"),
+                }
+            }
+        }
+    }
+
+    state_file(resources, entities)
+}
+
+fn state_file(resources: Map<String, Value>, entities: Map<String, Value>) -> Value {
+    let mut file = Map::new();
+    file.insert("format".into(), FORMAT_VERSION.into());
+    file.insert("resources".into(), Value::Object(resources));
+    file.insert("entities".into(), Value::Object(entities));
+    Value::Object(file)
+}
+
+fn serialize(value: &dyn PartialReflect, registry: &TypeRegistry) -> serde_json::Result<Value> {
+    serde_json::to_value(TypedReflectSerializer::new(value, registry))
+}
+
+/// Writes the state next to `path` and moves it into place, so a crash while writing doesn't lose
+/// the previous state.
+fn write_state(path: &Path, state: &Value) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, serde_json::to_vec_pretty(state)?)?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use bevy::reflect::{Reflect, TypePath};
+    use bevy_ecs::component::Component;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Resource, Reflect, Default)]
+    #[reflect(Resource, PersistentState)]
+    struct Garden {
+        plants: u32,
+    }
+
+    #[derive(Resource, Reflect, Debug, PartialEq)]
+    #[reflect(Resource, PersistentState)]
+    struct Greenhouse {
+        plants: u32,
+        temperature: f32,
+        label: String,
+    }
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, PersistentState)]
+    struct Tree {
+        height: f32,
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("persistence-{}-{name}.json", process::id()))
+    }
+
+    /// A world about to restore `file`, the way the plugin does. Apps aren't used, since they
+    /// would race [`saves_on_exit`] for its signal.
+    fn restoring_world(name: &str, file: &Value) -> World {
+        let path = temp_path(name);
+        fs::write(&path, file.to_string()).unwrap();
+        let pending = load_state(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Greenhouse>();
+        registry.write().register::<Tree>();
+
+        let mut world = World::new();
+        world.insert_resource(registry);
+        world.insert_resource(pending);
+        world
+    }
+
+    fn garden_app(name: &str) -> (App, PathBuf) {
+        let path = temp_path(name);
+        let _ = fs::remove_file(&path);
+
+        let mut app = App::new();
+        app.register_type::<Garden>()
+            .insert_resource(Garden { plants: 3 })
+            .add_plugins(PersistencePlugin {
+                config: PersistenceConfig {
+                    path: path.clone(),
+                    interval: None,
+                },
+            });
+        (app, path)
+    }
+
+    fn saved_plants(path: &Path) -> Option<u64> {
+        let state: Value = serde_json::from_slice(&fs::read(path).ok()?).unwrap();
+        state
+            .pointer(&format!("/resources/{}/plants", Garden::type_path()))?
+            .as_u64()
+    }
+
+    // One test, since signals reach every app in the process.
+    #[test]
+    fn saves_on_exit() {
+        let (mut app, exit_path) = garden_app("exit");
+        app.update();
+        assert_eq!(saved_plants(&exit_path), None);
+
+        app.world_mut().resource_mut::<Garden>().plants = 5;
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(saved_plants(&exit_path), Some(5));
+
+        // The runner finding the state saved already leaves it alone.
+        app.world_mut().resource_mut::<Garden>().plants = 8;
+        save_on_exit(app.world_mut());
+        assert_eq!(saved_plants(&exit_path), Some(5));
+
+        // A failed event loop never sent an exit.
+        let (mut app, failure_path) = garden_app("failure");
+        app.update();
+        save_on_exit(app.world_mut());
+        assert_eq!(saved_plants(&failure_path), Some(3));
+
+        let (mut app, signal_path) = garden_app("signal");
+        app.update();
+        // SAFETY: The handler is installed, so this doesn't terminate the test.
+        assert_eq!(unsafe { libc::raise(libc::SIGTERM) }, 0);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while app.should_exit().is_none() {
+            assert!(Instant::now() < deadline, "the app didn't exit");
+            app.update();
+        }
+        assert_eq!(saved_plants(&signal_path), Some(3));
+
+        for path in [exit_path, failure_path, signal_path] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn restores_hand_written_files() {
+        let mut file = json!({"format": 1, "resources": {}, "entities": {}});
+        // `temperature` was added since, `watered` removed and `label` changed its type.
+        file["resources"][Greenhouse::type_path()] =
+            json!({"plants": 7, "label": 12, "watered": true});
+        file["entities"]["oak"] = json!({Tree::type_path(): {"height": 4.5}});
+        file["entities"]["pine"] = json!({Tree::type_path(): {"height": 9.0}});
+        let mut world = restoring_world("hand-written", &file);
+
+        // Nothing to restore into yet.
+        restore_persistent_state(&mut world);
+        let pending = world.resource::<PendingState>();
+        assert_eq!(pending.resources.len(), 1);
+        assert_eq!(pending.entities.len(), 2);
+
+        world.insert_resource(Greenhouse {
+            plants: 1,
+            temperature: 21.0,
+            label: "glass".into(),
+        });
+        let oak = world.spawn((Name::new("oak"), Tree::default())).id();
+        // Named like a saved entity, but without its component.
+        world.spawn(Name::new("pine"));
+        restore_persistent_state(&mut world);
+
+        assert_eq!(
+            *world.resource::<Greenhouse>(),
+            Greenhouse {
+                plants: 7,
+                temperature: 21.0,
+                label: "glass".into(),
+            }
+        );
+        assert_eq!(world.get::<Tree>(oak), Some(&Tree { height: 4.5 }));
+
+        let pending = world.resource::<PendingState>();
+        assert!(pending.resources.is_empty());
+        assert_eq!(pending.entities.keys().collect::<Vec<_>>(), ["pine"]);
+
+        // Data still waiting for its entity is saved again.
+        let state = collect_state(&mut world);
+        let height = |name: &str| {
+            state
+                .pointer(&format!("/entities/{name}/{}/height", Tree::type_path()))
+                .cloned()
+        };
+        assert_eq!(height("oak"), Some(json!(4.5)));
+        assert_eq!(height("pine"), Some(json!(9.0)));
+        assert_eq!(state["format"], json!(FORMAT_VERSION));
+
+        world.spawn((Name::new("pine"), Tree::default()));
+        restore_persistent_state(&mut world);
+        assert!(!world.contains_resource::<PendingState>());
+    }
+
+    #[test]
+    fn rejects_newer_formats() {
+        let path = temp_path("newer");
+        let mut file = json!({"format": FORMAT_VERSION + 1, "resources": {}});
+        file["resources"][Greenhouse::type_path()] = json!({"plants": 7});
+        fs::write(&path, file.to_string()).unwrap();
+
+        let err = load_state(&path).unwrap_err();
+        assert!(err.to_string().contains("newer format, 2"), "{err}");
+
+        // Files from before the format was versioned are read.
+        file.as_object_mut().unwrap().remove("format");
+        fs::write(&path, file.to_string()).unwrap();
+        assert_eq!(load_state(&path).unwrap().resources.len(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use core::time::Duration;
+
+use bevy::{
+    ecs::reflect::AppTypeRegistry,
+    time::{Time, Virtual},
+};
+use bevy_ecs::{
+    event::{EventCursor, Events},
+    system::{Local, Resource},
+    world::World,
+};
+use tracing::{info, warn};
+use winit::{
+    event::{ElementState, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use crate::{
+    RawWinitWindowEvent, scene_params::SceneParam, wallpaper_clock::WallpaperClock,
+    wallpaper_command::WallpaperCommand,
+};
+
+/// The key bindings of [`WallpaperTarget::Preview`](crate::WallpaperTarget::Preview):
+///
+/// - Space pauses and resumes, like [`WallpaperCommand::TogglePause`].
+/// - `.` steps time by [`PreviewControls::step`], to go frame by frame while paused.
+/// - The left and right arrows move time by [`PreviewControls::scrub`].
+/// - `N` moves on to the next scene, like [`WallpaperCommand::Next`].
+/// - Tab picks the next of the [`PreviewControls::params`], and the up and down arrows nudge it.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PreviewControls {
+    /// How far `.` moves time.
+    pub step: Duration,
+    /// How far the arrows move time. [`Time`] only moves forward, so moving backwards only moves
+    /// the [`WallpaperClock`].
+    pub scrub: Duration,
+    pub params: Vec<PreviewParam>,
+}
+
+impl Default for PreviewControls {
+    fn default() -> Self {
+        Self {
+            step: Duration::from_secs_f64(1.0 / 60.0),
+            scrub: Duration::from_secs(5),
+            params: Vec::new(),
+        }
+    }
+}
+
+/// An `f32` or `f64` field of a reflected resource to tweak from the preview window, the way
+/// data feeds and OSC set scene parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewParam {
+    /// The type path of the resource, e.g. `my_wallpaper::Weather`, or its short path.
+    pub resource: String,
+    /// A reflection path into the resource, e.g. `sky.cloud_cover`.
+    pub field: String,
+    /// How far one press of an arrow moves the value.
+    pub step: f64,
+}
+
+impl PreviewParam {
+    pub fn new(resource: impl Into<String>, field: impl Into<String>, step: f64) -> Self {
+        Self {
+            resource: resource.into(),
+            field: field.into(),
+            step,
+        }
+    }
+}
+
+/// Applies the keys pressed in the preview window, after time was updated for the frame.
+pub(crate) fn preview_keys(
+    world: &mut World,
+    mut cursor: Local<EventCursor<RawWinitWindowEvent>>,
+    mut selected: Local<usize>,
+) {
+    let keys: Vec<Key> = cursor
+        .read(world.resource::<Events<RawWinitWindowEvent>>())
+        .filter_map(|raw| match &raw.event {
+            WindowEvent::KeyboardInput {
+                event,
+                is_synthetic: false,
+                ..
+            } if event.state == ElementState::Pressed => Some(event.logical_key.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for key in keys {
+        let controls = world.resource::<PreviewControls>();
+        let (step, scrub) = (controls.step, controls.scrub);
+        let params = controls.params.len();
+
+        match key.as_ref() {
+            Key::Named(NamedKey::Space) => {
+                world.send_event(WallpaperCommand::TogglePause);
+            }
+            Key::Character(".") => advance(world, step),
+            Key::Named(NamedKey::ArrowRight) => advance(world, scrub),
+            Key::Named(NamedKey::ArrowLeft) => {
+                if let Some(mut clock) = world.get_resource_mut::<WallpaperClock>() {
+                    clock.scrub(-scrub.as_secs_f64());
+                }
+            }
+            Key::Character("n" | "N") => {
+                world.send_event(WallpaperCommand::Next);
+            }
+            Key::Named(NamedKey::Tab) if params > 0 => {
+                *selected = (*selected + 1) % params;
+                tweak(world, *selected, 0.0);
+            }
+            Key::Named(NamedKey::ArrowUp) if params > 0 => tweak(world, *selected % params, 1.0),
+            Key::Named(NamedKey::ArrowDown) if params > 0 => {
+                tweak(world, *selected % params, -1.0);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Moves time and the [`WallpaperClock`] forward by `by`, whether paused or not.
+fn advance(world: &mut World, by: Duration) {
+    // `Time` was already copied from `Time<Virtual>` this frame, so both move.
+    if let Some(mut time) = world.get_resource_mut::<Time<Virtual>>() {
+        time.advance_by(by);
+    }
+    if let Some(mut time) = world.get_resource_mut::<Time>() {
+        time.advance_by(by);
+    }
+    if let Some(mut clock) = world.get_resource_mut::<WallpaperClock>() {
+        clock.scrub(by.as_secs_f64());
+    }
+}
+
+/// Moves the `idx`th parameter by `steps` of its step and logs its value.
+fn tweak(world: &mut World, idx: usize, steps: f64) {
+    let Some(registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+        return;
+    };
+    let registry = registry.read();
+    let param = world.resource::<PreviewControls>().params[idx].clone();
+    let scene_param = SceneParam {
+        resource: &param.resource,
+        field: &param.field,
+    };
+
+    let Some(value) = scene_param.number(world, &registry) else {
+        warn!(
+            "Could not read {}.{}, it needs to be an f32 or f64 field of a reflected resource",
+            param.resource, param.field
+        );
+        return;
+    };
+    let value = value + param.step * steps;
+
+    match scene_param.set_number(world, &registry, value) {
+        Ok(()) => info!("{}.{} = {value}", param.resource, param.field),
+        Err(err) => warn!("Could not set {}.{}: {err}", param.resource, param.field),
+    }
+}
@@ -0,0 +1,462 @@
+use core::{fmt, str::FromStr, time::Duration};
+use std::{
+    fs::OpenOptions,
+    io::{self, Read},
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+    },
+    thread,
+};
+
+use bevy::{
+    asset::Assets, image::Image, math::UVec2, render::render_resource::TextureFormat, time::Time,
+    utils::Instant,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use tracing::{info, warn};
+
+use crate::{
+    desktop_2d::{ImageFit, SceneTexture, TextureScene, follow_scene_monitors},
+    monitor_identity::{MonitorIdentity, MonitorMatcher},
+    stoppable_read::{STOP_POLL_INTERVAL, StoppableRead},
+};
+
+/// How many frames are read ahead of the one on screen.
+const QUEUED_FRAMES: usize = 2;
+
+/// Where [`RawFrames`] come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameSource {
+    /// A shell command writing frames to its standard output, run with `sh -c`, e.g.
+    /// `ffmpeg -stream_loop -1 -re -i video.mkv -f rawvideo -pix_fmt rgba -`.
+    Command(String),
+    /// A named pipe some other process writes frames into.
+    Fifo(PathBuf),
+}
+
+/// The layout of the pixels in a raw frame, named like ffmpeg's `-pix_fmt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    #[default]
+    Rgba,
+    Bgra,
+    Rgb24,
+    Gray,
+}
+
+impl PixelFormat {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba | Self::Bgra => 4,
+            Self::Rgb24 => 3,
+            Self::Gray => 1,
+        }
+    }
+
+    /// The texture format the frames are uploaded as.
+    fn texture_format(self) -> TextureFormat {
+        match self {
+            Self::Bgra => TextureFormat::Bgra8UnormSrgb,
+            _ => TextureFormat::Rgba8UnormSrgb,
+        }
+    }
+
+    /// Converts a frame into the layout of [`PixelFormat::texture_format`].
+    fn to_texture(self, frame: Vec<u8>) -> Vec<u8> {
+        match self {
+            Self::Rgba | Self::Bgra => frame,
+            Self::Rgb24 => frame
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+                .collect(),
+            Self::Gray => frame
+                .iter()
+                .flat_map(|&gray| [gray, gray, gray, u8::MAX])
+                .collect(),
+        }
+    }
+}
+
+/// Error returned when parsing an unsupported [`PixelFormat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePixelFormatError(pub String);
+
+impl fmt::Display for ParsePixelFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unsupported pixel format {:?}, expected rgba, bgra, rgb24 or gray",
+            self.0
+        )
+    }
+}
+
+impl core::error::Error for ParsePixelFormatError {}
+
+impl FromStr for PixelFormat {
+    type Err = ParsePixelFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "rgba" => Ok(Self::Rgba),
+            "bgra" => Ok(Self::Bgra),
+            "rgb24" | "rgb" => Ok(Self::Rgb24),
+            "gray" | "grey" => Ok(Self::Gray),
+            _ => Err(ParsePixelFormatError(s.trim().to_owned())),
+        }
+    }
+}
+
+/// What the [`RawFramesPlugin`] shows, which can be changed while it runs.
+#[derive(Resource, Debug, Clone)]
+pub struct RawFrames {
+    pub source: FrameSource,
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    /// How many frames are shown per second.
+    pub fps: f32,
+    pub fit: ImageFit,
+    /// Overrides `fit` on the monitors that match. The first match wins.
+    pub monitor_fit: Vec<(MonitorMatcher, ImageFit)>,
+    /// How long to wait before restarting the source after it ends.
+    pub restart_delay: Duration,
+}
+
+impl RawFrames {
+    pub fn new(
+        source: FrameSource,
+        format: PixelFormat,
+        width: u32,
+        height: u32,
+        fps: f32,
+    ) -> Self {
+        Self {
+            source,
+            format,
+            width,
+            height,
+            fps,
+            fit: ImageFit::default(),
+            monitor_fit: Vec::new(),
+            restart_delay: Duration::from_secs(1),
+        }
+    }
+
+    fn frame_size(&self) -> usize {
+        self.width as usize * self.height as usize * self.format.bytes_per_pixel()
+    }
+}
+
+impl TextureScene for RawFrames {
+    fn fit_for(&self, identity: Option<&MonitorIdentity>) -> ImageFit {
+        ImageFit::for_monitor(self.fit, &self.monitor_fit, identity)
+    }
+}
+
+/// A built-in scene that shows raw video frames piped in from another process, like
+/// `ffmpeg -f rawvideo -` produces them.
+///
+/// Frames are read on a background thread and shown at [`RawFrames::fps`] on
+/// [`Time<Virtual>`](bevy::time::Virtual). When the source stalls the last frame stays up, and
+/// when it ends it is started again.
+pub struct RawFramesPlugin {
+    pub frames: RawFrames,
+}
+
+impl Plugin for RawFramesPlugin {
+    fn build(&self, app: &mut App) {
+        RawFrames::add_to(app);
+
+        app.insert_resource(self.frames.clone())
+            .init_resource::<FrameStream>()
+            .add_systems(
+                Update,
+                (start_frame_source, show_raw_frames)
+                    .chain()
+                    .before(follow_scene_monitors::<RawFrames>),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct FrameStream {
+    /// Frames read from the source. Only locked to make the resource `Sync`.
+    frames: Option<Mutex<Receiver<Vec<u8>>>>,
+    /// Set to stop the reader of `frames`.
+    stop: Arc<AtomicBool>,
+    /// When the frame on screen is replaced, in [`Time`] seconds.
+    next_frame: f32,
+}
+
+/// (Re)starts reading frames whenever the [`RawFrames`] change.
+fn start_frame_source(config: Res<RawFrames>, time: Res<Time>, mut stream: ResMut<FrameStream>) {
+    if !config.is_changed() {
+        return;
+    }
+
+    // The previous reader stops along with its command, even while it waits for a frame.
+    stream.stop.store(true, Ordering::Relaxed);
+    stream.stop = Arc::default();
+    stream.frames = None;
+    stream.next_frame = time.elapsed_secs();
+
+    if config.frame_size() == 0 || config.fps <= 0.0 {
+        warn!("Not reading raw frames: the frame size and fps have to be positive");
+        return;
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(QUEUED_FRAMES);
+    let (config, stop) = (config.clone(), stream.stop.clone());
+
+    let reader = thread::Builder::new()
+        .name("raw frame reader".into())
+        .spawn(move || read_frames(&config, &sender, &stop));
+
+    match reader {
+        Ok(_) => stream.frames = Some(Mutex::new(receiver)),
+        Err(err) => warn!("Could not start reading raw frames: {err}"),
+    }
+}
+
+/// Reads frames into `frames`, restarting the source whenever it ends, until `stop` is set.
+fn read_frames(config: &RawFrames, frames: &SyncSender<Vec<u8>>, stop: &AtomicBool) {
+    loop {
+        let result = match &config.source {
+            FrameSource::Command(command) => read_command(command, config, frames, stop),
+            // Opening a FIFO waits for a writer unless it is non-blocking.
+            FrameSource::Fifo(path) => OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(path)
+                .and_then(|fifo| read_stream(&mut StoppableRead::new(fifo, stop), config, frames)),
+        };
+
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+
+        match result {
+            // Playback moved on to another source.
+            Ok(false) => return,
+            Ok(true) => info!("Raw frame source {:?} ended, restarting it", config.source),
+            Err(err) => warn!(
+                "Raw frame source {:?} failed: {err}, restarting it",
+                config.source
+            ),
+        }
+
+        let restart = Instant::now() + config.restart_delay;
+        while let Some(left) = restart.checked_duration_since(Instant::now()) {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            thread::sleep(left.min(STOP_POLL_INTERVAL));
+        }
+    }
+}
+
+fn read_command(
+    command: &str,
+    config: &RawFrames,
+    frames: &SyncSender<Vec<u8>>,
+    stop: &AtomicBool,
+) -> io::Result<bool> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let result = match child.stdout.take() {
+        Some(stdout) => read_stream(&mut StoppableRead::new(stdout, stop), config, frames),
+        None => Ok(true),
+    };
+
+    stop_child(&mut child);
+    result
+}
+
+fn stop_child(child: &mut Child) {
+    // The command may already have exited, which is fine.
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Reads whole frames from `stream` until it ends, returning whether frames are still wanted.
+fn read_stream(
+    stream: &mut impl Read,
+    config: &RawFrames,
+    frames: &SyncSender<Vec<u8>>,
+) -> io::Result<bool> {
+    loop {
+        let mut frame = vec![0; config.frame_size()];
+
+        match stream.read_exact(&mut frame) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(true),
+            Err(err) => return Err(err),
+        }
+
+        if frames.send(config.format.to_texture(frame)).is_err() {
+            return Ok(false);
+        }
+    }
+}
+
+/// Shows a new frame every `1 / fps` seconds, holding the last one while the source stalls.
+fn show_raw_frames(
+    config: Res<RawFrames>,
+    time: Res<Time>,
+    mut stream: ResMut<FrameStream>,
+    mut texture: ResMut<SceneTexture<RawFrames>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let now = time.elapsed_secs();
+    let stream = &mut *stream;
+    let Some(frames) = stream.frames.as_mut() else {
+        return;
+    };
+    let frames = frames.get_mut().unwrap();
+
+    let mut due = None;
+    while stream.next_frame <= now {
+        match frames.try_recv() {
+            Ok(frame) => {
+                // Don't race through frames to catch up after a long stall.
+                stream.next_frame = stream.next_frame.max(now - 1.0) + config.fps.recip();
+                due = Some(frame);
+            }
+            // The next frame is shown as soon as it arrives.
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+        }
+    }
+
+    let Some(frame) = due else {
+        return;
+    };
+
+    texture.upload(
+        &mut images,
+        UVec2::new(config.width, config.height),
+        config.format.texture_format(),
+        &frame,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, ffi::CString, fs, io::Cursor, os::unix::ffi::OsStrExt, process};
+
+    use super::*;
+
+    fn config(source: FrameSource, format: PixelFormat) -> RawFrames {
+        RawFrames::new(source, format, 2, 1, 30.0)
+    }
+
+    /// Starts `read_frames`, sets its stop flag after a moment and checks it returns.
+    fn stops_waiting_for(source: FrameSource) {
+        let config = config(source, PixelFormat::Rgba);
+        let (sender, _receiver) = mpsc::sync_channel(QUEUED_FRAMES);
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stop = stop.clone();
+            thread::spawn(move || read_frames(&config, &sender, &stop))
+        };
+
+        thread::sleep(STOP_POLL_INTERVAL * 3);
+        stop.store(true, Ordering::Relaxed);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !reader.is_finished() {
+            assert!(Instant::now() < deadline, "the reader didn't stop");
+            thread::sleep(STOP_POLL_INTERVAL);
+        }
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn pixel_formats() {
+        assert_eq!("RGBA".parse(), Ok(PixelFormat::Rgba));
+        assert_eq!(" bgra ".parse(), Ok(PixelFormat::Bgra));
+        assert_eq!("rgb".parse(), Ok(PixelFormat::Rgb24));
+        assert_eq!("rgb24".parse(), Ok(PixelFormat::Rgb24));
+        assert_eq!("grey".parse(), Ok(PixelFormat::Gray));
+        assert_eq!(
+            "yuv420p".parse::<PixelFormat>(),
+            Err(ParsePixelFormatError("yuv420p".into()))
+        );
+    }
+
+    #[test]
+    fn converts_to_texture_layout() {
+        assert_eq!(PixelFormat::Bgra.to_texture(vec![1, 2, 3, 4]), [1, 2, 3, 4]);
+        assert_eq!(
+            PixelFormat::Rgb24.to_texture(vec![1, 2, 3, 4, 5, 6]),
+            [1, 2, 3, 255, 4, 5, 6, 255]
+        );
+        assert_eq!(
+            PixelFormat::Gray.to_texture(vec![7, 8]),
+            [7, 7, 7, 255, 8, 8, 8, 255]
+        );
+        assert_eq!(
+            PixelFormat::Bgra.texture_format(),
+            TextureFormat::Bgra8UnormSrgb
+        );
+        assert_eq!(
+            PixelFormat::Gray.texture_format(),
+            TextureFormat::Rgba8UnormSrgb
+        );
+    }
+
+    #[test]
+    fn reads_whole_frames() {
+        let config = config(FrameSource::Command(String::new()), PixelFormat::Rgb24);
+        let (sender, receiver) = mpsc::sync_channel(QUEUED_FRAMES);
+
+        // Two frames of six bytes, and the start of a third that never finishes.
+        let mut stream = Cursor::new((0..15).collect::<Vec<u8>>());
+        assert!(read_stream(&mut stream, &config, &sender).unwrap());
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            [
+                vec![0, 1, 2, 255, 3, 4, 5, 255],
+                vec![6, 7, 8, 255, 9, 10, 11, 255]
+            ]
+        );
+
+        // Nothing wants frames anymore.
+        drop(receiver);
+        let mut stream = Cursor::new(vec![0; 6]);
+        assert!(!read_stream(&mut stream, &config, &sender).unwrap());
+    }
+
+    #[test]
+    fn stops_waiting_for_a_command() {
+        stops_waiting_for(FrameSource::Command("sleep 60".into()));
+    }
+
+    #[test]
+    fn stops_waiting_for_a_fifo_writer() {
+        let path = env::temp_dir().join(format!("raw-frames-{}.fifo", process::id()));
+        let _ = fs::remove_file(&path);
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        // SAFETY: `c_path` is a valid C string.
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        stops_waiting_for(FrameSource::Fifo(path.clone()));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
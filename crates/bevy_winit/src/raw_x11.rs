@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use bevy_ecs::{event::Event, system::Resource};
+use tracing::warn;
+use winit::{
+    event_loop::ActiveEventLoop,
+    platform::x11::{ActiveEventLoopExtX11, X11EventKinds, register_x11_event_hook},
+};
+use x11rb::protocol::{Event as X11Event, xproto::Window as XWindow};
+
+/// An X event on a window listened to through [`RawX11Listeners`]. This is an escape hatch for
+/// power users reacting to what other clients do, like `_NET_ACTIVE_WINDOW` changing on the
+/// root window or a new top-level window being mapped.
+///
+/// winit still handles the event as usual. Sending these events does nothing.
+#[derive(Debug, Clone, Event)]
+pub struct RawX11Event {
+    pub event: X11Event,
+}
+
+/// The windows [`RawX11Event`]s are sent for.
+///
+/// Several systems can listen to the same window, so listening only ever adds event kinds until
+/// [`RawX11Listeners::stop`] is called. Readers should filter for the events they care about.
+#[derive(Resource, Debug, Default)]
+pub struct RawX11Listeners {
+    listened: HashMap<XWindow, X11EventKinds>,
+    pending: Vec<XWindow>,
+}
+
+impl RawX11Listeners {
+    /// Sends the events of `kinds` on `window` as [`RawX11Event`]s from the next update on, on
+    /// top of what is already listened to on it.
+    ///
+    /// `window` is usually the [root window](crate::X11Desktop::root) or a window of another
+    /// client.
+    pub fn listen(&mut self, window: XWindow, kinds: X11EventKinds) {
+        let listened = self.listened.entry(window).or_default();
+        let combined = X11EventKinds {
+            property: listened.property || kinds.property,
+            structure: listened.structure || kinds.structure,
+            substructure: listened.substructure || kinds.substructure,
+        };
+
+        if *listened != combined {
+            *listened = combined;
+            self.pending.push(window);
+        }
+    }
+
+    /// Stops sending events on `window`.
+    pub fn stop(&mut self, window: XWindow) {
+        if self.listened.remove(&window).is_some() {
+            self.pending.push(window);
+        }
+    }
+
+    /// What is listened to on `window`.
+    pub fn kinds(&self, window: XWindow) -> X11EventKinds {
+        self.listened.get(&window).copied().unwrap_or_default()
+    }
+}
+
+/// Events caught by the winit hook that haven't been sent as [`RawX11Event`]s yet.
+#[derive(Resource, Clone, Default)]
+pub(crate) struct RawX11Queue(Arc<Mutex<Vec<X11Event>>>);
+
+impl RawX11Queue {
+    /// Creates the queue and registers the winit hook that fills it.
+    pub(crate) fn register() -> Self {
+        let queue = Self::default();
+        let events = queue.0.clone();
+
+        register_x11_event_hook(Box::new(move |event| {
+            events.lock().unwrap().push(event.clone());
+        }));
+
+        queue
+    }
+
+    pub(crate) fn take(&self) -> Vec<RawX11Event> {
+        let events = core::mem::take(&mut *self.0.lock().unwrap());
+
+        events
+            .into_iter()
+            .map(|event| RawX11Event { event })
+            .collect()
+    }
+}
+
+/// Selects the events requested through [`RawX11Listeners`] since the last call.
+pub(crate) fn apply_raw_x11_listeners(
+    event_loop: &ActiveEventLoop,
+    listeners: &mut RawX11Listeners,
+) {
+    for window in core::mem::take(&mut listeners.pending) {
+        let kinds = listeners.kinds(window);
+
+        if let Err(err) = event_loop.listen_x11_events(window, kinds) {
+            warn!("Could not listen to X events on window {window:#x}: {err}");
+        }
+    }
+}
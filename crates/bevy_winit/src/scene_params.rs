@@ -0,0 +1,114 @@
+use bevy::{
+    ecs::reflect::ReflectResource,
+    reflect::{GetPath, PartialReflect, TypeRegistry, serde::TypedReflectDeserializer},
+};
+use bevy_ecs::world::World;
+use serde::de::DeserializeSeed;
+use serde_json::Value;
+
+/// A scene parameter set by name from outside, like a data feed or OSC: a field of a resource
+/// that derives `Reflect`, is registered with `App::register_type` and has `#[reflect(Resource)]`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SceneParam<'a> {
+    /// The type path of the resource, e.g. `my_wallpaper::Weather`, or its short path.
+    pub(crate) resource: &'a str,
+    /// A reflection path into the resource, e.g. `sky.cloud_cover`.
+    pub(crate) field: &'a str,
+}
+
+impl SceneParam<'_> {
+    /// Deserializes `value` into the field the way `bevy_reflect` deserializes the field's type.
+    pub(crate) fn set(
+        self,
+        world: &mut World,
+        registry: &TypeRegistry,
+        value: &Value,
+    ) -> Result<(), String> {
+        self.modify(world, registry, |field| apply_json(field, registry, value))
+    }
+
+    /// The value of an `f32` or `f64` field.
+    pub(crate) fn number(self, world: &World, registry: &TypeRegistry) -> Option<f64> {
+        let resource = reflect_resource(registry, self.resource)
+            .ok()?
+            .reflect(world)?;
+        let field = resource.reflect_path(self.field).ok()?;
+
+        field
+            .try_downcast_ref::<f32>()
+            .map(|number| f64::from(*number))
+            .or_else(|| field.try_downcast_ref::<f64>().copied())
+    }
+
+    /// Sets an `f32` or `f64` field.
+    pub(crate) fn set_number(
+        self,
+        world: &mut World,
+        registry: &TypeRegistry,
+        number: f64,
+    ) -> Result<(), String> {
+        self.modify(world, registry, |field| {
+            if let Some(field) = field.try_downcast_mut::<f32>() {
+                *field = number as f32;
+            } else if let Some(field) = field.try_downcast_mut::<f64>() {
+                *field = number;
+            } else {
+                return Err("the field isn't an f32 or f64".into());
+            }
+            Ok(())
+        })
+    }
+
+    fn modify(
+        self,
+        world: &mut World,
+        registry: &TypeRegistry,
+        modify: impl FnOnce(&mut dyn PartialReflect) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut resource = reflect_resource(registry, self.resource)?
+            .reflect_mut(world)
+            .ok_or_else(|| format!("there is no {} resource", self.resource))?;
+        let field = resource
+            .reflect_path_mut(self.field)
+            .map_err(|err| err.to_string())?;
+
+        modify(field)
+    }
+}
+
+/// Deserializes `value` into `field` the way `bevy_reflect` deserializes the field's type.
+pub(crate) fn apply_json(
+    field: &mut dyn PartialReflect,
+    registry: &TypeRegistry,
+    value: &Value,
+) -> Result<(), String> {
+    let type_id = field
+        .get_represented_type_info()
+        .ok_or("the field's type isn't known")?
+        .type_id();
+    let registration = registry
+        .get(type_id)
+        .ok_or("the field's type isn't registered")?;
+
+    let deserialized = TypedReflectDeserializer::new(registration, registry)
+        .deserialize(value)
+        .map_err(|err| err.to_string())?;
+
+    field
+        .try_apply(deserialized.as_partial_reflect())
+        .map_err(|err| err.to_string())
+}
+
+fn reflect_resource<'r>(
+    registry: &'r TypeRegistry,
+    type_path: &str,
+) -> Result<&'r ReflectResource, String> {
+    let registration = registry
+        .get_with_type_path(type_path)
+        .or_else(|| registry.get_with_short_type_path(type_path))
+        .ok_or_else(|| format!("no registered type {type_path}"))?;
+
+    registration
+        .data::<ReflectResource>()
+        .ok_or_else(|| format!("{type_path} isn't a reflected resource"))
+}
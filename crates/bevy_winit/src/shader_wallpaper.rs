@@ -0,0 +1,199 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{
+    asset::{Asset, Assets, Handle},
+    math::{Vec2, Vec4, primitives::Rectangle},
+    reflect::TypePath,
+    render::{
+        mesh::{Mesh, Mesh2d},
+        render_resource::{AsBindGroup, Shader, ShaderRef},
+    },
+    sprite::{Material2d, Material2dPlugin, MeshMaterial2d},
+    time::Time,
+    transform::components::Transform,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    component::Component,
+    entity::Entity,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Commands, Query, Res, ResMut, Resource},
+};
+use bevy_window::Monitor;
+use tracing::warn;
+
+use crate::{
+    desktop_2d::{Desktop2dPlugin, to_world},
+    file_drop::{CurrentWallpaper, WallpaperSourceKind},
+};
+
+/// Where the fragment shader of the [`ShaderWallpaper`] is loaded into.
+const WALLPAPER_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x3f6b_2c1e_8d4a_4b9f_a7e2_51c0_d8e3_9a14);
+
+/// What the [`ShaderWallpaperPlugin`] renders, which can be changed while it runs.
+///
+/// The shader is a WGSL file with a `fragment` entry point. It is given the monitor's size in
+/// pixels in `xy` and the [`Time`] in seconds, wrapping every hour, in `z`:
+///
+/// ```wgsl
+/// #import bevy_sprite::mesh2d_vertex_output::VertexOutput
+///
+/// @group(2) @binding(0) var<uniform> wallpaper: vec4<f32>;
+///
+/// @fragment
+/// fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+///     return vec4(in.uv, 0.5 + 0.5 * sin(wallpaper.z), 1.0);
+/// }
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct ShaderWallpaper {
+    pub path: PathBuf,
+}
+
+impl ShaderWallpaper {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// A built-in scene that renders a fragment shader over every monitor.
+///
+/// Like the other scenes it runs on [`Time<Virtual>`](bevy::time::Virtual), so pausing the
+/// wallpaper or `ReducedMotion` hold the shader still.
+pub struct ShaderWallpaperPlugin {
+    pub shader: ShaderWallpaper,
+}
+
+impl ShaderWallpaperPlugin {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            shader: ShaderWallpaper::new(path),
+        }
+    }
+}
+
+impl Plugin for ShaderWallpaperPlugin {
+    fn build(&self, app: &mut App) {
+        Desktop2dPlugin::add_to(app);
+
+        app.add_plugins(Material2dPlugin::<ShaderWallpaperMaterial>::default())
+            .insert_resource(self.shader.clone())
+            .add_systems(
+                Update,
+                (
+                    show_current_wallpaper,
+                    load_wallpaper_shader,
+                    follow_shader_monitors,
+                    layout_shader_monitors,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Default)]
+struct ShaderWallpaperMaterial {
+    /// The size of the monitor in `xy` and the time in `z`.
+    #[uniform(0)]
+    wallpaper: Vec4,
+}
+
+impl Material2d for ShaderWallpaperMaterial {
+    fn fragment_shader() -> ShaderRef {
+        WALLPAPER_SHADER.into()
+    }
+}
+
+/// The quad the shader is rendered onto on a monitor.
+#[derive(Component)]
+struct ShaderQuad {
+    monitor: Entity,
+}
+
+/// Renders shaders that become the [`CurrentWallpaper`], e.g. by being dropped onto the desktop.
+fn show_current_wallpaper(
+    current: Option<Res<CurrentWallpaper>>,
+    mut shader: ResMut<ShaderWallpaper>,
+) {
+    let kinds = [WallpaperSourceKind::Shader];
+    if let Some(path) = CurrentWallpaper::changed_to(&current, &kinds)
+        && shader.path != path
+    {
+        shader.path = path.to_owned();
+    }
+}
+
+/// Reads the shader whenever the [`ShaderWallpaper`] changes. Pipelines using the previous one
+/// are rebuilt with it, and errors in it are logged when it is compiled.
+fn load_wallpaper_shader(shader: Res<ShaderWallpaper>, mut shaders: ResMut<Assets<Shader>>) {
+    if !shader.is_changed() {
+        return;
+    }
+
+    match fs::read_to_string(&shader.path) {
+        Ok(source) => shaders.insert(
+            &WALLPAPER_SHADER,
+            Shader::from_wgsl(source, shader.path.display().to_string()),
+        ),
+        Err(err) => warn!("Could not read the shader {:?}: {err}", shader.path),
+    }
+}
+
+/// Gives every monitor a quad to render the shader onto.
+fn follow_shader_monitors(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ShaderWallpaperMaterial>>,
+    monitors: Query<Entity, With<Monitor>>,
+    quads: Query<(Entity, &ShaderQuad)>,
+) {
+    for (entity, quad) in &quads {
+        if !monitors.contains(quad.monitor) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for monitor in &monitors {
+        if !quads.iter().any(|(_, quad)| quad.monitor == monitor) {
+            commands.spawn((
+                Mesh2d(meshes.add(Rectangle::new(1.0, 1.0))),
+                MeshMaterial2d(materials.add(ShaderWallpaperMaterial::default())),
+                ShaderQuad { monitor },
+            ));
+        }
+    }
+}
+
+/// Stretches the quads over their monitors and passes the time on to the shader.
+fn layout_shader_monitors(
+    time: Res<Time>,
+    monitors: Query<&Monitor>,
+    mut materials: ResMut<Assets<ShaderWallpaperMaterial>>,
+    mut quads: Query<(
+        &ShaderQuad,
+        &MeshMaterial2d<ShaderWallpaperMaterial>,
+        &mut Transform,
+    )>,
+) {
+    for (quad, material, mut transform) in &mut quads {
+        let Ok(monitor) = monitors.get(quad.monitor) else {
+            continue;
+        };
+        let size = Vec2::new(
+            monitor.physical_width as f32,
+            monitor.physical_height as f32,
+        );
+
+        transform.set_if_neq(
+            Transform::from_translation(to_world(monitor.physical_position, size).extend(0.0))
+                .with_scale(size.extend(1.0)),
+        );
+
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.wallpaper = size.extend(time.elapsed_secs_wrapped()).extend(0.0);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use core::time::Duration;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::{AssetServer, Assets, Handle, LoadState},
+    color::{Alpha, Color},
+    image::Image,
+    math::{Vec2, Vec3},
+    render::view::Visibility,
+    sprite::Sprite,
+    time::Time,
+    transform::components::Transform,
+    utils::Instant,
+};
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    component::Component,
+    entity::Entity,
+    event::EventReader,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Commands, Query, Res, ResMut, Resource},
+};
+use bevy_window::Monitor;
+use tracing::warn;
+
+use crate::{
+    desktop_2d::{Desktop2dPlugin, ImageFit},
+    file_drop::{CurrentWallpaper, WallpaperSourceKind},
+    monitor_identity::{MonitorIdentity, MonitorMatcher},
+    wallpaper_command::WallpaperCommand,
+};
+
+/// The file extensions picked up from a slideshow directory.
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// What the [`SlideshowPlugin`] shows, which can be changed while it runs.
+#[derive(Resource, Debug, Clone)]
+pub struct Slideshow {
+    /// An image, or a directory whose `png` and `jpeg` images are shown in turn.
+    pub source: PathBuf,
+    pub fit: ImageFit,
+    /// Overrides `fit` on the monitors that match. The first match wins.
+    pub monitor_fit: Vec<(MonitorMatcher, ImageFit)>,
+    /// How long each image is shown.
+    pub interval: Duration,
+    /// How long the crossfade into the next image takes.
+    pub transition: Duration,
+    /// Show the images in random order, reshuffled every round, instead of by file name.
+    pub shuffle: bool,
+    /// Slowly pans and zooms into each image, up to this zoom factor (e.g. `1.15`). `None` shows
+    /// still images. Tiled monitors always show still images.
+    pub ken_burns: Option<f32>,
+    /// How often the directory is checked for added and removed images.
+    pub rescan_interval: Duration,
+}
+
+impl Slideshow {
+    pub fn new(source: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            fit: ImageFit::default(),
+            monitor_fit: Vec::new(),
+            interval: Duration::from_secs(5 * 60),
+            transition: Duration::from_secs(2),
+            shuffle: false,
+            ken_burns: Some(1.15),
+            rescan_interval: Duration::from_secs(5),
+        }
+    }
+
+    /// The fit used on a monitor.
+    pub fn fit_for(&self, identity: Option<&MonitorIdentity>) -> ImageFit {
+        ImageFit::for_monitor(self.fit, &self.monitor_fit, identity)
+    }
+}
+
+impl Default for Slideshow {
+    /// A slideshow of `~/Pictures`.
+    fn default() -> Self {
+        let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        Self::new(home.join("Pictures"))
+    }
+}
+
+/// A built-in scene that shows an image or a slideshow of a directory on every monitor, with
+/// crossfades between images.
+///
+/// It brings its own 2D camera for each window, and a window spanning several monitors shows the
+/// image once per monitor.
+#[derive(Default)]
+pub struct SlideshowPlugin {
+    pub slideshow: Slideshow,
+}
+
+impl SlideshowPlugin {
+    pub fn new(source: impl Into<PathBuf>) -> Self {
+        Self {
+            slideshow: Slideshow::new(source),
+        }
+    }
+}
+
+impl Plugin for SlideshowPlugin {
+    fn build(&self, app: &mut App) {
+        Desktop2dPlugin::add_to(app);
+
+        app.insert_resource(self.slideshow.clone())
+            .init_resource::<SlideshowState>()
+            .add_event::<WallpaperCommand>()
+            .add_systems(
+                Update,
+                (
+                    show_current_wallpaper,
+                    scan_slideshow,
+                    skip_slide,
+                    advance_slideshow,
+                    follow_slideshow_monitors,
+                    layout_slideshow,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// An image on screen, or fading in.
+#[derive(Debug)]
+struct Slide {
+    path: PathBuf,
+    image: Handle<Image>,
+    /// When its crossfade started, in [`Time`] seconds.
+    started: f32,
+}
+
+#[derive(Resource, Debug, Default)]
+struct SlideshowState {
+    /// The images of the source, in the order they're shown.
+    images: Vec<PathBuf>,
+    /// The index in `images` shown next.
+    next: usize,
+    next_scan: Option<Instant>,
+    scan_failed: bool,
+    /// Show the next image right away instead of waiting for the interval.
+    advance_now: bool,
+    /// The next image while it loads.
+    pending: Option<(PathBuf, Handle<Image>)>,
+    current: Option<Slide>,
+    incoming: Option<Slide>,
+}
+
+impl SlideshowState {
+    fn next_image(&mut self, shuffle: bool) -> Option<PathBuf> {
+        if self.images.is_empty() {
+            return None;
+        }
+
+        if self.next >= self.images.len() {
+            self.next = 0;
+            if shuffle {
+                fastrand::shuffle(&mut self.images);
+            }
+        }
+
+        self.next += 1;
+        Some(self.images[self.next - 1].clone())
+    }
+}
+
+/// A pan and zoom from one framing of an image to another.
+#[derive(Debug, Clone, Copy)]
+struct KenBurns {
+    /// The pan in `x` and `y`, from -1 to 1, and the zoom in `z`.
+    from: Vec3,
+    to: Vec3,
+}
+
+impl KenBurns {
+    const STILL: Self = Self {
+        from: Vec3::Z,
+        to: Vec3::Z,
+    };
+
+    fn random(max_zoom: f32) -> Self {
+        let framing = || {
+            Vec3::new(
+                fastrand::f32() * 2.0 - 1.0,
+                fastrand::f32() * 2.0 - 1.0,
+                1.0 + fastrand::f32() * (max_zoom - 1.0).max(0.0),
+            )
+        };
+
+        Self {
+            from: framing(),
+            to: framing(),
+        }
+    }
+
+    /// The pan and zoom at `t`, from 0 to 1, easing in and out.
+    fn at(&self, t: f32) -> (Vec2, f32) {
+        let eased = t * t * (3.0 - 2.0 * t);
+        let framing = self.from.lerp(self.to, eased);
+        (framing.truncate(), framing.z)
+    }
+}
+
+/// One of the two images shown on a monitor, crossfading into each other.
+#[derive(Component)]
+struct SlideshowSprite {
+    monitor: Entity,
+    /// Shows the incoming image above the current one.
+    incoming: bool,
+    ken_burns: KenBurns,
+}
+
+/// Shows images and directories that become the [`CurrentWallpaper`], e.g. by being dropped onto
+/// the desktop.
+fn show_current_wallpaper(
+    current: Option<Res<CurrentWallpaper>>,
+    mut slideshow: ResMut<Slideshow>,
+) {
+    let kinds = [WallpaperSourceKind::Image, WallpaperSourceKind::Directory];
+    if let Some(path) = CurrentWallpaper::changed_to(&current, &kinds)
+        && slideshow.source != path
+    {
+        slideshow.source = path.to_owned();
+    }
+}
+
+fn scan_slideshow(slideshow: Res<Slideshow>, mut state: ResMut<SlideshowState>) {
+    let now = Instant::now();
+    if !slideshow.is_changed() && state.next_scan.is_some_and(|at| at > now) {
+        return;
+    }
+    state.next_scan = now.checked_add(slideshow.rescan_interval);
+
+    let mut images = match list_images(&slideshow.source) {
+        Ok(images) => {
+            state.scan_failed = false;
+            images
+        }
+        Err(err) => {
+            if !state.scan_failed {
+                warn!("Could not read the slideshow {:?}: {err}", slideshow.source);
+            }
+            state.scan_failed = true;
+            Vec::new()
+        }
+    };
+
+    let mut known = state.images.clone();
+    known.sort();
+    if known == images && !slideshow.is_changed() {
+        return;
+    }
+
+    if slideshow.shuffle {
+        fastrand::shuffle(&mut images);
+    }
+
+    // Carry on after the image on screen.
+    let shown = state.current.as_ref().map(|slide| &slide.path);
+    state.next = shown
+        .and_then(|shown| images.iter().position(|path| path == shown))
+        .map_or(0, |index| index + 1);
+    state.images = images;
+
+    if slideshow.is_changed() && !slideshow.is_added() {
+        state.advance_now = true;
+    }
+}
+
+/// Moves on to the next image on [`WallpaperCommand::Next`].
+fn skip_slide(mut commands: EventReader<WallpaperCommand>, mut state: ResMut<SlideshowState>) {
+    if commands
+        .read()
+        .any(|command| *command == WallpaperCommand::Next)
+    {
+        state.advance_now = true;
+    }
+}
+
+/// Lists the images in `source`, sorted by file name, or `source` itself if it is a file.
+fn list_images(source: &Path) -> io::Result<Vec<PathBuf>> {
+    if !fs::metadata(source)?.is_dir() {
+        return Ok(vec![source.to_owned()]);
+    }
+
+    let mut images: Vec<PathBuf> = fs::read_dir(source)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    IMAGE_EXTENSIONS
+                        .iter()
+                        .any(|known| extension.eq_ignore_ascii_case(known))
+                })
+        })
+        .collect();
+
+    images.sort();
+    Ok(images)
+}
+
+/// Loads the next image when the current one has been shown long enough, and crossfades into
+/// it once it is loaded.
+fn advance_slideshow(
+    slideshow: Res<Slideshow>,
+    mut state: ResMut<SlideshowState>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut sprites: Query<&mut SlideshowSprite>,
+) {
+    let now = time.elapsed_secs();
+    let transition = slideshow.transition.as_secs_f32();
+    let state = &mut *state;
+
+    if state
+        .incoming
+        .as_ref()
+        .is_some_and(|incoming| now - incoming.started >= transition)
+    {
+        state.current = state.incoming.take();
+        for mut sprite in &mut sprites {
+            sprite.incoming = !sprite.incoming;
+        }
+    }
+
+    if let Some((path, image)) = &state.pending {
+        match asset_server.get_load_state(image) {
+            Some(LoadState::Loaded) => {
+                for mut sprite in &mut sprites {
+                    if sprite.incoming {
+                        sprite.ken_burns = slideshow
+                            .ken_burns
+                            .map_or(KenBurns::STILL, KenBurns::random);
+                    }
+                }
+
+                state.incoming = state.pending.take().map(|(path, image)| Slide {
+                    path,
+                    image,
+                    started: now,
+                });
+            }
+            Some(LoadState::Failed(err)) => {
+                warn!("Skipping {path:?}: {err}");
+                state.pending = None;
+            }
+            _ => {}
+        }
+    }
+
+    let due = state.advance_now
+        || state.current.as_ref().is_none_or(|current| {
+            now - current.started >= transition + slideshow.interval.as_secs_f32()
+        });
+
+    if !due || state.pending.is_some() || state.incoming.is_some() {
+        return;
+    }
+
+    let Some(path) = state.next_image(slideshow.shuffle) else {
+        return;
+    };
+    state.advance_now = false;
+
+    // A single image stays up without fading into itself.
+    if state
+        .current
+        .as_ref()
+        .is_some_and(|current| current.path == path)
+    {
+        return;
+    }
+
+    let image = asset_server.load(path.clone());
+    state.pending = Some((path, image));
+}
+
+/// Gives every monitor a pair of sprites to crossfade between.
+fn follow_slideshow_monitors(
+    mut commands: Commands,
+    slideshow: Res<Slideshow>,
+    monitors: Query<Entity, With<Monitor>>,
+    sprites: Query<(Entity, &SlideshowSprite)>,
+) {
+    for (entity, sprite) in &sprites {
+        if !monitors.contains(sprite.monitor) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for monitor in &monitors {
+        if sprites.iter().any(|(_, sprite)| sprite.monitor == monitor) {
+            continue;
+        }
+
+        for incoming in [false, true] {
+            commands.spawn((
+                Sprite::default(),
+                Visibility::Hidden,
+                SlideshowSprite {
+                    monitor,
+                    incoming,
+                    ken_burns: slideshow
+                        .ken_burns
+                        .map_or(KenBurns::STILL, KenBurns::random),
+                },
+            ));
+        }
+    }
+}
+
+/// Places the images on their monitors, with their fit, pan, zoom and fade.
+fn layout_slideshow(
+    slideshow: Res<Slideshow>,
+    state: Res<SlideshowState>,
+    time: Res<Time>,
+    images: Res<Assets<Image>>,
+    monitors: Query<(&Monitor, Option<&MonitorIdentity>)>,
+    mut sprites: Query<(
+        &SlideshowSprite,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let now = time.elapsed_secs();
+    let transition = slideshow.transition.as_secs_f32();
+    // Ken Burns runs from the start of the fade in to the end of the fade out.
+    let shown_for = slideshow.interval.as_secs_f32() + 2.0 * transition;
+
+    for (slot, mut sprite, mut transform, mut visibility) in &mut sprites {
+        let slide = match slot.incoming {
+            true => state.incoming.as_ref(),
+            false => state.current.as_ref(),
+        };
+        let shown = slide.and_then(|slide| Some((slide, images.get(&slide.image)?)));
+
+        let (Some((slide, image)), Ok((monitor, identity))) = (shown, monitors.get(slot.monitor))
+        else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+
+        let elapsed = now - slide.started;
+
+        let alpha = match slot.incoming {
+            true if transition > 0.0 => (elapsed / transition).clamp(0.0, 1.0),
+            _ => 1.0,
+        };
+        let (pan, zoom) = slot
+            .ken_burns
+            .at((elapsed / shown_for.max(f32::EPSILON)).clamp(0.0, 1.0));
+
+        if sprite.image != slide.image {
+            sprite.image = slide.image.clone();
+        }
+        sprite.color = Color::WHITE.with_alpha(alpha);
+        transform.translation.z = if slot.incoming { 1.0 } else { 0.0 };
+
+        slideshow.fit_for(identity).apply(
+            &mut sprite,
+            &mut transform,
+            monitor,
+            image.size_f32(),
+            pan,
+            zoom,
+        );
+    }
+}
@@ -27,8 +27,10 @@ use winit::{
 
 use super::{
     AppSendEvent, CreateMonitorParams, CreateWindowParams, EventLoopProxyWrapper,
-    RawWinitWindowEvent, UpdateMode, WinitSettings, WinitWindows, converters, create_monitors,
-    create_windows, system::CachedWindow,
+    RawWinitWindowEvent, RawX11Event, RawX11Listeners, UpdateMode, WinitSettings, WinitWindows,
+    converters, create_monitors, create_windows,
+    raw_x11::{RawX11Queue, apply_raw_x11_listeners},
+    system::CachedWindow,
 };
 use bevy_window::{
     AppLifecycle, CursorEntered, CursorLeft, CursorMoved, FileDragAndDrop, Ime, RequestRedraw,
@@ -205,6 +207,20 @@ impl<T: Event> ApplicationHandler<T> for WinitAppRunnerState<T> {
     ) {
         self.window_event_received = true;
 
+        // The preview window has no entity, but its events are still sent raw for key bindings.
+        if self
+            .world()
+            .non_send_resource::<WinitWindows>()
+            .is_preview_window(window_id)
+        {
+            if matches!(event, WindowEvent::CloseRequested) {
+                self.world_mut().send_event(AppExit::Success);
+            }
+            self.raw_winit_events
+                .push(RawWinitWindowEvent { window_id, event });
+            return;
+        }
+
         let (
             mut window_resized,
             mut window_backend_scale_factor_changed,
@@ -442,6 +458,7 @@ impl<T: Event> ApplicationHandler<T> for WinitAppRunnerState<T> {
         create_monitor.apply(self.world_mut());
         create_windows(event_loop, create_window.get_mut(self.world_mut()));
         create_window.apply(self.world_mut());
+        self.forward_raw_x11_events(event_loop);
 
         // TODO: This is a workaround for https://github.com/bevyengine/bevy/issues/17488
         //       while preserving the iOS fix in https://github.com/bevyengine/bevy/pull/11245
@@ -639,6 +656,27 @@ impl<T: Event> WinitAppRunnerState<T> {
         }
     }
 
+    /// Selects newly requested [`RawX11Listeners`] and sends the events caught since the last
+    /// iteration, which counts as a window event for the [`UpdateMode`].
+    fn forward_raw_x11_events(&mut self, event_loop: &ActiveEventLoop) {
+        let world = self.world_mut();
+
+        if let Some(mut listeners) = world.get_resource_mut::<RawX11Listeners>() {
+            apply_raw_x11_listeners(event_loop, listeners.bypass_change_detection());
+        }
+
+        let Some(events) = world.get_resource::<RawX11Queue>().map(RawX11Queue::take) else {
+            return;
+        };
+
+        if !events.is_empty() {
+            world
+                .resource_mut::<Events<RawX11Event>>()
+                .send_batch(events);
+            self.window_event_received = true;
+        }
+    }
+
     fn forward_bevy_events(&mut self) {
         let raw_winit_events = self.raw_winit_events.drain(..).collect::<Vec<_>>();
         let buffered_events = self.bevy_window_events.drain(..).collect::<Vec<_>>();
@@ -788,6 +826,10 @@ pub fn winit_runner<T: Event>(mut app: App, event_loop: EventLoop<T>) -> AppExit
         error!("winit event loop returned an error: {err}");
     }
 
+    // Scene state is saved however the loop ended.
+    #[cfg(feature = "persistence")]
+    crate::persistence::save_on_exit(runner_state.world_mut());
+
     // If everything is working correctly then the event loop only exits after it's sent an exit code.
     runner_state.app_exit.unwrap_or_else(|| {
         error!("Failed to receive an app exit code! This is a bug");
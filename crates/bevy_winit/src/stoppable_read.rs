@@ -0,0 +1,90 @@
+use core::time::Duration;
+use std::{
+    io::{self, Read},
+    os::fd::AsRawFd,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::utils::Instant;
+
+/// How often a read waiting for data checks whether it should stop.
+pub(crate) const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reads from a pipe, FIFO or socket without blocking past `stop` being set.
+///
+/// Once `stop` is set, reads return end of file. The reader may also be non-blocking, like a
+/// FIFO opened with `O_NONBLOCK` so opening it doesn't wait for a writer.
+pub(crate) struct StoppableRead<'a, R> {
+    inner: R,
+    stop: &'a AtomicBool,
+    /// How long to wait for data before failing with [`io::ErrorKind::TimedOut`].
+    timeout: Option<Duration>,
+}
+
+impl<'a, R: Read + AsRawFd> StoppableRead<'a, R> {
+    pub(crate) fn new(inner: R, stop: &'a AtomicBool) -> Self {
+        Self {
+            inner,
+            stop,
+            timeout: None,
+        }
+    }
+
+    pub(crate) fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Waits until there is something to read, returning `false` once `stop` is set.
+    fn wait_readable(&self, deadline: Option<Instant>) -> io::Result<bool> {
+        let mut fd = libc::pollfd {
+            fd: self.inner.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        loop {
+            if self.stop.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+
+            // SAFETY: `fd` is a single valid `pollfd` for the duration of the call.
+            let ready = unsafe { libc::poll(&mut fd, 1, STOP_POLL_INTERVAL.as_millis() as i32) };
+            match ready {
+                // Hang ups and errors are readable too, and reported by the read.
+                1.. => return Ok(true),
+                0 => {}
+                _ => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read + AsRawFd> Read for StoppableRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self
+            .timeout
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+
+        loop {
+            if !self.wait_readable(deadline)? {
+                return Ok(0);
+            }
+
+            match self.inner.read(buf) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::utils::Instant;
+#[cfg(feature = "pbr")]
+use bevy::{
+    color::{Color, Mix},
+    ecs::{component::Component, world::Ref},
+    math::Vec3,
+    pbr::{AmbientLight, DirectionalLight, light_consts},
+    transform::components::Transform,
+};
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    event::{Event, EventWriter},
+    system::{Local, Res, ResMut, Resource},
+};
+#[cfg(feature = "pbr")]
+use bevy_ecs::{schedule::IntoSystemConfigs, system::Query};
+
+/// The elevation, in degrees, below which it is night: the end of civil twilight.
+const NIGHT_BELOW: f64 = -6.0;
+
+/// The elevation, in degrees, above which it is day rather than dawn or dusk.
+const DAY_ABOVE: f64 = 6.0;
+
+/// Where the [`SunPlugin`] computes the sun's position for, which can be changed while it runs.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SunConfig {
+    /// In degrees, north positive.
+    pub latitude: f64,
+    /// In degrees, east positive. `None` estimates it from the UTC offset of the system time
+    /// zone, which puts the sun roughly, but not exactly, where it is.
+    pub longitude: Option<f64>,
+    /// A time to show instead of now, e.g. to preview a sunset.
+    pub time: Option<SystemTime>,
+    /// How often the position is updated.
+    pub interval: Duration,
+    /// The brightness of the [`AmbientLight`] at day, which follows the sun if set. It fades to a
+    /// twentieth of this at night.
+    #[cfg(feature = "pbr")]
+    pub ambient: Option<f32>,
+}
+
+impl Default for SunConfig {
+    fn default() -> Self {
+        Self {
+            latitude: 0.0,
+            longitude: None,
+            time: None,
+            interval: Duration::from_secs(1),
+            #[cfg(feature = "pbr")]
+            ambient: None,
+        }
+    }
+}
+
+impl SunConfig {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude: Some(longitude),
+            ..Default::default()
+        }
+    }
+
+    /// Where the sun is for this configuration right now.
+    fn sun(&self) -> SunState {
+        sun_position(
+            self.time.unwrap_or_else(SystemTime::now),
+            self.latitude,
+            self.longitude.unwrap_or_else(time_zone_longitude),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SunPhase {
+    /// The sun is near the horizon and rising.
+    Dawn,
+    #[default]
+    Day,
+    /// The sun is near the horizon and setting.
+    Dusk,
+    Night,
+}
+
+/// Where the sun is in the sky.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SunState {
+    /// Degrees above the horizon, negative below it.
+    pub elevation: f32,
+    /// Degrees clockwise from north.
+    pub azimuth: f32,
+    pub phase: SunPhase,
+}
+
+impl SunState {
+    /// A unit vector pointing at the sun, with north along -Z, east along +X and up along +Y.
+    #[cfg(feature = "pbr")]
+    pub fn direction(&self) -> Vec3 {
+        let (elevation, azimuth) = (self.elevation.to_radians(), self.azimuth.to_radians());
+        Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        )
+    }
+}
+
+/// Sent when the sun moves into another [`SunPhase`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SunPhaseChanged {
+    pub previous: SunPhase,
+    pub current: SunPhase,
+}
+
+/// Keeps [`SunState`] at the sun's real position, computed offline from [`SunConfig`] and the
+/// system clock, so lighting can follow the day.
+///
+/// [`SunLight`] points a [`DirectionalLight`] along the sunlight, and [`SunConfig::ambient`] fades
+/// the [`AmbientLight`] between day and night.
+#[derive(Default)]
+pub struct SunPlugin {
+    pub config: SunConfig,
+}
+
+impl Plugin for SunPlugin {
+    fn build(&self, app: &mut App) {
+        // Starting out where the sun is, rather than at the default, keeps the first update from
+        // announcing a phase change that didn't happen.
+        app.insert_resource(self.config.clone())
+            .insert_resource(self.config.sun())
+            .add_event::<SunPhaseChanged>()
+            .add_systems(PreUpdate, update_sun);
+
+        #[cfg(feature = "pbr")]
+        app.add_systems(PreUpdate, (light_sun, light_ambient).after(update_sun));
+    }
+}
+
+/// Keeps a [`DirectionalLight`] shining from the sun, warming its color near the horizon and
+/// turning it off below it.
+#[cfg(feature = "pbr")]
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SunLight {
+    /// The illuminance with the sun straight overhead.
+    pub illuminance: f32,
+}
+
+#[cfg(feature = "pbr")]
+impl Default for SunLight {
+    fn default() -> Self {
+        Self {
+            illuminance: light_consts::lux::AMBIENT_DAYLIGHT,
+        }
+    }
+}
+
+fn update_sun(
+    config: Res<SunConfig>,
+    mut sun: ResMut<SunState>,
+    mut changed: EventWriter<SunPhaseChanged>,
+    mut updated: Local<Option<Instant>>,
+) {
+    let now = Instant::now();
+    let due = config.is_changed()
+        || updated.is_none_or(|updated| now.duration_since(updated) >= config.interval);
+    if !due {
+        return;
+    }
+    *updated = Some(now);
+
+    let state = config.sun();
+
+    if sun.phase != state.phase {
+        changed.send(SunPhaseChanged {
+            previous: sun.phase,
+            current: state.phase,
+        });
+    }
+
+    if *sun != state {
+        *sun = state;
+    }
+}
+
+#[cfg(feature = "pbr")]
+fn light_sun(
+    sun: Res<SunState>,
+    mut lights: Query<(Ref<SunLight>, &mut DirectionalLight, &mut Transform)>,
+) {
+    let warm = Color::srgb(1.0, 0.55, 0.25);
+    // The light is fully white from this elevation up.
+    let white_above = 20.0;
+
+    for (sun_light, mut light, mut transform) in &mut lights {
+        if !sun.is_changed() && !sun_light.is_changed() {
+            continue;
+        }
+
+        let height = sun.elevation.to_radians().sin().max(0.0);
+        light.illuminance = sun_light.illuminance * height;
+        light.color = warm.mix(&Color::WHITE, (sun.elevation / white_above).clamp(0.0, 1.0));
+        transform.rotation = Transform::IDENTITY
+            .looking_to(-sun.direction(), Vec3::Y)
+            .rotation;
+    }
+}
+
+#[cfg(feature = "pbr")]
+fn light_ambient(
+    config: Res<SunConfig>,
+    sun: Res<SunState>,
+    ambient: Option<ResMut<AmbientLight>>,
+) {
+    let (Some(brightness), Some(mut ambient)) = (config.ambient, ambient) else {
+        return;
+    };
+    if !sun.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    let night = Color::srgb(0.35, 0.4, 0.7);
+    // From the end of civil twilight to full day.
+    let day = ((f64::from(sun.elevation) - NIGHT_BELOW) / (DAY_ABOVE - NIGHT_BELOW)).clamp(0.0, 1.0)
+        as f32;
+
+    ambient.color = night.mix(&Color::WHITE, day);
+    ambient.brightness = brightness * (0.05 + 0.95 * day);
+}
+
+/// The sun's position seen from `latitude` and `longitude` at `time`, to within about a tenth of
+/// a degree this century, ignoring refraction.
+fn sun_position(time: SystemTime, latitude: f64, longitude: f64) -> SunState {
+    let unix_secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(err) => -err.duration().as_secs_f64(),
+    };
+    // Days since J2000.0.
+    let days = unix_secs / 86400.0 + 2440587.5 - 2451545.0;
+
+    let mean_anomaly = (357.529 + 0.98560028 * days).to_radians();
+    let mean_longitude = 280.459 + 0.98564736 * days;
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin())
+            .to_radians();
+    let obliquity = (23.439 - 0.00000036 * days).to_radians();
+
+    let right_ascension =
+        (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+
+    let sidereal_degrees = (18.697374558 + 24.06570982441908 * days) * 15.0 + longitude;
+    // From -180 to 180 degrees, negative before solar noon.
+    let hour_angle = ((sidereal_degrees - right_ascension.to_degrees() + 180.0).rem_euclid(360.0)
+        - 180.0)
+        .to_radians();
+
+    let latitude = latitude.to_radians();
+    let elevation = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .asin()
+    .to_degrees();
+    let azimuth = (-hour_angle.sin())
+        .atan2(declination.tan() * latitude.cos() - latitude.sin() * hour_angle.cos())
+        .to_degrees()
+        .rem_euclid(360.0);
+
+    let phase = if elevation < NIGHT_BELOW {
+        SunPhase::Night
+    } else if elevation >= DAY_ABOVE {
+        SunPhase::Day
+    } else if hour_angle < 0.0 {
+        SunPhase::Dawn
+    } else {
+        SunPhase::Dusk
+    };
+
+    SunState {
+        elevation: elevation as f32,
+        azimuth: azimuth as f32,
+        phase,
+    }
+}
+
+/// The longitude the system time zone's UTC offset is centred on, 15 degrees per hour.
+fn time_zone_longitude() -> f64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs()) as libc::time_t;
+
+    // SAFETY: `localtime_r` only writes to the `tm` it is given.
+    let offset = unsafe {
+        let mut tm = core::mem::zeroed::<libc::tm>();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return 0.0;
+        }
+        tm.tm_gmtoff
+    };
+
+    offset as f64 / 3600.0 * 15.0
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::event::Events;
+
+    use super::*;
+
+    fn at(unix_secs: u64, latitude: f64, longitude: f64) -> SunState {
+        sun_position(
+            UNIX_EPOCH + Duration::from_secs(unix_secs),
+            latitude,
+            longitude,
+        )
+    }
+
+    fn assert_near(sun: SunState, elevation: f32, azimuth: f32, phase: SunPhase) {
+        assert!((sun.elevation - elevation).abs() < 0.05, "{sun:?}");
+        assert!((sun.azimuth - azimuth).abs() < 0.05, "{sun:?}");
+        assert_eq!(sun.phase, phase);
+    }
+
+    #[test]
+    fn noon_at_greenwich() {
+        // 2024-06-20 and 2024-12-21, 12:00 UTC.
+        let greenwich = (51.4779, -0.0015);
+        assert_near(
+            at(1718884800, greenwich.0, greenwich.1),
+            61.958,
+            179.164,
+            SunPhase::Day,
+        );
+        assert_near(
+            at(1734782400, greenwich.0, greenwich.1),
+            15.083,
+            180.397,
+            SunPhase::Day,
+        );
+    }
+
+    #[test]
+    fn a_day_in_new_york() {
+        let new_york = (40.7128, -74.006);
+        // 2024-06-20 09:25 UTC, the published sunrise.
+        assert_near(
+            at(1718875500, new_york.0, new_york.1),
+            -0.818,
+            57.515,
+            SunPhase::Dawn,
+        );
+        // 20:00 UTC.
+        assert_near(
+            at(1718913600, new_york.0, new_york.1),
+            48.285,
+            259.822,
+            SunPhase::Day,
+        );
+        // 2024-06-21 00:40 UTC, just after sunset.
+        assert_near(
+            at(1718930400, new_york.0, new_york.1),
+            -2.314,
+            304.053,
+            SunPhase::Dusk,
+        );
+        // 04:00 UTC.
+        assert_near(
+            at(1718942400, new_york.0, new_york.1),
+            -24.453,
+            345.414,
+            SunPhase::Night,
+        );
+    }
+
+    #[test]
+    fn southern_hemisphere() {
+        // Sydney, 2024-01-16 09:00 local time.
+        assert_near(
+            at(1705356000, -33.8688, 151.2093),
+            35.064,
+            93.342,
+            SunPhase::Day,
+        );
+    }
+
+    #[test]
+    fn starts_without_a_phase_change() {
+        let mut app = App::new();
+        app.add_plugins(SunPlugin {
+            // 04:00 UTC in New York, at night.
+            config: SunConfig {
+                time: Some(UNIX_EPOCH + Duration::from_secs(1718942400)),
+                ..SunConfig::new(40.7128, -74.006)
+            },
+        });
+
+        assert_eq!(app.world().resource::<SunState>().phase, SunPhase::Night);
+        app.update();
+        assert!(app.world().resource::<Events<SunPhaseChanged>>().is_empty());
+
+        app.world_mut().resource_mut::<SunConfig>().time =
+            Some(UNIX_EPOCH + Duration::from_secs(1718913600));
+        app.update();
+        let changes: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<SunPhaseChanged>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            changes,
+            [SunPhaseChanged {
+                previous: SunPhase::Night,
+                current: SunPhase::Day,
+            }]
+        );
+    }
+}
@@ -1,4 +1,3 @@
-use crate::get_screen_roots;
 use bevy_ecs::{
     entity::Entity,
     event::EventWriter,
@@ -8,21 +7,27 @@ use bevy_ecs::{
     system::{Local, NonSendMut, Query, SystemParamItem},
 };
 use bevy_window::{
-    ClosingWindow, Monitor, PrimaryMonitor, RawHandleWrapper, VideoMode, Window, WindowClosed,
-    WindowClosing, WindowCreated, WindowMode, WindowResized, WindowWrapper,
+    ClosingWindow, PrimaryMonitor, RawHandleWrapper, Window, WindowClosed, WindowClosing,
+    WindowCreated, WindowMode, WindowPosition, WindowResized, WindowWrapper,
 };
 use tracing::{debug, error, info, warn};
 use winit::{
     dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
     event_loop::ActiveEventLoop,
+    monitor::MonitorHandle,
 };
 
 use super::{
-    CreateMonitorParams, CreateWindowParams, WinitWindows,
+    CreateMonitorParams, CreateWindowParams, PreviewMonitor, WallpaperTarget, WinitWindows,
     converters::{
         convert_enabled_buttons, convert_resize_direction, convert_window_level,
         convert_window_theme,
     },
+    error::{WallpaperFailed, WindowCreationFailed},
+    monitors::{
+        MonitorAdded, MonitorChanged, MonitorRemoved, RandrMonitor, preview_monitors,
+        query_monitors, randr_changed, same_monitor,
+    },
     select_monitor,
     state::react_to_resize,
     winit_monitors::WinitMonitors,
@@ -30,47 +35,93 @@ use super::{
 };
 use bevy_app::AppExit;
 use bevy_ecs::{prelude::EventReader, query::With, system::Res};
-use bevy_math::{IVec2, UVec2};
 
 /// Creates new windows on the [`winit`] backend for each entity with a newly-added
 /// [`Window`] component.
 ///
 /// If any of these entities are missing required components, those will be added with their
 /// default values.
+///
+/// Windows that can't be created are marked with [`WindowCreationFailed`] and reported with a
+/// [`WallpaperFailed`] event. They are only tried again when the
+/// [`FallbackPolicy`](crate::FallbackPolicy) schedules a retry.
 pub fn create_windows<F: QueryFilter + 'static>(
     event_loop: &ActiveEventLoop,
     (
         mut commands,
         mut created_windows,
         mut window_created_events,
+        mut wallpaper_failed_events,
         mut winit_windows,
         // mut adapters,
         // mut handlers,
         // accessibility_requested,
         monitors,
+        fallback_policy,
+        desktop,
+        target,
+        layout,
     ): SystemParamItem<CreateWindowParams<F>>,
 ) {
-    for (entity, window, handle_holder) in &mut created_windows {
-        let parent_window_id = get_screen_roots();
+    // Follow layout changes once the preview window is open.
+    if *target == WallpaperTarget::Preview
+        && winit_windows.preview.is_some()
+        && let Err(err) = winit_windows.fit_preview_window(event_loop, &layout)
+    {
+        warn!("Could not resize the preview window: {err}");
+    }
+
+    for (entity, window, handle_holder, failed) in &mut created_windows {
+        if failed.is_some_and(|failed| !failed.should_retry()) {
+            continue;
+        }
 
         if winit_windows.get_window(entity).is_some() {
             continue;
-        } else {
-            warn!("attempting to parent to windows {:?}", parent_window_id);
         }
 
+        let parent_window_id = desktop.root();
+        warn!("attempting to parent to windows {:?}", parent_window_id);
+
         info!("Creating new window {} ({})", window.title.as_str(), entity);
 
-        let winit_window = winit_windows.create_window(
-            event_loop,
-            entity,
-            &window,
-            // &mut adapters,
-            // &mut handlers,
-            // &accessibility_requested,
-            &monitors,
-            parent_window_id,
-        );
+        let winit_window = match *target {
+            WallpaperTarget::Embedded => winit_windows.create_window(
+                event_loop,
+                entity,
+                &window,
+                // &mut adapters,
+                // &mut handlers,
+                // &accessibility_requested,
+                &monitors,
+                &desktop,
+                parent_window_id,
+            ),
+            WallpaperTarget::Root => {
+                winit_windows.adopt_root_window(event_loop, entity, &window, parent_window_id)
+            }
+            WallpaperTarget::Preview => {
+                winit_windows.create_preview_window(event_loop, entity, &window, &monitors, &layout)
+            }
+        };
+
+        let winit_window = match winit_window {
+            Ok(winit_window) => winit_window,
+            Err(error) => {
+                commands
+                    .entity(entity)
+                    .insert(WindowCreationFailed::record(failed, *fallback_policy));
+                wallpaper_failed_events.send(WallpaperFailed {
+                    window: entity,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        if failed.is_some() {
+            commands.entity(entity).remove::<WindowCreationFailed>();
+        }
 
         // if let Some(theme) = winit_window.theme() {
         //     window.window_theme = Some(convert_winit_theme(theme));
@@ -162,67 +213,182 @@ pub fn create_windows<F: QueryFilter + 'static>(
 //     }
 // }
 
-/// Synchronize available monitors as reported by [`winit`] with [`Monitor`] entities in the world.
+/// Synchronizes [`Monitor`](bevy_window::Monitor) entities with the monitors RandR reports.
+///
+/// The monitor list is only read again after the [`X11Desktop`](crate::X11Desktop) connection
+/// receives a RandR change notification. Existing monitor components are updated in place,
+/// and every change is announced with a [`MonitorAdded`], [`MonitorChanged`] or
+/// [`MonitorRemoved`] event.
+///
+/// With [`WallpaperTarget::Preview`], the monitors of the
+/// [`PreviewLayout`](crate::PreviewLayout) are simulated instead.
 pub fn create_monitors(
     event_loop: &ActiveEventLoop,
-    (mut commands, mut monitors): SystemParamItem<CreateMonitorParams>,
+    (
+        mut commands,
+        mut monitors,
+        desktop,
+        target,
+        layout,
+        mut monitor_components,
+        mut added_events,
+        mut changed_events,
+        mut removed_events,
+    ): SystemParamItem<CreateMonitorParams>,
 ) {
-    let primary_monitor = event_loop.primary_monitor();
-    let mut seen_monitors = vec![false; monitors.monitors.len()];
-
-    'outer: for monitor in event_loop.available_monitors() {
-        for (idx, (m, _)) in monitors.monitors.iter().enumerate() {
-            if &monitor == m {
-                seen_monitors[idx] = true;
-                continue 'outer;
+    let preview = *target == WallpaperTarget::Preview;
+
+    // RandR events are taken either way so they don't pile up while previewing.
+    if randr_changed(&desktop) && !preview {
+        monitors.up_to_date = false;
+    }
+
+    if preview
+        && !monitors
+            .previews
+            .iter()
+            .map(|(monitor, _)| monitor)
+            .eq(&layout.monitors)
+    {
+        monitors.up_to_date = false;
+    }
+
+    if monitors.up_to_date {
+        return;
+    }
+
+    let (found_monitors, sources) = if preview {
+        let scale_factor = event_loop
+            .primary_monitor()
+            .map_or(1.0, |monitor| monitor.scale_factor());
+        let sources = layout
+            .monitors
+            .iter()
+            .cloned()
+            .map(MonitorSource::Preview)
+            .collect();
+
+        (preview_monitors(&layout, scale_factor), sources)
+    } else {
+        let randr_monitors = match query_monitors(&desktop) {
+            Ok(randr_monitors) => randr_monitors,
+            Err(err) => {
+                warn!("Could not query monitors: {err}");
+                return;
             }
+        };
+
+        let winit_handles: Vec<MonitorHandle> = event_loop.available_monitors().collect();
+        let mut sources = Vec::with_capacity(randr_monitors.len());
+
+        for randr_monitor in &randr_monitors {
+            // winit refreshes its monitor list lazily on its own connection, so it may not have
+            // caught up with the change yet. Try again on the next iteration in that case.
+            let Some(handle) = winit_handles
+                .iter()
+                .find(|handle| handle.name() == randr_monitor.monitor.name)
+            else {
+                debug!(
+                    "Waiting for winit to see monitor {:?}",
+                    randr_monitor.monitor.name
+                );
+                return;
+            };
+
+            sources.push(MonitorSource::Winit(handle.clone()));
         }
 
-        let size = monitor.size();
-        let position = monitor.position();
-
-        let entity = commands
-            .spawn(Monitor {
-                name: monitor.name(),
-                physical_height: size.height,
-                physical_width: size.width,
-                physical_position: IVec2::new(position.x, position.y),
-                refresh_rate_millihertz: monitor.refresh_rate_millihertz(),
-                scale_factor: monitor.scale_factor(),
-                video_modes: monitor
-                    .video_modes()
-                    .map(|v| {
-                        let size = v.size();
-                        VideoMode {
-                            physical_size: UVec2::new(size.width, size.height),
-                            bit_depth: v.bit_depth(),
-                            refresh_rate_millihertz: v.refresh_rate_millihertz(),
-                        }
-                    })
-                    .collect(),
+        (randr_monitors, sources)
+    };
+
+    let mut previous: Vec<(Option<String>, Entity)> = core::mem::take(&mut monitors.monitors)
+        .into_iter()
+        .map(|(handle, entity)| (handle.name(), entity))
+        .chain(
+            core::mem::take(&mut monitors.previews)
+                .into_iter()
+                .map(|(preview, entity)| (Some(preview.name), entity)),
+        )
+        .collect();
+
+    for (
+        RandrMonitor {
+            mut monitor,
+            identity,
+            primary,
+        },
+        source,
+    ) in found_monitors.into_iter().zip(sources)
+    {
+        if let MonitorSource::Winit(handle) = &source {
+            monitor.scale_factor = handle.scale_factor();
+        }
+
+        // A monitor moved to another port (e.g. when docking) keeps its entity if its EDID
+        // identifies it.
+        let existing = previous
+            .iter()
+            .position(|(_, entity)| {
+                monitor_components
+                    .get(*entity)
+                    .is_ok_and(|(_, _, old)| old.is_some_and(|old| old.same_device(&identity)))
             })
-            .id();
+            .or_else(|| previous.iter().position(|(name, _)| *name == monitor.name))
+            .map(|idx| previous.swap_remove(idx).1);
+
+        let entity = if let Some(entity) = existing {
+            let Ok((mut component, was_primary, old_identity)) = monitor_components.get_mut(entity)
+            else {
+                continue;
+            };
+
+            if !same_monitor(&component, &monitor)
+                || was_primary != primary
+                || old_identity != Some(&identity)
+            {
+                info!("Monitor changed {} ({identity})", entity);
+                *component = monitor;
+                commands.entity(entity).insert(identity);
+                changed_events.send(MonitorChanged { monitor: entity });
+            }
+
+            entity
+        } else {
+            info!("Monitor added {identity}");
+            let entity = commands.spawn((monitor, identity)).id();
+            added_events.send(MonitorAdded { monitor: entity });
+            entity
+        };
 
-        if primary_monitor.as_ref() == Some(&monitor) {
+        if primary {
             commands.entity(entity).insert(PrimaryMonitor);
+        } else {
+            commands.entity(entity).remove::<PrimaryMonitor>();
         }
 
-        seen_monitors.push(true);
-        monitors.monitors.push((monitor, entity));
+        match source {
+            MonitorSource::Winit(handle) => monitors.monitors.push((handle, entity)),
+            MonitorSource::Preview(preview) => monitors.previews.push((preview, entity)),
+        }
     }
 
-    let mut idx = 0;
-    monitors.monitors.retain(|(_m, entity)| {
-        if seen_monitors[idx] {
-            idx += 1;
-            true
-        } else {
-            info!("Monitor removed {}", entity);
-            commands.entity(*entity).despawn();
-            idx += 1;
-            false
-        }
-    });
+    for (name, entity) in previous {
+        info!("Monitor removed {}", entity);
+        commands.entity(entity).despawn();
+        removed_events.send(MonitorRemoved {
+            monitor: entity,
+            name,
+        });
+    }
+
+    monitors.up_to_date = true;
+}
+
+/// What a monitor found by [`create_monitors`] is.
+enum MonitorSource {
+    Winit(MonitorHandle),
+    /// Simulated for [`WallpaperTarget::Preview`].
+    Preview(PreviewMonitor),
 }
 
 pub(crate) fn despawn_windows(
@@ -285,6 +451,7 @@ pub(crate) fn changed_windows(
     mut changed_windows: Query<(Entity, &mut Window, &mut CachedWindow), Changed<Window>>,
     winit_windows: NonSendMut<WinitWindows>,
     monitors: Res<WinitMonitors>,
+    target: Res<WallpaperTarget>,
     mut window_resized: EventWriter<WindowResized>,
 ) {
     for (entity, mut window, mut cache) in &mut changed_windows {
@@ -296,7 +463,15 @@ pub(crate) fn changed_windows(
             winit_window.set_title(window.title.as_str());
         }
 
-        if window.mode != cache.window.mode {
+        if window.mode != cache.window.mode && *target == WallpaperTarget::Preview {
+            // Moved over another simulated monitor below, rather than made fullscreen.
+            if let Some(monitor) = monitors.preview_monitor(&window.mode) {
+                window
+                    .resolution
+                    .set_physical_resolution(monitor.size.x, monitor.size.y);
+                window.position = WindowPosition::At(monitor.position);
+            }
+        } else if window.mode != cache.window.mode {
             let new_mode = match window.mode {
                 WindowMode::BorderlessFullscreen(monitor_selection) => {
                     Some(Some(winit::window::Fullscreen::Borderless(select_monitor(
@@ -0,0 +1,718 @@
+use core::time::Duration;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+};
+
+use bevy::utils::Instant;
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    schedule::IntoSystemConfigs,
+    system::{Res, ResMut, Resource},
+};
+use tracing::warn;
+
+/// Disk statistics count in sectors of this many bytes, whatever the device uses.
+const SECTOR_SIZE: f32 = 512.0;
+
+/// How the [`TelemetryPlugin`] samples the system, which can be changed while it runs.
+#[derive(Resource, Debug, Clone)]
+pub struct TelemetryConfig {
+    pub interval: Duration,
+    /// How much of the previous sample is kept, from 0 (none) to 1 (frozen).
+    pub smoothing: f32,
+    /// Where procfs is mounted, e.g. a fixture tree in tests.
+    pub proc_root: PathBuf,
+    /// Where sysfs is mounted.
+    pub sys_root: PathBuf,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            smoothing: 0.5,
+            proc_root: PathBuf::from("/proc"),
+            sys_root: PathBuf::from("/sys"),
+        }
+    }
+}
+
+/// The load of the machine, kept up to date by the [`TelemetryPlugin`].
+///
+/// Loads and fractions go from 0 to 1. Anything the system doesn't report stays at zero, or
+/// `None`.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct SystemStats {
+    /// The share of time all CPUs were busy.
+    pub cpu: f32,
+    /// The same for each CPU.
+    pub cpu_cores: Vec<f32>,
+    pub memory: MemoryStats,
+    pub network: NetworkStats,
+    pub disk: DiskStats,
+    /// `None` on machines without a battery.
+    pub battery: Option<BatteryStats>,
+    /// Every hwmon temperature sensor.
+    pub temperatures: Vec<Temperature>,
+}
+
+impl SystemStats {
+    /// The hottest sensor, in degrees Celsius.
+    pub fn max_temperature(&self) -> Option<f32> {
+        self.temperatures
+            .iter()
+            .map(|temperature| temperature.celsius)
+            .max_by(f32::total_cmp)
+    }
+
+    /// Moves the smoothed values towards `sample`, keeping `keep` of their old value.
+    fn smooth(&mut self, sample: Self, keep: f32) {
+        let smooth = |old: &mut f32, new: f32| *old = *old * keep + new * (1.0 - keep);
+
+        smooth(&mut self.cpu, sample.cpu);
+        self.cpu_cores.resize(sample.cpu_cores.len(), 0.0);
+        for (old, new) in self.cpu_cores.iter_mut().zip(sample.cpu_cores) {
+            smooth(old, new);
+        }
+
+        self.memory.total = sample.memory.total;
+        self.memory.available = sample.memory.available;
+        smooth(&mut self.memory.used, sample.memory.used);
+        smooth(&mut self.memory.swap_used, sample.memory.swap_used);
+
+        smooth(&mut self.network.received, sample.network.received);
+        smooth(&mut self.network.sent, sample.network.sent);
+
+        smooth(&mut self.disk.read, sample.disk.read);
+        smooth(&mut self.disk.written, sample.disk.written);
+        smooth(&mut self.disk.busy, sample.disk.busy);
+
+        // Battery levels change slowly enough already.
+        self.battery = sample.battery;
+
+        let mut temperatures = sample.temperatures;
+        for temperature in &mut temperatures {
+            let previous = self.temperatures.iter().find(|previous| {
+                previous.sensor == temperature.sensor && previous.label == temperature.label
+            });
+            if let Some(previous) = previous {
+                let mut celsius = previous.celsius;
+                smooth(&mut celsius, temperature.celsius);
+                temperature.celsius = celsius;
+            }
+        }
+        self.temperatures = temperatures;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryStats {
+    /// In bytes.
+    pub total: u64,
+    /// In bytes, including caches the kernel can reclaim.
+    pub available: u64,
+    /// The share of memory in use.
+    pub used: f32,
+    /// The share of swap in use, or zero without swap.
+    pub swap_used: f32,
+}
+
+/// Traffic over all interfaces but loopback.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkStats {
+    /// In bytes per second.
+    pub received: f32,
+    /// In bytes per second.
+    pub sent: f32,
+}
+
+/// Traffic of all physical disks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiskStats {
+    /// In bytes per second.
+    pub read: f32,
+    /// In bytes per second.
+    pub written: f32,
+    /// The share of time the busiest disk was doing I/O.
+    pub busy: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryStats {
+    /// The charge of all batteries together.
+    pub charge: f32,
+    pub charging: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Temperature {
+    /// The hwmon driver, e.g. `coretemp` or `amdgpu`.
+    pub sensor: String,
+    /// What the sensor measures, e.g. `Package id 0`, if the driver says.
+    pub label: Option<String>,
+    pub celsius: f32,
+}
+
+/// Samples CPU, memory, network, disk, battery and temperature statistics from procfs and sysfs
+/// into [`SystemStats`], for wallpapers that follow the load of the machine.
+///
+/// Sampling happens on a background thread every [`TelemetryConfig::interval`], so slow reads
+/// never hold up a frame.
+#[derive(Default)]
+pub struct TelemetryPlugin {
+    pub config: TelemetryConfig,
+}
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .init_resource::<SystemStats>()
+            .init_resource::<TelemetrySamples>()
+            .add_systems(PreUpdate, (start_sampling, update_system_stats).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+struct TelemetrySamples {
+    /// Samples taken by the sampler thread. Only locked to make the resource `Sync`.
+    samples: Option<Mutex<Receiver<SystemStats>>>,
+}
+
+/// (Re)starts sampling whenever the [`TelemetryConfig`] changes.
+fn start_sampling(config: Res<TelemetryConfig>, mut samples: ResMut<TelemetrySamples>) {
+    if !config.is_changed() {
+        return;
+    }
+
+    // The previous sampler stops once it notices the receiver is gone.
+    samples.samples = None;
+
+    let (sender, receiver) = mpsc::channel();
+    let config = config.clone();
+
+    let sampler = thread::Builder::new()
+        .name("telemetry sampler".into())
+        .spawn(move || sample(&config, &sender));
+
+    match sampler {
+        Ok(_) => samples.samples = Some(Mutex::new(receiver)),
+        Err(err) => warn!("Could not start sampling system statistics: {err}"),
+    }
+}
+
+fn update_system_stats(
+    config: Res<TelemetryConfig>,
+    mut samples: ResMut<TelemetrySamples>,
+    mut stats: ResMut<SystemStats>,
+) {
+    let Some(receiver) = samples.samples.as_mut() else {
+        return;
+    };
+    let receiver = receiver.get_mut().unwrap();
+
+    loop {
+        match receiver.try_recv() {
+            Ok(sample) => {
+                let mut smoothed = stats.clone();
+                smoothed.smooth(sample, config.smoothing.clamp(0.0, 1.0));
+                stats.set_if_neq(smoothed);
+            }
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => {
+                samples.samples = None;
+                break;
+            }
+        }
+    }
+}
+
+/// Counters that only mean something compared to their previous value.
+#[derive(Default)]
+struct Counters {
+    at: Option<Instant>,
+    /// Busy and total time of all CPUs, then of each CPU.
+    cpus: Vec<(u64, u64)>,
+    /// Bytes received and sent.
+    network: (u64, u64),
+    /// Sectors read and written, and milliseconds spent doing I/O, by disk.
+    disks: HashMap<String, (u64, u64, u64)>,
+}
+
+/// Sends a sample every interval until the receiver is gone.
+fn sample(config: &TelemetryConfig, samples: &Sender<SystemStats>) {
+    let mut previous = Counters::default();
+
+    loop {
+        let mut counters = Counters {
+            at: Some(Instant::now()),
+            ..Default::default()
+        };
+        let elapsed = previous
+            .at
+            .zip(counters.at)
+            .map(|(previous, now)| now.duration_since(previous).as_secs_f32())
+            .filter(|elapsed| *elapsed > 0.0);
+
+        let mut stats = SystemStats {
+            memory: read_memory(&config.proc_root),
+            battery: read_battery(&config.sys_root),
+            temperatures: read_temperatures(&config.sys_root),
+            ..Default::default()
+        };
+
+        counters.cpus = read_cpus(&config.proc_root);
+        let loads: Vec<f32> = counters
+            .cpus
+            .iter()
+            .zip(&previous.cpus)
+            .map(|(&(busy, total), &(previous_busy, previous_total))| {
+                let total = total.saturating_sub(previous_total);
+                let busy = busy.saturating_sub(previous_busy);
+                if total == 0 {
+                    0.0
+                } else {
+                    busy as f32 / total as f32
+                }
+            })
+            .collect();
+        if let Some((cpu, cores)) = loads.split_first() {
+            stats.cpu = *cpu;
+            stats.cpu_cores = cores.to_vec();
+        }
+
+        counters.network = read_network(&config.proc_root);
+        counters.disks = read_disks(&config.proc_root, &config.sys_root);
+
+        // Rates need a previous sample.
+        if let Some(elapsed) = elapsed {
+            let rate = |now: u64, before: u64| now.saturating_sub(before) as f32 / elapsed;
+
+            stats.network = NetworkStats {
+                received: rate(counters.network.0, previous.network.0),
+                sent: rate(counters.network.1, previous.network.1),
+            };
+
+            for (disk, &(read, written, busy)) in &counters.disks {
+                let Some(&(previous_read, previous_written, previous_busy)) =
+                    previous.disks.get(disk)
+                else {
+                    continue;
+                };
+
+                stats.disk.read += rate(read, previous_read) * SECTOR_SIZE;
+                stats.disk.written += rate(written, previous_written) * SECTOR_SIZE;
+                stats.disk.busy = stats
+                    .disk
+                    .busy
+                    .max((rate(busy, previous_busy) / 1000.0).min(1.0));
+            }
+        }
+
+        previous = counters;
+
+        if samples.send(stats).is_err() {
+            return;
+        }
+
+        thread::sleep(config.interval);
+    }
+}
+
+/// The busy and total jiffies of all CPUs, followed by each CPU, from `/proc/stat`.
+fn read_cpus(proc_root: &Path) -> Vec<(u64, u64)> {
+    let Ok(stat) = fs::read_to_string(proc_root.join("stat")) else {
+        return Vec::new();
+    };
+
+    stat.lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(|line| {
+            let times: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .filter_map(|time| time.parse().ok())
+                .collect();
+
+            // user nice system idle iowait irq softirq steal, and guest time counted in user.
+            let total: u64 = times.iter().take(8).sum();
+            let idle = times.get(3).unwrap_or(&0) + times.get(4).unwrap_or(&0);
+            (total.saturating_sub(idle), total)
+        })
+        .collect()
+}
+
+fn read_memory(proc_root: &Path) -> MemoryStats {
+    let Ok(meminfo) = fs::read_to_string(proc_root.join("meminfo")) else {
+        return MemoryStats::default();
+    };
+
+    let field = |name: &str| -> u64 {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map_or(0, |kib| kib * 1024)
+    };
+
+    let (total, available) = (field("MemTotal"), field("MemAvailable"));
+    let (swap_total, swap_free) = (field("SwapTotal"), field("SwapFree"));
+    let share = |used: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            used as f32 / total as f32
+        }
+    };
+
+    MemoryStats {
+        total,
+        available,
+        used: share(total.saturating_sub(available), total),
+        swap_used: share(swap_total.saturating_sub(swap_free), swap_total),
+    }
+}
+
+/// The bytes received and sent over all interfaces but loopback, from `/proc/net/dev`.
+fn read_network(proc_root: &Path) -> (u64, u64) {
+    let Ok(dev) = fs::read_to_string(proc_root.join("net/dev")) else {
+        return (0, 0);
+    };
+
+    dev.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(interface, _)| interface.trim() != "lo")
+        .map(|(_, counters)| {
+            let counters: Vec<u64> = counters
+                .split_whitespace()
+                .filter_map(|counter| counter.parse().ok())
+                .collect();
+            (
+                counters.first().copied().unwrap_or(0),
+                counters.get(8).copied().unwrap_or(0),
+            )
+        })
+        .fold((0, 0), |(received, sent), (rx, tx)| {
+            (received + rx, sent + tx)
+        })
+}
+
+/// The sectors read and written and the time spent on I/O of every whole, physical disk, from
+/// `/proc/diskstats`.
+fn read_disks(proc_root: &Path, sys_root: &Path) -> HashMap<String, (u64, u64, u64)> {
+    let Ok(diskstats) = fs::read_to_string(proc_root.join("diskstats")) else {
+        return HashMap::new();
+    };
+
+    diskstats
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = *fields.get(2)?;
+
+            // Partitions aren't in /sys/block, and would count their disk's traffic twice.
+            let virtual_disk = ["loop", "ram", "zram", "dm-"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix));
+            if virtual_disk || !sys_root.join("block").join(name).exists() {
+                return None;
+            }
+
+            let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+            Some((name.to_owned(), (field(5)?, field(9)?, field(12)?)))
+        })
+        .collect()
+}
+
+fn read_battery(sys_root: &Path) -> Option<BatteryStats> {
+    let supplies = fs::read_dir(sys_root.join("class/power_supply")).ok()?;
+    let (mut now, mut full, mut charging, mut found) = (0.0, 0.0, false, false);
+
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        let read = |name: &str| fs::read_to_string(path.join(name)).ok();
+        let number = |name: &str| read(name)?.trim().parse::<f32>().ok();
+
+        if read("type").is_none_or(|kind| kind.trim() != "Battery") {
+            continue;
+        }
+
+        // Batteries report their energy or their charge, or else only a percentage.
+        let (battery_now, battery_full) = match (number("energy_now"), number("energy_full")) {
+            (Some(now), Some(full)) => (now, full),
+            _ => match (number("charge_now"), number("charge_full")) {
+                (Some(now), Some(full)) => (now, full),
+                _ => (number("capacity").unwrap_or(0.0), 100.0),
+            },
+        };
+
+        now += battery_now;
+        full += battery_full;
+        charging |= read("status").is_some_and(|status| status.trim() == "Charging");
+        found = true;
+    }
+
+    found.then(|| BatteryStats {
+        charge: if full > 0.0 {
+            (now / full).min(1.0)
+        } else {
+            0.0
+        },
+        charging,
+    })
+}
+
+fn read_temperatures(sys_root: &Path) -> Vec<Temperature> {
+    let Ok(hwmons) = fs::read_dir(sys_root.join("class/hwmon")) else {
+        return Vec::new();
+    };
+
+    let mut temperatures = Vec::new();
+
+    for hwmon in hwmons.flatten() {
+        let path = hwmon.path();
+        let sensor = fs::read_to_string(path.join("name"))
+            .map(|name| name.trim().to_owned())
+            .unwrap_or_default();
+
+        let Ok(files) = fs::read_dir(&path) else {
+            continue;
+        };
+
+        for file in files.flatten() {
+            let name = file.file_name();
+            let Some(input) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("temp")?.strip_suffix("_input"))
+            else {
+                continue;
+            };
+
+            let Some(millidegrees) = fs::read_to_string(file.path())
+                .ok()
+                .and_then(|value| value.trim().parse::<f32>().ok())
+            else {
+                continue;
+            };
+
+            temperatures.push(Temperature {
+                sensor: sensor.clone(),
+                label: fs::read_to_string(path.join(format!("temp{input}_label")))
+                    .ok()
+                    .map(|label| label.trim().to_owned()),
+                celsius: millidegrees / 1000.0,
+            });
+        }
+    }
+
+    // Directory order isn't stable, but smoothing and readers want it to be.
+    temperatures.sort_by(|a, b| (&a.sensor, &a.label).cmp(&(&b.sensor, &b.label)));
+    temperatures
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// A fresh fixture root holding `files`.
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("telemetry-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        root
+    }
+
+    fn config(root: &Path, interval: Duration) -> TelemetryConfig {
+        TelemetryConfig {
+            interval,
+            smoothing: 0.0,
+            proc_root: root.join("proc"),
+            sys_root: root.join("sys"),
+        }
+    }
+
+    #[test]
+    fn sample_fixture() {
+        let root = fixture(
+            "sample",
+            &[
+                (
+                    "proc/stat",
+                    "cpu  100 0 100 700 100 0 0 0 0 0\n\
+                     cpu0 50 0 50 350 50 0 0 0 0 0\n\
+                     cpu1 50 0 50 350 50 0 0 0 0 0\n\
+                     intr 12345 0 0\n",
+                ),
+                (
+                    "proc/meminfo",
+                    "MemTotal:        8000000 kB\n\
+                     MemFree:          500000 kB\n\
+                     MemAvailable:    2000000 kB\n\
+                     SwapTotal:          1000 kB\n\
+                     SwapFree:            250 kB\n",
+                ),
+                ("sys/class/power_supply/AC/type", "Mains\n"),
+                ("sys/class/power_supply/AC/online", "1\n"),
+                ("sys/class/power_supply/BAT0/type", "Battery\n"),
+                ("sys/class/power_supply/BAT0/energy_now", "30000000\n"),
+                ("sys/class/power_supply/BAT0/energy_full", "60000000\n"),
+                ("sys/class/power_supply/BAT0/status", "Charging\n"),
+                ("sys/class/power_supply/BAT1/type", "Battery\n"),
+                ("sys/class/power_supply/BAT1/charge_now", "10000000\n"),
+                ("sys/class/power_supply/BAT1/charge_full", "40000000\n"),
+                ("sys/class/power_supply/BAT1/status", "Discharging\n"),
+                ("sys/class/hwmon/hwmon0/name", "coretemp\n"),
+                ("sys/class/hwmon/hwmon0/temp1_input", "45000\n"),
+                ("sys/class/hwmon/hwmon0/temp1_label", "Package id 0\n"),
+                ("sys/class/hwmon/hwmon0/temp2_input", "52500\n"),
+                ("sys/class/hwmon/hwmon0/temp2_max", "100000\n"),
+                ("sys/class/hwmon/hwmon1/name", "amdgpu\n"),
+                ("sys/class/hwmon/hwmon1/temp1_input", "61000\n"),
+                ("sys/class/hwmon/hwmon1/temp1_label", "edge\n"),
+            ],
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let config = config(&root, Duration::from_millis(500));
+        let sampler = thread::spawn(move || sample(&config, &sender));
+
+        let first = receiver.recv().unwrap();
+        // The sampler reads the CPU counters again after its interval.
+        fs::write(
+            root.join("proc/stat"),
+            "cpu  400 0 200 1000 200 0 0 0 0 0\n\
+             cpu0 250 0 100 450 100 0 0 0 0 0\n\
+             cpu1 50 0 50 750 50 0 0 0 0 0\n",
+        )
+        .unwrap();
+        let second = receiver.recv().unwrap();
+        drop(receiver);
+        sampler.join().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        // Loads need two samples.
+        assert_eq!(first.cpu, 0.0);
+        assert!(first.cpu_cores.is_empty());
+        assert_eq!(second.cpu, 0.5);
+        assert_eq!(second.cpu_cores, [0.625, 0.0]);
+
+        assert_eq!(
+            second.memory,
+            MemoryStats {
+                total: 8_192_000_000,
+                available: 2_048_000_000,
+                used: 0.75,
+                swap_used: 0.75,
+            }
+        );
+
+        assert_eq!(
+            second.battery,
+            Some(BatteryStats {
+                charge: 0.4,
+                charging: true,
+            })
+        );
+
+        let temperature = |sensor: &str, label: Option<&str>, celsius| Temperature {
+            sensor: sensor.into(),
+            label: label.map(Into::into),
+            celsius,
+        };
+        assert_eq!(
+            second.temperatures,
+            [
+                temperature("amdgpu", Some("edge"), 61.0),
+                temperature("coretemp", None, 52.5),
+                temperature("coretemp", Some("Package id 0"), 45.0),
+            ]
+        );
+        assert_eq!(second.max_temperature(), Some(61.0));
+    }
+
+    #[test]
+    fn battery_capacity() {
+        let root = fixture(
+            "capacity",
+            &[
+                ("sys/class/power_supply/BAT0/type", "Battery\n"),
+                ("sys/class/power_supply/BAT0/capacity", "80\n"),
+                ("sys/class/power_supply/BAT0/status", "Full\n"),
+            ],
+        );
+
+        let battery = read_battery(&root.join("sys"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            battery,
+            Some(BatteryStats {
+                charge: 0.8,
+                charging: false,
+            })
+        );
+    }
+
+    #[test]
+    fn network_and_disks() {
+        let root = fixture(
+            "counters",
+            &[
+                (
+                    "proc/net/dev",
+                    "Inter-|   Receive                            |  Transmit\n \
+                     face |bytes    packets errs drop fifo frame compressed multicast|bytes\n    \
+                     lo:    1000      10    0    0    0     0          0         0     1000\n  \
+                     eth0:    5000      50    0    0    0     0          0         0     3000\n \
+                     wlan0:     200       2    0    0    0     0          0         0      100\n",
+                ),
+                (
+                    "proc/diskstats",
+                    "   8       0 sda 100 0 2000 50 40 0 800 20 0 70 70\n   \
+                     8       1 sda1 90 0 1800 45 40 0 800 20 0 60 60\n   \
+                     7       0 loop0 10 0 80 1 0 0 0 0 0 1 1\n",
+                ),
+                ("sys/block/sda/size", "1000\n"),
+                ("sys/block/loop0/size", "80\n"),
+            ],
+        );
+
+        let network = read_network(&root.join("proc"));
+        let disks = read_disks(&root.join("proc"), &root.join("sys"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(network, (5200, 3100));
+        assert_eq!(disks, HashMap::from([("sda".into(), (2000, 800, 70))]));
+    }
+
+    #[test]
+    fn missing_trees() {
+        let root = fixture("missing", &[]);
+        let (sender, receiver) = mpsc::channel();
+        let config = config(&root, Duration::ZERO);
+        let sampler = thread::spawn(move || sample(&config, &sender));
+
+        let stats = receiver.recv().unwrap();
+        drop(receiver);
+        sampler.join().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(stats, SystemStats::default());
+    }
+}
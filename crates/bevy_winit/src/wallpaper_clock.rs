@@ -0,0 +1,275 @@
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::time::{Time, TimeSystem, Virtual};
+use bevy_app::{App, First, Plugin};
+use bevy_ecs::{
+    event::EventReader,
+    schedule::IntoSystemConfigs,
+    system::{Local, Res, ResMut, Resource},
+};
+
+use crate::wallpaper_command::WallpaperCommand;
+
+/// Animation time derived from the wall clock rather than from when the app started, so a
+/// restarted wallpaper, or one process per monitor, shows the same phase everywhere.
+///
+/// The animation time is `(now - origin) * speed + offset` seconds. Instances sharing those stay
+/// in phase. Freezing, scrubbing and changing the speed through the methods keep the animation
+/// continuous by adjusting `offset`, so instances that should stay together need the same
+/// controls.
+///
+/// Scenes read it instead of [`Time`], or set [`WallpaperClock::drive_time`] so scenes reading
+/// [`Time`] follow it unchanged. The animation time of the default `origin` is large, so
+/// [`WallpaperClock::phase`] is the precise way to drive repeating animations, and
+/// [`WallpaperClock::elapsed_secs`] is only precise near a recent `origin`.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct WallpaperClock {
+    /// The wall-clock time the animation time counts from.
+    pub origin: SystemTime,
+    /// How fast the animation time runs compared to the wall clock. Can be negative.
+    pub speed: f64,
+    /// Added to the animation time, in seconds. Time spent frozen is taken off it.
+    pub offset: f64,
+    /// Whether [`Time<Virtual>`] follows the animation time, so its elapsed time is the animation
+    /// time and its delta is how far that moved, or zero while it runs backwards.
+    ///
+    /// Pausing [`Time<Virtual>`] still holds it, and it jumps to the animation time once resumed.
+    /// Its speed is ignored, [`WallpaperClock::set_speed`] changes it instead. Scenes reading
+    /// [`Time::elapsed_secs`] need a recent `origin` to stay precise, while
+    /// [`Time::elapsed_secs_wrapped`] is precise anyway.
+    pub drive_time: bool,
+    /// The animation time held while frozen.
+    frozen: Option<f64>,
+    /// The animation time of this frame, `None` before the first one.
+    elapsed: Option<f64>,
+    delta: f64,
+}
+
+impl Default for WallpaperClock {
+    fn default() -> Self {
+        Self {
+            origin: UNIX_EPOCH,
+            speed: 1.0,
+            offset: 0.0,
+            drive_time: false,
+            frozen: None,
+            elapsed: None,
+            delta: 0.0,
+        }
+    }
+}
+
+impl WallpaperClock {
+    /// A clock counting from `origin`.
+    pub fn new(origin: SystemTime) -> Self {
+        Self {
+            origin,
+            ..Default::default()
+        }
+    }
+
+    /// The animation time of this frame in seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed.unwrap_or_else(|| self.at(SystemTime::now()))
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed_secs_f64() as f32
+    }
+
+    /// The animation time of this frame, or zero while it is before the origin.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.elapsed_secs_f64().max(0.0))
+    }
+
+    /// How far the animation time moved since the last frame, in seconds. Negative while running
+    /// or scrubbing backwards.
+    pub fn delta_secs(&self) -> f32 {
+        self.delta as f32
+    }
+
+    /// How far through a cycle of `period` the animation time is, from 0 to 1.
+    pub fn phase(&self, period: Duration) -> f32 {
+        let period = period.as_secs_f64();
+        if period == 0.0 {
+            return 0.0;
+        }
+
+        (self.elapsed_secs_f64().rem_euclid(period) / period) as f32
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// Holds the animation time where it is.
+    pub fn freeze(&mut self) {
+        if self.frozen.is_none() {
+            self.frozen = Some(self.at(SystemTime::now()));
+        }
+    }
+
+    /// Carries on from where the animation time was frozen.
+    pub fn unfreeze(&mut self) {
+        if let Some(frozen) = self.frozen.take() {
+            self.set_elapsed_secs(frozen);
+        }
+    }
+
+    /// Jumps to an animation time, in seconds.
+    pub fn set_elapsed_secs(&mut self, secs: f64) {
+        match &mut self.frozen {
+            Some(frozen) => *frozen = secs,
+            None => self.offset = secs - self.wall_secs(SystemTime::now()) * self.speed,
+        }
+    }
+
+    /// Moves the animation time by `secs`, backwards if negative.
+    pub fn scrub(&mut self, secs: f64) {
+        self.set_elapsed_secs(self.at(SystemTime::now()) + secs);
+    }
+
+    /// Changes the speed without the animation time jumping.
+    pub fn set_speed(&mut self, speed: f64) {
+        let now = self.at(SystemTime::now());
+        self.speed = speed;
+        self.set_elapsed_secs(now);
+    }
+
+    /// The animation time at the wall-clock time `now`.
+    fn at(&self, now: SystemTime) -> f64 {
+        self.frozen
+            .unwrap_or_else(|| self.wall_secs(now) * self.speed + self.offset)
+    }
+
+    /// The seconds from the origin to `now`, negative before it.
+    fn wall_secs(&self, now: SystemTime) -> f64 {
+        match now.duration_since(self.origin) {
+            Ok(since) => since.as_secs_f64(),
+            Err(err) => -err.duration().as_secs_f64(),
+        }
+    }
+}
+
+/// Keeps [`WallpaperClock`] up to date, freezing it while the wallpaper is paused by a
+/// [`WallpaperCommand`], and drives [`Time<Virtual>`] from it if
+/// [`WallpaperClock::drive_time`] is set.
+#[derive(Default)]
+pub struct WallpaperClockPlugin {
+    pub clock: WallpaperClock,
+}
+
+impl Plugin for WallpaperClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.clock.clone())
+            .add_event::<WallpaperCommand>()
+            .add_systems(
+                First,
+                (update_wallpaper_clock, drive_virtual_time)
+                    .chain()
+                    .after(TimeSystem),
+            );
+    }
+}
+
+pub(crate) fn update_wallpaper_clock(
+    mut commands: EventReader<WallpaperCommand>,
+    mut clock: ResMut<WallpaperClock>,
+) {
+    for command in commands.read() {
+        match command {
+            WallpaperCommand::Pause => clock.freeze(),
+            WallpaperCommand::Resume => clock.unfreeze(),
+            WallpaperCommand::TogglePause if clock.is_frozen() => clock.unfreeze(),
+            WallpaperCommand::TogglePause => clock.freeze(),
+            WallpaperCommand::Next => {}
+        }
+    }
+
+    let elapsed = clock.at(SystemTime::now());
+    clock.delta = clock.elapsed.map_or(0.0, |previous| elapsed - previous);
+    clock.elapsed = Some(elapsed);
+}
+
+/// Moves [`Time<Virtual>`], and [`Time`] copied from it, to the animation time.
+fn drive_virtual_time(
+    clock: Res<WallpaperClock>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut time: ResMut<Time>,
+    // Whether the previous frame was driven, so its elapsed time can be moved on from.
+    mut driven: Local<bool>,
+) {
+    if !clock.drive_time || virtual_time.is_paused() {
+        *driven = false;
+        return;
+    }
+
+    // `Time` can only move forwards from zero, so it is built up again each frame with the
+    // settings of the old one.
+    let previous = virtual_time.elapsed() - virtual_time.delta();
+    let target = clock.elapsed();
+    let mut next = Time::<Virtual>::from_max_delta(virtual_time.max_delta());
+    next.set_wrap_period(virtual_time.wrap_period());
+    next.set_relative_speed_f64(virtual_time.relative_speed_f64());
+
+    // The first driven frame and running backwards have no delta.
+    next.advance_to(if *driven && target >= previous {
+        previous
+    } else {
+        target
+    });
+    next.advance_to(target);
+
+    *time = next.as_generic();
+    *virtual_time = next;
+    *driven = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{event::Events, world::World};
+
+    use super::*;
+
+    fn drive(world: &mut World) -> (f64, f64) {
+        world.run_system_cached(update_wallpaper_clock).unwrap();
+        world.run_system_cached(drive_virtual_time).unwrap();
+
+        let time = world.resource::<Time<Virtual>>();
+        assert_eq!(world.resource::<Time>().elapsed(), time.elapsed());
+        (time.elapsed_secs_f64(), time.delta_secs_f64())
+    }
+
+    #[test]
+    fn drives_virtual_time() {
+        let mut clock = WallpaperClock::new(SystemTime::now() - Duration::from_secs(100));
+        clock.drive_time = true;
+
+        let mut world = World::new();
+        world.insert_resource(clock);
+        world.init_resource::<Events<WallpaperCommand>>();
+        world.init_resource::<Time<Virtual>>();
+        world.init_resource::<Time>();
+
+        // Joining in at the clock's phase isn't a step.
+        let (elapsed, delta) = drive(&mut world);
+        assert!((elapsed - 100.0).abs() < 1.0, "{elapsed}");
+        assert_eq!(delta, 0.0);
+
+        world.resource_mut::<WallpaperClock>().scrub(5.0);
+        let (scrubbed, delta) = drive(&mut world);
+        assert!((scrubbed - elapsed - 5.0).abs() < 0.5, "{scrubbed}");
+        assert!((delta - 5.0).abs() < 0.5, "{delta}");
+
+        world.resource_mut::<WallpaperClock>().scrub(-10.0);
+        let (rewound, delta) = drive(&mut world);
+        assert!((scrubbed - rewound - 10.0).abs() < 0.5, "{rewound}");
+        assert_eq!(delta, 0.0);
+
+        // A paused `Time<Virtual>` holds still.
+        world.resource_mut::<Time<Virtual>>().pause();
+        world.resource_mut::<WallpaperClock>().scrub(20.0);
+        assert_eq!(drive(&mut world).0, rewound);
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::time::{Time, Virtual};
+use bevy_ecs::{
+    event::{Event, EventReader},
+    system::{ResMut, Resource},
+};
+
+/// Controls the running wallpaper, e.g. from OSC or a key binding.
+///
+/// Pausing stops [`Time<Virtual>`], so everything driven by it holds still. The built-in scenes
+/// skip ahead on [`WallpaperCommand::Next`], and custom scenes can read these events too.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallpaperCommand {
+    Pause,
+    Resume,
+    TogglePause,
+    /// Move on to the next image, clip or scene.
+    Next,
+}
+
+/// Why [`Time<Virtual>`] is paused, so resuming for one reason doesn't undo another.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PauseReasons {
+    /// Paused by a [`WallpaperCommand`].
+    pub(crate) command: bool,
+    /// Frozen by [`ReducedMotion`](crate::ReducedMotion).
+    pub(crate) reduced_motion: bool,
+}
+
+impl PauseReasons {
+    /// Pauses `time` while any reason applies, and lets it run otherwise.
+    pub(crate) fn apply(&self, time: &mut Time<Virtual>) {
+        if self.command || self.reduced_motion {
+            time.pause();
+        } else {
+            time.unpause();
+        }
+    }
+}
+
+pub(crate) fn apply_wallpaper_commands(
+    mut commands: EventReader<WallpaperCommand>,
+    mut reasons: ResMut<PauseReasons>,
+    time: Option<ResMut<Time<Virtual>>>,
+) {
+    let Some(mut time) = time else {
+        commands.clear();
+        return;
+    };
+
+    if commands.is_empty() {
+        return;
+    }
+
+    for command in commands.read() {
+        match command {
+            WallpaperCommand::Pause => reasons.command = true,
+            WallpaperCommand::Resume => reasons.command = false,
+            WallpaperCommand::TogglePause => reasons.command = !reasons.command,
+            WallpaperCommand::Next => {}
+        }
+    }
+
+    reasons.apply(&mut time);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{event::Events, world::World};
+
+    use super::*;
+    use crate::{DesktopSettings, ReducedMotion, desktop_settings::apply_reduced_motion};
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<PauseReasons>();
+        world.init_resource::<Time<Virtual>>();
+        world.init_resource::<Events<WallpaperCommand>>();
+        world.init_resource::<DesktopSettings>();
+        world.insert_resource(ReducedMotion::Freeze);
+        world
+    }
+
+    fn command(world: &mut World, command: WallpaperCommand) -> bool {
+        world.send_event(command);
+        world.run_system_cached(apply_wallpaper_commands).unwrap();
+        world.resource::<Time<Virtual>>().is_paused()
+    }
+
+    fn animations(world: &mut World, enable_animations: bool) -> bool {
+        world.resource_mut::<DesktopSettings>().enable_animations = enable_animations;
+        world.run_system_cached(apply_reduced_motion).unwrap();
+        world.resource::<Time<Virtual>>().is_paused()
+    }
+
+    #[test]
+    fn toggle() {
+        let mut world = world();
+
+        assert!(command(&mut world, WallpaperCommand::TogglePause));
+        assert!(!command(&mut world, WallpaperCommand::TogglePause));
+        assert!(!command(&mut world, WallpaperCommand::Next));
+    }
+
+    #[test]
+    fn reasons_do_not_undo_each_other() {
+        let mut world = world();
+
+        assert!(animations(&mut world, false));
+        // Resuming doesn't bring back motion the desktop turned off.
+        assert!(command(&mut world, WallpaperCommand::Pause));
+        assert!(command(&mut world, WallpaperCommand::Resume));
+
+        // Animations coming back don't resume a paused wallpaper.
+        assert!(command(&mut world, WallpaperCommand::Pause));
+        assert!(animations(&mut world, true));
+        assert!(!command(&mut world, WallpaperCommand::Resume));
+    }
+}
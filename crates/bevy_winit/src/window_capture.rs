@@ -0,0 +1,477 @@
+use core::time::Duration;
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    asset::{Assets, Handle, RenderAssetUsages},
+    image::Image,
+    math::UVec2,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    utils::Instant,
+};
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    component::Component,
+    entity::Entity,
+    event::EventReader,
+    system::{Local, Query, Res, ResMut},
+};
+use tracing::{info, warn};
+use winit::{event_loop::EventLoopProxy, platform::x11::X11EventKinds};
+use x11rb::{
+    connection::Connection,
+    errors::ReplyOrIdError,
+    protocol::{
+        Event as X11Event,
+        composite::{ConnectionExt as _, Redirect},
+        damage::{ConnectionExt as _, Damage, ReportLevel},
+        xfixes::ConnectionExt as _,
+        xproto::{AtomEnum, ConnectionExt as _, ImageFormat, MapState, Pixmap, Window as XWindow},
+    },
+};
+
+use crate::{
+    EventLoopProxyWrapper, WakeUp,
+    raw_x11::{RawX11Event, RawX11Listeners},
+    x11_desktop::{X11Desktop, X11EventQueue},
+};
+
+/// How often a window that isn't there is looked for again.
+const LOOKUP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Which window a [`WindowCapture`] shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureTarget {
+    /// The window with this id, e.g. from `xwininfo`.
+    Xid(u32),
+    /// The first top-level window whose `WM_CLASS` instance or class name is this, ignoring
+    /// case, e.g. `Alacritty`.
+    Class(String),
+    /// The first top-level window whose title contains this.
+    Title(String),
+}
+
+/// Mirrors another X client's window into an [`Image`], e.g. to put a terminal onto a surface
+/// in the scene.
+///
+/// The window is redirected offscreen with XComposite, which needs no compositing manager and
+/// leaves it on screen as usual. Whenever XDamage reports a change, its contents are copied into
+/// `image` on the CPU, so this works without GPU access to the window. A resized window gets an
+/// image of its new size. When the window goes away, it is looked for again every few seconds.
+/// The event loop is woken for changes if the app uses [`WakeUp`] events, otherwise they are
+/// copied on its next update.
+///
+/// Needs the `Composite` and `DAMAGE` extensions, see [`X11Extensions`](crate::X11Extensions).
+#[derive(Component, Debug, Clone)]
+pub struct WindowCapture {
+    pub target: CaptureTarget,
+    /// The image the window is copied into. If this is the default handle, a new image is added
+    /// on the first copy. Use [`Assets::reserve_handle`] to bind the image to a material before
+    /// then.
+    pub image: Handle<Image>,
+}
+
+impl WindowCapture {
+    pub fn new(target: CaptureTarget) -> Self {
+        Self {
+            target,
+            image: Handle::default(),
+        }
+    }
+}
+
+/// A window being captured.
+struct Captured {
+    window: XWindow,
+    damage: Damage,
+    /// The offscreen contents of the window, named anew after it is resized or mapped again.
+    pixmap: Option<Pixmap>,
+    /// The size of the window when it was last copied.
+    size: UVec2,
+    /// Whether there is damage that hasn't been copied yet.
+    dirty: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct CaptureState {
+    /// Whether the extensions are there and their versions were negotiated, which has to happen
+    /// before using them. `None` until the first capture is added.
+    available: Option<bool>,
+    captured: HashMap<Entity, Captured>,
+    /// When captures of windows that weren't found are tried again.
+    next_lookup: HashMap<Entity, Instant>,
+}
+
+/// Starts, updates and stops the captures of all [`WindowCapture`]s.
+pub(crate) fn capture_windows(
+    mut raw_events: EventReader<RawX11Event>,
+    desktop: Res<X11Desktop>,
+    proxy: Option<Res<EventLoopProxyWrapper<WakeUp>>>,
+    mut listeners: ResMut<RawX11Listeners>,
+    mut captures: Query<(Entity, &mut WindowCapture)>,
+    mut images: ResMut<Assets<Image>>,
+    mut state: Local<CaptureState>,
+) {
+    let state = &mut *state;
+
+    if captures.is_empty() && state.captured.is_empty() {
+        return;
+    }
+
+    let available = *state.available.get_or_insert_with(|| {
+        let extensions = desktop.extensions();
+        if !(extensions.composite && extensions.damage && extensions.xfixes) {
+            warn!("Windows can't be captured without the Composite and DAMAGE extensions");
+            return false;
+        }
+
+        if let Err(err) = negotiate_versions(&desktop) {
+            warn!("Could not set up window capture: {err}");
+            return false;
+        }
+
+        if let Some(proxy) = &proxy {
+            let proxy = EventLoopProxy::clone(proxy);
+            desktop.wake_on_damage(move || {
+                let _ = proxy.send_event(WakeUp);
+            });
+        }
+
+        true
+    });
+    if !available {
+        return;
+    }
+
+    // Stop the captures that were removed or pointed at another window.
+    let captured: Vec<Entity> = state.captured.keys().copied().collect();
+    for entity in captured {
+        let changed = captures
+            .get_mut(entity)
+            .is_ok_and(|(_, capture)| capture.is_changed());
+
+        if changed || !captures.contains(entity) {
+            let captured = state.captured.remove(&entity).unwrap();
+            stop(&desktop, &mut listeners, &captured);
+        }
+    }
+    state.next_lookup.retain(|&entity, _| {
+        captures
+            .get_mut(entity)
+            .is_ok_and(|(_, capture)| !capture.is_changed())
+    });
+
+    let damaged: HashSet<Damage> = desktop
+        .take_events(X11EventQueue::Damage)
+        .into_iter()
+        .filter_map(|event| match event {
+            X11Event::DamageNotify(event) => Some(event.damage),
+            _ => None,
+        })
+        .collect();
+
+    for RawX11Event { event } in raw_events.read() {
+        // Which window the event is about, and its new size if it was reconfigured.
+        let (window, size, gone) = match event {
+            X11Event::ConfigureNotify(event) => (
+                event.window,
+                Some(UVec2::new(event.width.into(), event.height.into())),
+                false,
+            ),
+            X11Event::MapNotify(event) => (event.window, None, false),
+            X11Event::UnmapNotify(event) => (event.window, None, false),
+            X11Event::DestroyNotify(event) => (event.window, None, true),
+            _ => continue,
+        };
+
+        let affected: Vec<Entity> = state
+            .captured
+            .iter()
+            .filter(|(_, captured)| captured.window == window)
+            .map(|(entity, _)| *entity)
+            .collect();
+
+        for entity in affected {
+            if gone {
+                info!("Captured window {window:#x} was destroyed, looking for it again");
+                // The server already freed the damage along with the window.
+                if let Some(captured) = state.captured.remove(&entity) {
+                    listeners.stop(captured.window);
+                    free_pixmap(&desktop, &captured);
+                }
+            } else {
+                let captured = state.captured.get_mut(&entity).unwrap();
+                // Moving the window keeps its pixmap.
+                if size.is_some_and(|size| size == captured.size) {
+                    continue;
+                }
+                free_pixmap(&desktop, captured);
+                captured.pixmap = None;
+                captured.dirty = true;
+            }
+        }
+    }
+
+    let now = Instant::now();
+
+    for (entity, mut capture) in &mut captures {
+        if !state.captured.contains_key(&entity) {
+            if state.next_lookup.get(&entity).is_some_and(|at| *at > now) {
+                continue;
+            }
+            state.next_lookup.insert(entity, now + LOOKUP_INTERVAL);
+
+            match start(&desktop, &mut listeners, &capture.target) {
+                Ok(Some(captured)) => {
+                    state.next_lookup.remove(&entity);
+                    state.captured.insert(entity, captured);
+                }
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("Could not capture {:?}: {err}", capture.target);
+                    continue;
+                }
+            }
+        }
+
+        let captured = state.captured.get_mut(&entity).unwrap();
+        if damaged.contains(&captured.damage) {
+            captured.dirty = true;
+        }
+        if !captured.dirty {
+            continue;
+        }
+
+        match copy_window(&desktop, captured) {
+            Ok(Some(image)) => {
+                captured.dirty = false;
+
+                // Only the first copy needs the capture itself, to set the handle.
+                if capture.image == Handle::default() {
+                    capture.bypass_change_detection().image = images.add(image);
+                } else if let Some(existing) = images
+                    .get_mut(&capture.image)
+                    .filter(|existing| existing.size() == image.size())
+                {
+                    existing.data = image.data;
+                } else {
+                    images.insert(&capture.image, image);
+                }
+            }
+            // Unmapped windows have no contents until they are mapped again.
+            Ok(None) => captured.dirty = false,
+            Err(err) => {
+                warn!("Could not copy window {:#x}: {err}", captured.window);
+                if let Some(captured) = state.captured.remove(&entity) {
+                    stop(&desktop, &mut listeners, &captured);
+                }
+            }
+        }
+    }
+}
+
+fn negotiate_versions(desktop: &X11Desktop) -> Result<(), ReplyOrIdError> {
+    let conn = desktop.connection();
+    conn.xfixes_query_version(5, 0)?.reply()?;
+    conn.damage_query_version(1, 1)?.reply()?;
+    conn.composite_query_version(0, 4)?.reply()?;
+    Ok(())
+}
+
+/// Finds the target window and redirects it, or returns `None` if there is no such window.
+fn start(
+    desktop: &X11Desktop,
+    listeners: &mut RawX11Listeners,
+    target: &CaptureTarget,
+) -> Result<Option<Captured>, ReplyOrIdError> {
+    let Some(window) = find_window(desktop, target)? else {
+        return Ok(None);
+    };
+
+    let conn = desktop.connection();
+    conn.composite_redirect_window(window, Redirect::AUTOMATIC)?
+        .check()?;
+
+    let damage = conn.generate_id()?;
+    conn.damage_create(damage, window, ReportLevel::NON_EMPTY)?
+        .check()?;
+    conn.flush()?;
+
+    listeners.listen(
+        window,
+        X11EventKinds {
+            structure: true,
+            ..Default::default()
+        },
+    );
+
+    info!("Capturing window {window:#x} for {target:?}");
+
+    Ok(Some(Captured {
+        window,
+        damage,
+        pixmap: None,
+        size: UVec2::ZERO,
+        dirty: true,
+    }))
+}
+
+fn stop(desktop: &X11Desktop, listeners: &mut RawX11Listeners, captured: &Captured) {
+    let conn = desktop.connection();
+    listeners.stop(captured.window);
+    free_pixmap(desktop, captured);
+
+    // The window may be gone already, in which case the server cleaned up after it.
+    let _ = conn.damage_destroy(captured.damage);
+    let _ = conn.composite_unredirect_window(captured.window, Redirect::AUTOMATIC);
+    let _ = conn.flush();
+}
+
+fn free_pixmap(desktop: &X11Desktop, captured: &Captured) {
+    if let Some(pixmap) = captured.pixmap {
+        let _ = desktop.connection().free_pixmap(pixmap);
+    }
+}
+
+fn find_window(
+    desktop: &X11Desktop,
+    target: &CaptureTarget,
+) -> Result<Option<XWindow>, ReplyOrIdError> {
+    let conn = desktop.connection();
+
+    if let CaptureTarget::Xid(window) = target {
+        let exists = conn.get_window_attributes(*window)?.reply().is_ok();
+        return Ok(exists.then_some(*window));
+    }
+
+    let atoms = desktop.atoms();
+    let clients = conn
+        .get_property(
+            false,
+            desktop.root(),
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            0,
+            u32::MAX / 4,
+        )?
+        .reply()?;
+    let clients: Vec<XWindow> = match clients.value32() {
+        Some(clients) => clients.collect(),
+        // Without a window manager publishing its clients, look at the root's children.
+        None => conn.query_tree(desktop.root())?.reply()?.children,
+    };
+
+    for window in clients {
+        // Windows may disappear while they are looked at.
+        let matches = match target {
+            CaptureTarget::Xid(_) => unreachable!(),
+            CaptureTarget::Class(class) => read_string(desktop, window, AtomEnum::WM_CLASS.into())?
+                .split('\0')
+                .any(|name| name.eq_ignore_ascii_case(class)),
+            CaptureTarget::Title(title) => {
+                let name = read_string(desktop, window, atoms._NET_WM_NAME)?;
+                let name = if name.is_empty() {
+                    read_string(desktop, window, AtomEnum::WM_NAME.into())?
+                } else {
+                    name
+                };
+                name.contains(title.as_str())
+            }
+        };
+
+        if matches {
+            return Ok(Some(window));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads a text property, or an empty string if the window doesn't have it.
+fn read_string(
+    desktop: &X11Desktop,
+    window: XWindow,
+    property: u32,
+) -> Result<String, ReplyOrIdError> {
+    let value = desktop
+        .connection()
+        .get_property(false, window, property, AtomEnum::ANY, 0, 1024)?
+        .reply()
+        .map(|reply| reply.value)
+        .unwrap_or_default();
+
+    Ok(String::from_utf8_lossy(&value).into_owned())
+}
+
+/// Copies the contents of the window, or returns `None` if it isn't mapped.
+fn copy_window(
+    desktop: &X11Desktop,
+    captured: &mut Captured,
+) -> Result<Option<Image>, ReplyOrIdError> {
+    let conn = desktop.connection();
+
+    // Copy everything damaged so far, and be told about damage from here on.
+    conn.damage_subtract(captured.damage, x11rb::NONE, x11rb::NONE)?;
+
+    let pixmap = match captured.pixmap {
+        Some(pixmap) => pixmap,
+        None => {
+            let attributes = conn.get_window_attributes(captured.window)?.reply()?;
+            if attributes.map_state != MapState::VIEWABLE {
+                return Ok(None);
+            }
+
+            let pixmap = conn.generate_id()?;
+            conn.composite_name_window_pixmap(captured.window, pixmap)?
+                .check()?;
+            captured.pixmap = Some(pixmap);
+            pixmap
+        }
+    };
+
+    let geometry = conn.get_geometry(pixmap)?.reply()?;
+    let size = UVec2::new(geometry.width.into(), geometry.height.into());
+    captured.size = size;
+    if size.min_element() == 0 {
+        return Ok(None);
+    }
+
+    let reply = conn
+        .get_image(
+            ImageFormat::Z_PIXMAP,
+            pixmap,
+            0,
+            0,
+            geometry.width,
+            geometry.height,
+            !0,
+        )?
+        .reply()?;
+
+    let mut pixels = reply.data;
+    if pixels.len() != size.element_product() as usize * 4 {
+        warn!(
+            "Window {:#x} has an unsupported depth of {}",
+            captured.window, geometry.depth
+        );
+        return Ok(None);
+    }
+
+    // Windows without an alpha channel leave the padding byte undefined.
+    if geometry.depth != 32 {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = u8::MAX;
+        }
+    }
+
+    Ok(Some(Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    )))
+}
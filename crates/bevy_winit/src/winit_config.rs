@@ -1,5 +1,6 @@
 // use bevy_ecs::resource::Resource;
 use bevy::prelude::Resource;
+use bevy_math::{IVec2, UVec2};
 use core::time::Duration;
 
 /// Settings for the [`WinitPlugin`](super::WinitPlugin).
@@ -124,3 +125,144 @@ impl UpdateMode {
         }
     }
 }
+
+/// Where the [`WallpaperPlugin`](crate::WallpaperPlugin) draws the wallpaper.
+#[derive(Debug, Resource, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WallpaperTarget {
+    /// Create a window for each [`Window`](bevy_window::Window) entity and embed it into the
+    /// root window, below every other window.
+    ///
+    /// This works with compositors that paint over the root window.
+    #[default]
+    Embedded,
+    /// Draw straight onto the root window.
+    ///
+    /// Only the first window entity can use the root window. It isn't reparented, mapped or
+    /// destroyed, and only receives events no other client holds. Compositors that paint over
+    /// the root window will hide it.
+    Root,
+    /// Open a regular, decorated window showing the wallpaper, to try it out without replacing
+    /// the desktop background.
+    ///
+    /// Each window entity is embedded into the preview window over the simulated monitor of the
+    /// [`PreviewLayout`] resource it is fullscreen on, so scenes run as they would on the
+    /// desktop. Closing the preview window exits the app.
+    Preview,
+}
+
+/// The monitors [`WallpaperTarget::Preview`] simulates, which can be changed while it runs.
+///
+/// Monitor positions are relative to the top left corner of the preview window, which is sized
+/// to fit them all, the way real monitor positions are relative to the root window. Scaling a
+/// layout down keeps it on the screen.
+#[derive(Debug, Resource, Clone, PartialEq)]
+pub struct PreviewLayout {
+    pub monitors: Vec<PreviewMonitor>,
+}
+
+impl Default for PreviewLayout {
+    fn default() -> Self {
+        Self::side_by_side([UVec2::new(1280, 720)])
+    }
+}
+
+impl PreviewLayout {
+    /// Monitors of the given sizes next to each other, aligned at the top. The first is the
+    /// primary monitor, and they are named `PREVIEW-1`, `PREVIEW-2` and so on.
+    pub fn side_by_side(sizes: impl IntoIterator<Item = UVec2>) -> Self {
+        let mut x = 0;
+        let monitors = sizes
+            .into_iter()
+            .enumerate()
+            .map(|(idx, size)| {
+                let monitor = PreviewMonitor {
+                    primary: idx == 0,
+                    ..PreviewMonitor::new(format!("PREVIEW-{}", idx + 1), IVec2::new(x, 0), size)
+                };
+                x += size.x as i32;
+                monitor
+            })
+            .collect();
+
+        Self { monitors }
+    }
+
+    /// The size of the preview window, which fits every monitor.
+    pub fn size(&self) -> UVec2 {
+        self.monitors
+            .iter()
+            .map(|monitor| monitor.position.max(IVec2::ZERO).as_uvec2() + monitor.size)
+            .fold(UVec2::ONE, UVec2::max)
+    }
+}
+
+/// A monitor simulated inside the preview window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewMonitor {
+    /// The output name the monitor reports, e.g. `DP-2`.
+    pub name: String,
+    /// In physical pixels from the top left corner of the preview window.
+    pub position: IVec2,
+    /// In physical pixels.
+    pub size: UVec2,
+    /// When no monitor is primary, the first one is.
+    pub primary: bool,
+}
+
+impl PreviewMonitor {
+    pub fn new(name: impl Into<String>, position: IVec2, size: UVec2) -> Self {
+        Self {
+            name: name.into(),
+            position,
+            size,
+            primary: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_by_side() {
+        let layout = PreviewLayout::side_by_side([UVec2::new(1920, 1080), UVec2::new(1280, 1024)]);
+
+        assert_eq!(
+            layout.monitors,
+            [
+                PreviewMonitor {
+                    primary: true,
+                    ..PreviewMonitor::new("PREVIEW-1", IVec2::ZERO, UVec2::new(1920, 1080))
+                },
+                PreviewMonitor::new("PREVIEW-2", IVec2::new(1920, 0), UVec2::new(1280, 1024)),
+            ]
+        );
+        assert_eq!(layout.size(), UVec2::new(3200, 1080));
+    }
+
+    #[test]
+    fn size() {
+        let layout = |monitors| PreviewLayout { monitors };
+
+        assert_eq!(layout(Vec::new()).size(), UVec2::ONE);
+        assert_eq!(
+            layout(vec![
+                PreviewMonitor::new("A", IVec2::new(100, 50), UVec2::new(800, 600)),
+                PreviewMonitor::new("B", IVec2::new(0, 700), UVec2::new(400, 300)),
+            ])
+            .size(),
+            UVec2::new(900, 1000)
+        );
+        // Parts left of or above the window are cut off rather than growing it.
+        assert_eq!(
+            layout(vec![PreviewMonitor::new(
+                "A",
+                IVec2::new(-200, -100),
+                UVec2::new(800, 600)
+            )])
+            .size(),
+            UVec2::new(800, 600)
+        );
+    }
+}
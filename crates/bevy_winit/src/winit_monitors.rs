@@ -1,6 +1,9 @@
 use winit::monitor::MonitorHandle;
 
 use bevy_ecs::entity::Entity;
+use bevy_window::{MonitorSelection, WindowMode};
+
+use crate::winit_config::PreviewMonitor;
 
 /// Stores [`winit`] monitors and their corresponding entities
 ///
@@ -18,6 +21,12 @@ pub struct WinitMonitors {
     // on some platforms. Using a `Vec` is fine because we don't expect to have a large number of
     // monitors and avoids having to audit the code for `MonitorHandle` equality.
     pub(crate) monitors: Vec<(MonitorHandle, Entity)>,
+    /// The monitors simulated by [`WallpaperTarget::Preview`](crate::WallpaperTarget::Preview)
+    /// instead, in layout order.
+    pub(crate) previews: Vec<(PreviewMonitor, Entity)>,
+    /// Whether `monitors` matches what RandR reported last. Cleared by RandR change
+    /// notifications.
+    pub(crate) up_to_date: bool,
 }
 
 impl WinitMonitors {
@@ -31,4 +40,39 @@ impl WinitMonitors {
             .find(|(_, e)| *e == entity)
             .map(|(monitor, _)| monitor.clone())
     }
+
+    /// The entity of the `n`th monitor, real or simulated.
+    pub(crate) fn nth_entity(&self, n: usize) -> Option<Entity> {
+        self.monitors
+            .get(n)
+            .map(|(_, entity)| *entity)
+            .or_else(|| self.previews.get(n).map(|(_, entity)| *entity))
+    }
+
+    /// The simulated monitor a window in `mode` covers, `None` for windowed windows.
+    pub(crate) fn preview_monitor(&self, mode: &WindowMode) -> Option<&PreviewMonitor> {
+        let (WindowMode::BorderlessFullscreen(selection)
+        | WindowMode::Fullscreen(selection)
+        | WindowMode::SizedFullscreen(selection)) = mode
+        else {
+            return None;
+        };
+
+        let primary = || {
+            self.previews
+                .iter()
+                .find(|(monitor, _)| monitor.primary)
+                .or(self.previews.first())
+        };
+
+        let found = match selection {
+            MonitorSelection::Primary | MonitorSelection::Current => primary(),
+            MonitorSelection::Index(n) => self.previews.get(*n),
+            MonitorSelection::Entity(entity) => {
+                self.previews.iter().find(|(_, found)| found == entity)
+            }
+        };
+
+        found.map(|(monitor, _)| monitor)
+    }
 }
@@ -2,18 +2,22 @@ use std::num::NonZeroU32;
 
 use super::{
     converters::{convert_enabled_buttons, convert_window_level, convert_window_theme},
+    error::WallpaperError,
+    winit_config::PreviewLayout,
     winit_monitors::WinitMonitors,
+    x11_desktop::X11Desktop,
 };
 use bevy::utils::HashMap;
 use bevy_ecs::entity::Entity;
 use bevy_ecs::entity::EntityHashMap;
+use bevy_math::{IVec2, UVec2};
 use bevy_window::{
     CursorGrabMode, MonitorSelection, Window, WindowMode, WindowPosition, WindowResolution,
     WindowWrapper,
 };
 use tracing::{error, info, warn};
 use winit::{
-    dpi::{LogicalSize, PhysicalPosition},
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
     error::ExternalError,
     event_loop::ActiveEventLoop,
     monitor::{MonitorHandle, VideoModeHandle},
@@ -21,7 +25,9 @@ use winit::{
     window::{CursorGrabMode as WinitCursorGrabMode, Fullscreen, Window as WinitWindow, WindowId},
 };
 use winit::{
-    platform::x11::{WindowAttributesExtX11, WindowType},
+    platform::x11::{
+        ActiveEventLoopExtX11, ForeignWindowOptions, WindowAttributesExtX11, WindowType,
+    },
     window::WindowButtons,
 };
 
@@ -41,6 +47,9 @@ pub struct WinitWindows {
     pub entity_to_winit: EntityHashMap<WindowId>,
     /// Maps `winit` window identifiers to entities.
     pub winit_to_entity: HashMap<WindowId, Entity>,
+    /// The window [`WallpaperTarget::Preview`](crate::WallpaperTarget::Preview) embeds the
+    /// other windows into, with the size it was given.
+    pub(crate) preview: Option<(WinitWindow, UVec2)>,
     // Many `winit` window functions (e.g. `set_window_icon`) can only be called on the main thread.
     // If they're called on other threads, the program might hang. This marker indicates that this
     // type is not thread-safe and will be `!Send` and `!Sync`.
@@ -49,6 +58,8 @@ pub struct WinitWindows {
 
 impl WinitWindows {
     /// Creates a `winit` window and associates it with our entity.
+    ///
+    /// The window is embedded into `parent_window_id`, which must be a window on `desktop`.
     pub fn create_window(
         &mut self,
         event_loop: &ActiveEventLoop,
@@ -58,8 +69,13 @@ impl WinitWindows {
         // handlers: &mut WinitActionRequestHandlers,
         // accessibility_requested: &AccessibilityRequested,
         monitors: &WinitMonitors,
+        desktop: &X11Desktop,
         parent_window_id: u32,
-    ) -> &WindowWrapper<WinitWindow> {
+    ) -> Result<&WindowWrapper<WinitWindow>, WallpaperError> {
+        let parent_window =
+            NonZeroU32::new(parent_window_id).ok_or(WallpaperError::BadParent(parent_window_id))?;
+        desktop.check_window(parent_window_id)?;
+
         // println!("create_window");
         let mut winit_window_attributes = WinitWindow::default_attributes();
         // winit_window_attributes.;
@@ -97,7 +113,7 @@ impl WinitWindows {
 
         winit_window_attributes = unsafe {
             winit_window_attributes.with_parent_window(Some(RawWindowHandle::Xcb(
-                XcbWindowHandle::new(NonZeroU32::new(parent_window_id).unwrap()),
+                XcbWindowHandle::new(parent_window),
             )))
         };
 
@@ -126,7 +142,7 @@ impl WinitWindows {
             WindowMode::Fullscreen(monitor_selection) => {
                 let select_monitor = &maybe_selected_monitor
                     .clone()
-                    .expect("Unable to get monitor.");
+                    .ok_or(WallpaperError::NoMonitor(monitor_selection))?;
 
                 if let Some(video_mode) = get_current_videomode(select_monitor) {
                     winit_window_attributes = winit_window_attributes
@@ -278,13 +294,148 @@ impl WinitWindows {
         // println!("winit_window_atters => {:?}", winit_window_attributes);
         let winit_window_res = event_loop.create_window(winit_window_attributes);
         // println!("winit_window_res => {:?}", winit_window_res);
-        let winit_window = winit_window_res.unwrap();
+        let winit_window = winit_window_res?;
         let _name = window.title.clone();
 
         // Now that the AccessKit adapter is created, it's safe to show
         // the window.
         winit_window.set_visible(window.visible);
 
+        Ok(self.insert_window(entity, winit_window))
+    }
+
+    /// Draws `entity` straight onto the root window `root` instead of creating a window for it.
+    ///
+    /// The root window belongs to the X server, so it is only adopted: none of the `window`
+    /// settings that concern the window manager are applied.
+    pub fn adopt_root_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        entity: Entity,
+        window: &Window,
+        root: u32,
+    ) -> Result<&WindowWrapper<WinitWindow>, WallpaperError> {
+        let root_id = WindowId::from(u64::from(root));
+
+        if self.windows.contains_key(&root_id) {
+            return Err(WallpaperError::RootInUse);
+        }
+
+        let options = ForeignWindowOptions {
+            drag_and_drop: true,
+            ..ForeignWindowOptions::default()
+        };
+
+        info!(
+            "Drawing window {} onto the root window {root:#x}",
+            window.title
+        );
+        let winit_window = event_loop.adopt_foreign_window(root, options)?;
+
+        Ok(self.insert_window(entity, winit_window))
+    }
+
+    /// Creates a `winit` window for `entity` inside the preview window, over the simulated
+    /// monitor the window is fullscreen on, opening the preview window first if needed.
+    ///
+    /// Windowed windows keep their size and position within the preview window.
+    pub fn create_preview_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        entity: Entity,
+        window: &Window,
+        monitors: &WinitMonitors,
+        layout: &PreviewLayout,
+    ) -> Result<&WindowWrapper<WinitWindow>, WallpaperError> {
+        let parent = self.fit_preview_window(event_loop, layout)?;
+        let parent_window = NonZeroU32::new(parent).ok_or(WallpaperError::BadParent(parent))?;
+
+        let (position, size) = match monitors.preview_monitor(&window.mode) {
+            Some(monitor) => (monitor.position, monitor.size),
+            None => {
+                let position = match window.position {
+                    WindowPosition::At(position) => position,
+                    _ => IVec2::ZERO,
+                };
+                (position, window.resolution.physical_size())
+            }
+        };
+
+        let mut winit_window_attributes = WinitWindow::default_attributes()
+            .with_embed_parent_window(parent)
+            .with_decorations(false)
+            .with_position(PhysicalPosition::new(position.x, position.y))
+            .with_inner_size(PhysicalSize::new(size.x.max(1), size.y.max(1)))
+            .with_transparent(window.transparent)
+            .with_title(window.title.as_str());
+
+        winit_window_attributes = unsafe {
+            winit_window_attributes.with_parent_window(Some(RawWindowHandle::Xcb(
+                XcbWindowHandle::new(parent_window),
+            )))
+        };
+
+        if let Some(name) = &window.name {
+            winit_window_attributes = winit_window_attributes.with_name(name.clone(), "");
+        }
+
+        info!(
+            "Previewing window {} at {position} with size {size}",
+            window.title
+        );
+        let winit_window = event_loop.create_window(winit_window_attributes)?;
+        winit_window.set_visible(window.visible);
+
+        Ok(self.insert_window(entity, winit_window))
+    }
+
+    /// Whether `winit_id` is the preview window.
+    pub(crate) fn is_preview_window(&self, winit_id: WindowId) -> bool {
+        self.preview
+            .as_ref()
+            .is_some_and(|(preview, _)| preview.id() == winit_id)
+    }
+
+    /// Opens the preview window, or resizes it after the layout changed, and returns its X
+    /// window id.
+    pub(crate) fn fit_preview_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        layout: &PreviewLayout,
+    ) -> Result<u32, WallpaperError> {
+        let size = layout.size();
+
+        match &mut self.preview {
+            Some((preview, fitted)) => {
+                if *fitted != size {
+                    *fitted = size;
+                    let _ = preview.request_inner_size(PhysicalSize::new(size.x, size.y));
+                }
+            }
+            None => {
+                let attributes = WinitWindow::default_attributes()
+                    .with_title("Wallpaper preview")
+                    .with_name("bevy-linux-wallpaper-preview", "")
+                    .with_inner_size(PhysicalSize::new(size.x, size.y))
+                    .with_resizable(false);
+
+                info!("Opening a {size} preview window");
+                self.preview = Some((event_loop.create_window(attributes)?, size));
+            }
+        }
+
+        let (preview, _) = self
+            .preview
+            .as_ref()
+            .expect("the preview window was opened");
+        Ok(u64::from(preview.id()) as u32)
+    }
+
+    fn insert_window(
+        &mut self,
+        entity: Entity,
+        winit_window: WinitWindow,
+    ) -> &WindowWrapper<WinitWindow> {
         // winit_window.
 
         // Do not set the grab mode on window creation if it's none. It can fail on mobile.
@@ -0,0 +1,303 @@
+use core::mem;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use bevy_ecs::system::Resource;
+use tracing::warn;
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    errors::ReplyError,
+    protocol::{
+        ErrorKind, Event as X11Event,
+        randr::{self, ConnectionExt as _},
+        xproto::{ConnectionExt as _, Screen, Window as XWindow},
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::error::WallpaperError;
+
+x11rb::atom_manager! {
+    /// Atoms the wallpaper looks up, interned once when connecting.
+    pub Atoms: AtomsCookie {
+        UTF8_STRING,
+        EDID,
+        RESOURCE_MANAGER,
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST,
+        _NET_WM_NAME,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_DESKTOP,
+        _XROOTPMAP_ID,
+        _XSETTINGS_SETTINGS,
+    }
+}
+
+/// Optional X extensions the wallpaper can make use of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct X11Extensions {
+    /// Redirecting other windows offscreen for capture.
+    pub composite: bool,
+    /// Being told when a captured window changes.
+    pub damage: bool,
+    /// Cursor and region helpers used alongside damage tracking.
+    pub xfixes: bool,
+}
+
+/// Which reader an event on the [`X11Desktop`] connection is queued for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum X11EventQueue {
+    /// Screen, CRTC and output changes, read when monitors are updated.
+    Randr,
+    /// Changes to captured windows, read by `capture_windows`.
+    Damage,
+}
+
+impl X11EventQueue {
+    fn of(event: &X11Event) -> Option<Self> {
+        match event {
+            X11Event::RandrScreenChangeNotify(_) | X11Event::RandrNotify(_) => Some(Self::Randr),
+            X11Event::DamageNotify(_) => Some(Self::Damage),
+            _ => None,
+        }
+    }
+}
+
+/// Events read off the connection that haven't been taken by their reader yet.
+#[derive(Default)]
+struct X11EventQueues {
+    randr: Vec<X11Event>,
+    damage: Vec<X11Event>,
+    /// Called when damage arrives while none is queued, since the event loop isn't woken by
+    /// events on this connection.
+    wake_on_damage: Option<Box<dyn Fn() + Send>>,
+}
+
+impl X11EventQueues {
+    fn queue(&mut self, queue: X11EventQueue) -> &mut Vec<X11Event> {
+        match queue {
+            X11EventQueue::Randr => &mut self.randr,
+            X11EventQueue::Damage => &mut self.damage,
+        }
+    }
+
+    /// Queues the event for its reader. Errors of requests that weren't checked are dropped,
+    /// and so are events no reader takes.
+    fn route(&mut self, event: X11Event) {
+        let Some(queue) = X11EventQueue::of(&event) else {
+            return;
+        };
+
+        let was_empty = self.queue(queue).is_empty();
+        self.queue(queue).push(event);
+
+        if queue == X11EventQueue::Damage
+            && was_empty
+            && let Some(wake) = &self.wake_on_damage
+        {
+            wake();
+        }
+    }
+}
+
+/// X extensions winit can't run without.
+const REQUIRED_EXTENSIONS: [&str; 3] = ["XInputExtension", "XKEYBOARD", "RANDR"];
+
+/// The X server the wallpaper is drawn on.
+///
+/// Owns the one x11rb connection the crate uses, so systems that talk to X directly should go
+/// through this resource instead of opening their own connection.
+#[derive(Resource, Clone)]
+pub struct X11Desktop {
+    conn: Arc<RustConnection>,
+    screen_num: usize,
+    atoms: Atoms,
+    extensions: X11Extensions,
+    events: Arc<Mutex<X11EventQueues>>,
+}
+
+impl X11Desktop {
+    /// Connects to the display named by `DISPLAY` and checks it supports everything winit needs.
+    pub fn connect() -> Result<Self, WallpaperError> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+
+        for name in REQUIRED_EXTENSIONS {
+            if !has_extension(&conn, name) {
+                return Err(WallpaperError::MissingExtension(name));
+            }
+        }
+
+        let extensions = X11Extensions {
+            composite: has_extension(&conn, "Composite"),
+            damage: has_extension(&conn, "DAMAGE"),
+            xfixes: has_extension(&conn, "XFIXES"),
+        };
+
+        let atoms = Atoms::new(&conn)?.reply()?;
+
+        // Monitor hotplug is picked up by `create_monitors`.
+        let root = conn.setup().roots[screen_num].root;
+        conn.randr_select_input(
+            root,
+            randr::NotifyMask::SCREEN_CHANGE
+                | randr::NotifyMask::CRTC_CHANGE
+                | randr::NotifyMask::OUTPUT_CHANGE,
+        )?;
+        conn.flush()?;
+
+        let conn = Arc::new(conn);
+        let events = Arc::<Mutex<X11EventQueues>>::default();
+
+        // Events are routed as they arrive rather than when they are taken, so damage can wake
+        // the event loop. Without this thread they are still routed when taken.
+        let started = thread::Builder::new()
+            .name("X11 event router".into())
+            .spawn({
+                let (conn, events) = (conn.clone(), events.clone());
+                move || {
+                    while let Ok(event) = conn.wait_for_event() {
+                        events.lock().unwrap().route(event);
+                    }
+                }
+            });
+        if let Err(err) = started {
+            warn!("Could not start routing X11 events: {err}");
+        }
+
+        Ok(Self {
+            conn,
+            screen_num,
+            atoms,
+            extensions,
+            events,
+        })
+    }
+
+    /// The shared connection to the X server.
+    pub fn connection(&self) -> &Arc<RustConnection> {
+        &self.conn
+    }
+
+    pub fn screen_num(&self) -> usize {
+        self.screen_num
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.conn.setup().roots[self.screen_num]
+    }
+
+    /// The root window of the default screen, which wallpaper windows are embedded into.
+    pub fn root(&self) -> XWindow {
+        self.screen().root
+    }
+
+    pub fn atoms(&self) -> &Atoms {
+        &self.atoms
+    }
+
+    pub fn extensions(&self) -> X11Extensions {
+        self.extensions
+    }
+
+    /// Checks that `window` exists, so embedding into it fails with
+    /// [`WallpaperError::BadParent`] rather than a generic window creation error.
+    pub(crate) fn check_window(&self, window: XWindow) -> Result<(), WallpaperError> {
+        match self.conn.get_window_attributes(window)?.reply() {
+            Ok(_) => Ok(()),
+            Err(ReplyError::X11Error(err)) if err.error_kind == ErrorKind::Window => {
+                Err(WallpaperError::BadParent(window))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Takes the pending events of one queue.
+    ///
+    /// Events only arrive for what was selected on this connection, like RandR changes and
+    /// damage on captured windows. Each queue has a single reader, so events no other reader
+    /// takes don't pile up.
+    pub(crate) fn take_events(&self, queue: X11EventQueue) -> Vec<X11Event> {
+        let mut events = self.events.lock().unwrap();
+
+        while let Ok(Some(event)) = self.conn.poll_for_event() {
+            events.route(event);
+        }
+
+        mem::take(events.queue(queue))
+    }
+
+    /// Calls `wake` whenever damage arrives for [`X11EventQueue::Damage`] while none is queued.
+    pub(crate) fn wake_on_damage(&self, wake: impl Fn() + Send + 'static) {
+        self.events.lock().unwrap().wake_on_damage = Some(Box::new(wake));
+    }
+}
+
+fn has_extension(conn: &RustConnection, name: &'static str) -> bool {
+    conn.extension_information(name)
+        .is_ok_and(|info| info.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use x11rb::protocol::{
+        damage::NotifyEvent as DamageNotifyEvent, randr::ScreenChangeNotifyEvent,
+        xproto::PropertyNotifyEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn routes_events_to_their_queue() {
+        let mut queues = X11EventQueues::default();
+        let damage = |damage| {
+            X11Event::DamageNotify(DamageNotifyEvent {
+                damage,
+                ..Default::default()
+            })
+        };
+
+        queues.route(X11Event::RandrScreenChangeNotify(
+            ScreenChangeNotifyEvent::default(),
+        ));
+        queues.route(damage(1));
+        queues.route(X11Event::PropertyNotify(PropertyNotifyEvent::default()));
+        queues.route(damage(2));
+
+        assert_eq!(queues.randr.len(), 1);
+        assert!(matches!(
+            queues.damage[..],
+            [
+                X11Event::DamageNotify(DamageNotifyEvent { damage: 1, .. }),
+                X11Event::DamageNotify(DamageNotifyEvent { damage: 2, .. }),
+            ]
+        ));
+    }
+
+    #[test]
+    fn wakes_once_per_batch_of_damage() {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let mut queues = X11EventQueues::default();
+        queues.wake_on_damage = Some(Box::new({
+            let wakes = wakes.clone();
+            move || {
+                wakes.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+
+        let damage = || X11Event::DamageNotify(DamageNotifyEvent::default());
+        queues.route(damage());
+        queues.route(damage());
+        queues.route(X11Event::RandrScreenChangeNotify(
+            ScreenChangeNotifyEvent::default(),
+        ));
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+
+        queues.queue(X11EventQueue::Damage).clear();
+        queues.route(damage());
+        assert_eq!(wakes.load(Ordering::Relaxed), 2);
+    }
+}
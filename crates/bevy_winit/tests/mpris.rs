@@ -0,0 +1,159 @@
+//! Follows a fake MPRIS player on a private bus started with `dbus-daemon`.
+#![cfg(feature = "mpris")]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    env, fs, process,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{asset::Assets, image::Image};
+use bevy_app::App;
+use bevy_ecs::event::Events;
+use bevy_linux_wallpaper::{
+    MprisConfig, MprisPlugin, NowPlaying, PlaybackStatus, Track, TrackChanged,
+};
+use image::{ImageFormat, RgbaImage};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+use crate::common::PrivateBus;
+
+/// Plays track after track, all with the same cover art.
+struct Player {
+    track: Arc<AtomicU32>,
+    art_url: String,
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        "Playing"
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<&str, OwnedValue> {
+        let track = self.track.load(Ordering::Relaxed);
+        let id = ObjectPath::try_from(format!("/org/mpris/track/{track}")).unwrap();
+
+        [
+            ("mpris:trackid", Value::from(id)),
+            ("xesam:title", Value::from(format!("Song {track}"))),
+            ("xesam:artist", Value::from(vec!["Band"])),
+            ("mpris:artUrl", Value::from(self.art_url.as_str())),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key, value.try_into().unwrap()))
+        .collect()
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        2_000_000
+    }
+}
+
+fn track(number: u32, art_url: &str) -> Track {
+    Track {
+        id: format!("/org/mpris/track/{number}"),
+        title: format!("Song {number}"),
+        artists: vec!["Band".into()],
+        album: String::new(),
+        length: None,
+        art_url: Some(art_url.into()),
+    }
+}
+
+/// Updates `app` until a track change arrives.
+fn next_change(app: &mut App) -> TrackChanged {
+    let mut cursor = app
+        .world()
+        .resource::<Events<TrackChanged>>()
+        .get_cursor_current();
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while Instant::now() < deadline {
+        app.update();
+
+        let events = app.world().resource::<Events<TrackChanged>>();
+        if let Some(changed) = cursor.read(events).next() {
+            return changed.clone();
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    panic!("the track didn't change");
+}
+
+#[test]
+fn follows_tracks_and_cover_art() {
+    let bus = PrivateBus::start();
+
+    // Saved without an extension, like Chromium does.
+    let art = env::temp_dir().join(format!("mpris-test-{}-art", process::id()));
+    RgbaImage::new(4, 3)
+        .save_with_format(&art, ImageFormat::Png)
+        .unwrap();
+    let art_url = format!("file://{}", art.display());
+
+    let playing = Arc::new(AtomicU32::new(1));
+    let player = bus.connect();
+    player
+        .object_server()
+        .at(
+            "/org/mpris/MediaPlayer2",
+            Player {
+                track: playing.clone(),
+                art_url: art_url.clone(),
+            },
+        )
+        .unwrap();
+    player.request_name("org.mpris.MediaPlayer2.fake").unwrap();
+
+    let mut app = App::new();
+    app.init_resource::<Assets<Image>>()
+        .add_plugins(MprisPlugin {
+            config: MprisConfig {
+                bus: Some(bus.address.clone()),
+                player: None,
+                interval: Duration::from_millis(50),
+            },
+        });
+
+    assert_eq!(
+        next_change(&mut app),
+        TrackChanged {
+            previous: None,
+            current: Some(track(1, &art_url)),
+        }
+    );
+
+    let now_playing = app.world().resource::<NowPlaying>().clone();
+    assert_eq!(now_playing.player.as_deref(), Some("fake"));
+    assert_eq!(now_playing.status, PlaybackStatus::Playing);
+    assert_eq!(now_playing.position, Duration::from_secs(2));
+    let cover = now_playing.art.expect("the cover art wasn't loaded");
+    let image = app.world().resource::<Assets<Image>>().get(&cover).unwrap();
+    assert_eq!((image.width(), image.height()), (4, 3));
+
+    playing.store(2, Ordering::Relaxed);
+    assert_eq!(
+        next_change(&mut app),
+        TrackChanged {
+            previous: Some(track(1, &art_url)),
+            current: Some(track(2, &art_url)),
+        }
+    );
+    // The art stayed the same, so it wasn't loaded again.
+    assert_eq!(app.world().resource::<NowPlaying>().art, Some(cover));
+
+    fs::remove_file(&art).unwrap();
+}
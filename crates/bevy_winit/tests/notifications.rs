@@ -0,0 +1,129 @@
+//! Watches `Notify` calls on a private bus started with `dbus-daemon`.
+#![cfg(feature = "notifications")]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy_app::App;
+use bevy_ecs::event::Events;
+use bevy_linux_wallpaper::{
+    DesktopNotification, NotificationPlugin, NotificationRule, NotificationWatch, Urgency,
+};
+use zbus::{Message, blocking::Connection, message::Flags, zvariant::Value};
+
+use crate::common::PrivateBus;
+
+/// Calls `Notify` like an app would, without waiting for the daemon to answer.
+fn notify(connection: &Connection, app: &str, summary: &str, urgency: u8) {
+    let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+    let message = Message::method_call("/org/freedesktop/Notifications", "Notify")
+        .unwrap()
+        .destination("org.freedesktop.Notifications")
+        .unwrap()
+        .interface("org.freedesktop.Notifications")
+        .unwrap()
+        .with_flags(Flags::NoReplyExpected)
+        .unwrap()
+        .build(&(app, 0u32, "", summary, "", Vec::<&str>::new(), hints, -1i32))
+        .unwrap();
+
+    connection.send(&message).unwrap();
+}
+
+/// Updates `app` until a notification from `app_name` arrives, returning all that did.
+fn receive_until(
+    app: &mut App,
+    app_name: &str,
+    mut send: impl FnMut(),
+) -> Vec<DesktopNotification> {
+    let mut cursor = app
+        .world()
+        .resource::<Events<DesktopNotification>>()
+        .get_cursor_current();
+    let mut received = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while Instant::now() < deadline {
+        send();
+        app.update();
+
+        let events = app.world().resource::<Events<DesktopNotification>>();
+        received.extend(cursor.read(events).cloned());
+        if received
+            .iter()
+            .any(|notification| notification.app == app_name)
+        {
+            return received;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    panic!("no notification from {app_name} arrived, only {received:?}");
+}
+
+#[test]
+fn rules_and_rate_limit() {
+    let bus = PrivateBus::start();
+
+    // Stands in for the notification daemon, so the calls have somewhere to go.
+    let daemon = bus.connect();
+    daemon
+        .request_name("org.freedesktop.Notifications")
+        .unwrap();
+    let apps = bus.connect();
+
+    let mut app = App::new();
+    app.add_plugins(NotificationPlugin {
+        watch: NotificationWatch {
+            bus: Some(bus.address.clone()),
+            rules: vec![
+                NotificationRule {
+                    summary: Some("build failed".into()),
+                    allow: true,
+                    ..Default::default()
+                },
+                NotificationRule::ignore_app("Spammy"),
+                NotificationRule::ignore_below(Urgency::Normal),
+            ],
+            rate_limit: Duration::from_secs(60),
+        },
+    });
+
+    // The monitor connects in the background, so keep knocking until it listens.
+    receive_until(&mut app, "probe", || {
+        notify(&apps, "probe", "Are you there?", 1)
+    });
+
+    notify(&apps, "Mail", "New message", 1);
+    // Rate limited.
+    notify(&apps, "Mail", "Another message", 1);
+    // Critical notifications get through anyway.
+    notify(&apps, "Mail", "Disk full", 2);
+    notify(&apps, "Spammy", "Buy now", 1);
+    notify(&apps, "Weather", "Sunny", 0);
+    // The first matching rule wins over the rules ignoring the app and low urgency.
+    notify(&apps, "Spammy", "Nightly BUILD FAILED", 0);
+    notify(&apps, "done", "", 1);
+
+    let received = receive_until(&mut app, "done", || {});
+    let notification = |app: &str, summary: &str, urgency| DesktopNotification {
+        app: app.into(),
+        summary: summary.into(),
+        urgency,
+    };
+    assert_eq!(
+        received,
+        [
+            notification("Mail", "New message", Urgency::Normal),
+            notification("Mail", "Disk full", Urgency::Critical),
+            notification("Spammy", "Nightly BUILD FAILED", Urgency::Low),
+            notification("done", "", Urgency::Normal),
+        ]
+    );
+}
@@ -383,7 +383,6 @@ impl ActiveEventLoop {
         .entered();
 
         let window = platform_impl::Window::new(&self.p, window_attributes)?;
-        println!("window-id {:?}", window.id());
         Ok(Window { window })
     }
 
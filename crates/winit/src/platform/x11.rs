@@ -1,10 +1,13 @@
 //! # X11
+use std::collections::HashMap;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::error::OsError;
 use crate::event_loop::{ActiveEventLoop, EventLoop, EventLoopBuilder};
 use crate::monitor::MonitorHandle;
-use crate::window::{Window, WindowAttributes};
+use crate::window::{Theme, Window, WindowAttributes};
 
 use crate::dpi::Size;
 
@@ -84,10 +87,189 @@ pub fn register_xlib_error_hook(hook: XlibErrorHook) {
     crate::platform_impl::XLIB_ERROR_HOOKS.lock().unwrap().push(hook);
 }
 
+/// Hook receiving the raw X events selected with [`ActiveEventLoopExtX11::listen_x11_events`].
+pub type X11EventHook = Box<dyn Fn(&x11rb::protocol::Event) + Send + Sync>;
+
+/// Hook to winit's X event processing.
+///
+/// Hooks are called on the event loop thread with every event selected through
+/// [`ActiveEventLoopExtX11::listen_x11_events`], before winit processes the event itself. Events
+/// on windows nobody listens to never reach the hooks.
+#[inline]
+pub fn register_x11_event_hook(hook: X11EventHook) {
+    crate::platform_impl::X11_EVENT_HOOKS.lock().unwrap().push(hook);
+}
+
+/// The core X events [`ActiveEventLoopExtX11::listen_x11_events`] can forward.
+///
+/// Any number of clients may select these, so listening never conflicts with the window manager.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct X11EventKinds {
+    /// `PropertyNotify` for the window's properties, like `_NET_ACTIVE_WINDOW` on the root.
+    pub property: bool,
+    /// `ConfigureNotify`, `MapNotify`, `UnmapNotify`, `ReparentNotify` and `DestroyNotify` for
+    /// the window itself.
+    pub structure: bool,
+    /// The same events as `structure` plus `CreateNotify`, for the children of the window. On
+    /// the root window these are the top-level windows of every client.
+    pub substructure: bool,
+}
+
+/// Which events a window adopted with [`ActiveEventLoopExtX11::adopt_foreign_window`] listens
+/// to.
+///
+/// Exposure, resize and property change events are always selected, since any number of clients
+/// may listen to them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ForeignWindowOptions {
+    /// Receive cursor motion, enter and leave events.
+    pub pointer_motion: bool,
+    /// Receive mouse button and touch events.
+    ///
+    /// Only one client may select these on a window. On a root window that is usually the window
+    /// manager or desktop, in which case adopting fails.
+    pub pointer_buttons: bool,
+    /// Receive keyboard and focus events.
+    pub keyboard: bool,
+    /// Accept files dragged onto the window.
+    ///
+    /// This goes through a hidden proxy window, so it works even though the drag source can't
+    /// send messages to a window winit didn't create.
+    pub drag_and_drop: bool,
+}
+
+impl Default for ForeignWindowOptions {
+    fn default() -> Self {
+        Self { pointer_motion: true, pointer_buttons: false, keyboard: false, drag_and_drop: true }
+    }
+}
+
+/// The value of a setting published by the XSETTINGS manager.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum XSettingValue {
+    Integer(i32),
+    String(String),
+    /// A color with 16 bits per channel.
+    Color { red: u16, green: u16, blue: u16, alpha: u16 },
+}
+
+/// Every setting published by the XSETTINGS manager, like `Net/ThemeName` or `Xft/DPI`.
+///
+/// Which settings exist depends on the settings daemon (`xsettingsd`, `gsd-xsettings`,
+/// `xfsettingsd`, ...), so every getter returns `None` for settings that aren't published.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct XSettings {
+    settings: HashMap<String, XSettingValue>,
+}
+
+impl XSettings {
+    /// Parse the contents of the `_XSETTINGS_SETTINGS` property on the XSETTINGS manager window.
+    ///
+    /// Returns `None` if the data is malformed.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        crate::platform_impl::x11::parse_xsettings(data).ok()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&XSettingValue> {
+        self.settings.get(name)
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            XSettingValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            XSettingValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// A color setting as `[red, green, blue, alpha]`.
+    pub fn color(&self, name: &str) -> Option<[u16; 4]> {
+        match *self.get(name)? {
+            XSettingValue::Color { red, green, blue, alpha } => Some([red, green, blue, alpha]),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &XSettingValue)> {
+        self.settings.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// The theme the desktop prefers.
+    ///
+    /// `Net/ColorScheme` set to `prefer-dark` or `prefer-light` wins. Otherwise the theme is dark
+    /// if `Net/ThemeName` names a dark variant, like `Adwaita-dark`.
+    pub fn theme(&self) -> Option<Theme> {
+        match self.string("Net/ColorScheme") {
+            Some("prefer-dark") => return Some(Theme::Dark),
+            Some("prefer-light") => return Some(Theme::Light),
+            _ => {},
+        }
+
+        let name = self.string("Net/ThemeName")?;
+        Some(if name.to_ascii_lowercase().contains("dark") { Theme::Dark } else { Theme::Light })
+    }
+}
+
+impl FromIterator<(String, XSettingValue)> for XSettings {
+    fn from_iter<I: IntoIterator<Item = (String, XSettingValue)>>(iter: I) -> Self {
+        Self { settings: iter.into_iter().collect() }
+    }
+}
+
 /// Additional methods on [`ActiveEventLoop`] that are specific to X11.
 pub trait ActiveEventLoopExtX11 {
     /// True if the [`ActiveEventLoop`] uses X11.
     fn is_x11(&self) -> bool;
+
+    /// Wrap an existing window created by another client, such as the root window, in a
+    /// [`Window`].
+    ///
+    /// The returned [`Window`] delivers events and can be rendered to like any other, but winit
+    /// leaves the properties a window manager looks at alone, and dropping it never unmaps or
+    /// destroys the X window. Setters that only make sense for windows winit owns, like
+    /// [`Window::set_visible`] hiding the window, are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `xid` doesn't exist, if the event loop doesn't use X11, or if `options` asks for
+    /// events another client already selected exclusively on the window.
+    fn adopt_foreign_window(
+        &self,
+        xid: XWindow,
+        options: ForeignWindowOptions,
+    ) -> Result<Window, OsError>;
+
+    /// Forward the raw X events of `kinds` on `xid` to the hooks registered with
+    /// [`register_x11_event_hook`].
+    ///
+    /// `xid` may be any window, including the root window and windows of other clients. Calling
+    /// this again for the same window replaces `kinds`, and [`X11EventKinds::default`] stops
+    /// forwarding. The X event selection itself is only ever widened, since winit may rely on
+    /// the events too.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `xid` doesn't exist or if the event loop doesn't use X11.
+    fn listen_x11_events(&self, xid: XWindow, kinds: X11EventKinds) -> Result<(), OsError>;
+
+    /// The settings currently published by the XSETTINGS manager, or `None` if there is no
+    /// manager or the event loop doesn't use X11.
+    ///
+    /// When the settings change, winit sends [`WindowEvent::ThemeChanged`] to every window if
+    /// the [preferred theme](XSettings::theme) changed.
+    ///
+    /// [`WindowEvent::ThemeChanged`]: crate::event::WindowEvent::ThemeChanged
+    fn xsettings(&self) -> Option<XSettings>;
 }
 
 impl ActiveEventLoopExtX11 for ActiveEventLoop {
@@ -95,6 +277,38 @@ impl ActiveEventLoopExtX11 for ActiveEventLoop {
     fn is_x11(&self) -> bool {
         !self.p.is_wayland()
     }
+
+    fn adopt_foreign_window(
+        &self,
+        xid: XWindow,
+        options: ForeignWindowOptions,
+    ) -> Result<Window, OsError> {
+        let _span = tracing::debug_span!(
+            "winit::ActiveEventLoop::adopt_foreign_window",
+            xid,
+            options = ?options
+        )
+        .entered();
+
+        let window = crate::platform_impl::Window::adopt_foreign_window(&self.p, xid, options)?;
+        Ok(Window { window })
+    }
+
+    fn listen_x11_events(&self, xid: XWindow, kinds: X11EventKinds) -> Result<(), OsError> {
+        let _span = tracing::debug_span!(
+            "winit::ActiveEventLoop::listen_x11_events",
+            xid,
+            kinds = ?kinds
+        )
+        .entered();
+
+        self.p.listen_x11_events(xid, kinds)
+    }
+
+    #[inline]
+    fn xsettings(&self) -> Option<XSettings> {
+        self.p.xsettings()
+    }
 }
 
 /// Additional methods on [`EventLoop`] that are specific to X11.
@@ -26,7 +26,9 @@ use crate::icon::Icon;
 use crate::keyboard::Key;
 use crate::platform::pump_events::PumpStatus;
 #[cfg(x11_platform)]
-use crate::platform::x11::{WindowType as XWindowType, XlibErrorHook};
+use crate::platform::x11::{
+    WindowType as XWindowType, X11EventHook, X11EventKinds, XSettings, XlibErrorHook,
+};
 use crate::window::{
     ActivationToken, Cursor, CursorGrabMode, CustomCursor, CustomCursorSource, ImePurpose,
     ResizeDirection, Theme, UserAttentionType, WindowAttributes, WindowButtons, WindowLevel,
@@ -304,6 +306,23 @@ impl Window {
         }
     }
 
+    #[cfg(x11_platform)]
+    pub(crate) fn adopt_foreign_window(
+        window_target: &ActiveEventLoop,
+        xwindow: u32,
+        options: crate::platform::x11::ForeignWindowOptions,
+    ) -> Result<Self, RootOsError> {
+        match *window_target {
+            #[cfg(wayland_platform)]
+            ActiveEventLoop::Wayland(_) => {
+                Err(os_error!(OsError::Misc("foreign windows can only be adopted on X11")))
+            },
+            ActiveEventLoop::X(ref window_target) => {
+                x11::Window::adopt(window_target, xwindow, options).map(Window::X)
+            },
+        }
+    }
+
     pub(crate) fn maybe_queue_on_main(&self, f: impl FnOnce(&Self) + Send + 'static) {
         f(self)
     }
@@ -649,6 +668,10 @@ pub(crate) enum PlatformCustomCursor {
 #[cfg(x11_platform)]
 pub(crate) static XLIB_ERROR_HOOKS: Mutex<Vec<XlibErrorHook>> = Mutex::new(Vec::new());
 
+/// Hooks for raw X events.
+#[cfg(x11_platform)]
+pub(crate) static X11_EVENT_HOOKS: Mutex<Vec<X11EventHook>> = Mutex::new(Vec::new());
+
 #[cfg(x11_platform)]
 unsafe extern "C" fn x_error_callback(
     display: *mut x11::ffi::Display,
@@ -897,6 +920,30 @@ impl ActiveEventLoop {
         x11_or_wayland!(match self; Self(evlp) => evlp.listen_device_events(allowed))
     }
 
+    #[cfg(x11_platform)]
+    pub(crate) fn listen_x11_events(
+        &self,
+        xwindow: u32,
+        kinds: X11EventKinds,
+    ) -> Result<(), RootOsError> {
+        match *self {
+            #[cfg(wayland_platform)]
+            ActiveEventLoop::Wayland(_) => {
+                Err(os_error!(OsError::Misc("X events can only be listened to on X11")))
+            },
+            ActiveEventLoop::X(ref evlp) => evlp.listen_x11_events(xwindow, kinds),
+        }
+    }
+
+    #[cfg(x11_platform)]
+    pub(crate) fn xsettings(&self) -> Option<XSettings> {
+        match *self {
+            #[cfg(wayland_platform)]
+            ActiveEventLoop::Wayland(_) => None,
+            ActiveEventLoop::X(ref evlp) => evlp.x_connection().xsettings().ok().flatten(),
+        }
+    }
+
     #[cfg(feature = "rwh_05")]
     #[inline]
     pub fn raw_display_handle_rwh_05(&self) -> rwh_05::RawDisplayHandle {
@@ -905,7 +952,12 @@ impl ActiveEventLoop {
 
     #[inline]
     pub fn system_theme(&self) -> Option<Theme> {
-        None
+        match *self {
+            #[cfg(wayland_platform)]
+            ActiveEventLoop::Wayland(_) => None,
+            #[cfg(x11_platform)]
+            ActiveEventLoop::X(ref evlp) => evlp.x_connection().xsettings_theme(),
+        }
     }
 
     #[cfg(feature = "rwh_06")]
@@ -80,6 +80,7 @@ atom_manager! {
 
     // Drag-N-Drop Atoms
     XdndAware,
+    XdndProxy,
     XdndEnter,
     XdndLeave,
     XdndDrop,
@@ -2,7 +2,7 @@ use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, VecDeque};
 use std::os::raw::{c_char, c_int, c_long, c_ulong};
 use std::slice;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 use x11_dl::xinput2::{
     self, XIDeviceEvent, XIEnterEvent, XIFocusInEvent, XIFocusOutEvent, XIHierarchyEvent,
@@ -10,8 +10,9 @@ use x11_dl::xinput2::{
 };
 use x11_dl::xlib::{
     self, Display as XDisplay, Window as XWindow, XAnyEvent, XClientMessageEvent, XConfigureEvent,
-    XDestroyWindowEvent, XEvent, XExposeEvent, XKeyEvent, XMapEvent, XPropertyEvent,
-    XReparentEvent, XSelectionEvent, XVisibilityEvent, XkbAnyEvent, XkbStateRec,
+    XCreateWindowEvent, XDestroyWindowEvent, XEvent, XExposeEvent, XKeyEvent, XMapEvent,
+    XPropertyEvent, XReparentEvent, XSelectionEvent, XUnmapEvent, XVisibilityEvent, XkbAnyEvent,
+    XkbStateRec,
 };
 use x11rb::protocol::xinput;
 use x11rb::protocol::xkb::ID as XkbId;
@@ -26,6 +27,7 @@ use crate::event::{
 };
 use crate::event_loop::ActiveEventLoop as RootAEL;
 use crate::keyboard::ModifiersState;
+use crate::platform::x11::X11EventKinds;
 use crate::platform_impl::common::xkb::{self, XkbState};
 use crate::platform_impl::platform::common::xkb::Context;
 use crate::platform_impl::platform::x11::ime::{ImeEvent, ImeEventReceiver, ImeRequest};
@@ -37,6 +39,8 @@ use crate::platform_impl::x11::{
     mkdid, mkwid, util, CookieResultExt, Device, DeviceId, DeviceInfo, Dnd, DndState, ImeReceiver,
     ScrollOrientation, UnownedWindow, WindowId,
 };
+use crate::platform_impl::X11_EVENT_HOOKS;
+use crate::window::Theme;
 
 /// The maximum amount of X modifiers to replay.
 pub const MAX_MOD_REPLAY_LEN: usize = 32;
@@ -72,6 +76,8 @@ pub struct EventProcessor {
     pub xfiltered_modifiers: VecDeque<u8>,
     pub xmodmap: util::ModifierKeymap,
     pub is_composing: bool,
+    /// The theme XSettings prefers, to tell when it changes.
+    pub theme: Option<Theme>,
 }
 
 impl EventProcessor {
@@ -184,6 +190,8 @@ impl EventProcessor {
             return;
         }
 
+        self.forward_raw_event(xev);
+
         match event_type {
             xlib::ClientMessage => self.client_message(xev.as_ref(), &mut callback),
             xlib::SelectionNotify => self.selection_notify(xev.as_ref(), &mut callback),
@@ -318,6 +326,27 @@ impl EventProcessor {
         }
     }
 
+    /// Pass events on windows listened to with `listen_x11_events` to the X event hooks.
+    fn forward_raw_event(&self, xev: &XEvent) {
+        let hooks = X11_EVENT_HOOKS.lock().unwrap();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let wt = Self::window_target(&self.target);
+        let xany: &XAnyEvent = xev.as_ref();
+        let window = xany.window as xproto::Window;
+        let Some(kinds) = wt.xconn.x11_event_listeners.lock().unwrap().get(&window).copied() else {
+            return;
+        };
+
+        if let Some(event) = raw_x11_event(xev, kinds) {
+            for hook in hooks.iter() {
+                hook(&event);
+            }
+        }
+    }
+
     pub fn poll(&self) -> bool {
         let window_target = Self::window_target(&self.target);
         let result = unsafe { (window_target.xconn.xlib.XPending)(window_target.xconn.display) };
@@ -525,8 +554,9 @@ impl EventProcessor {
                 wt.xconn.set_timestamp(time);
 
                 // This results in the `SelectionNotify` event below
+                let requestor = self.with_window(window, |w| w.dnd_proxy()).flatten();
                 unsafe {
-                    self.dnd.convert_selection(window, time);
+                    self.dnd.convert_selection(requestor.unwrap_or(window), time);
                 }
             }
 
@@ -582,7 +612,6 @@ impl EventProcessor {
         let atoms = wt.xconn.atoms();
 
         let window = xev.requestor as xproto::Window;
-        let window_id = mkwid(window);
 
         // Set the timestamp.
         wt.xconn.set_timestamp(xev.time as xproto::Timestamp);
@@ -591,6 +620,9 @@ impl EventProcessor {
             return;
         }
 
+        // Selections requested through a drag and drop proxy belong to the proxied window.
+        let window_id = mkwid(self.dnd_proxy_owner(window).unwrap_or(window));
+
         // This is where we receive data from drag and drop
         self.dnd.result = None;
         if let Ok(mut data) = unsafe { self.dnd.read_data(window) } {
@@ -858,6 +890,10 @@ impl EventProcessor {
         {
             self.process_dpi_change(&mut callback);
         }
+
+        if atom == atoms[_XSETTINGS_SETTINGS] {
+            self.process_theme_change(&mut callback);
+        }
     }
 
     fn visibility_notify<T: 'static, F>(&self, xev: &XVisibilityEvent, mut callback: F)
@@ -1857,9 +1893,44 @@ impl EventProcessor {
         }
     }
 
+    fn process_theme_change<T: 'static, F>(&mut self, callback: &mut F)
+    where
+        F: FnMut(&RootAEL, Event<T>),
+    {
+        let wt = Self::window_target(&self.target);
+        let theme = wt.xconn.xsettings_theme();
+        if theme == self.theme {
+            return;
+        }
+
+        self.theme = theme;
+        let Some(theme) = theme else {
+            return;
+        };
+
+        for window in wt.windows.borrow().values().filter_map(Weak::upgrade) {
+            let window_id = mkwid(window.id().0 as xproto::Window);
+            callback(&self.target, Event::WindowEvent {
+                window_id,
+                event: WindowEvent::ThemeChanged(theme),
+            });
+        }
+    }
+
     fn window_exists(&self, window_id: xproto::Window) -> bool {
         self.with_window(window_id, |_| ()).is_some()
     }
+
+    /// Find the window that `proxy` receives drag and drop messages for.
+    fn dnd_proxy_owner(&self, proxy: xproto::Window) -> Option<xproto::Window> {
+        let window_target = Self::window_target(&self.target);
+        let windows = window_target.windows.borrow();
+        windows
+            .values()
+            .filter_map(Weak::upgrade)
+            .find(|window| window.dnd_proxy() == Some(proxy))
+            .map(|window| window.id().0 as xproto::Window)
+    }
 }
 
 fn is_first_touch(first: &mut Option<u64>, num: &mut u32, id: u64, phase: TouchPhase) -> bool {
@@ -1881,3 +1952,126 @@ fn is_first_touch(first: &mut Option<u64>, num: &mut u32, id: u64, phase: TouchP
 
     *first == Some(id)
 }
+
+/// Convert an Xlib event to the x11rb event it was decoded from, if it is one of `kinds`.
+fn raw_x11_event(xev: &XEvent, kinds: X11EventKinds) -> Option<x11rb::protocol::Event> {
+    use x11rb::protocol::Event as RawEvent;
+
+    let xany: &XAnyEvent = xev.as_ref();
+    let sent = if xany.send_event != 0 { 0x80 } else { 0 };
+    let sequence = xany.serial as u16;
+    // Events about the window itself are structure events, those about its children
+    // substructure events.
+    let wanted = |event: c_ulong, window: c_ulong| {
+        if event == window {
+            kinds.structure
+        } else {
+            kinds.substructure
+        }
+    };
+
+    let event = match xev.get_type() {
+        xlib::PropertyNotify if kinds.property => {
+            let xev: &XPropertyEvent = xev.as_ref();
+            RawEvent::PropertyNotify(xproto::PropertyNotifyEvent {
+                response_type: xproto::PROPERTY_NOTIFY_EVENT | sent,
+                sequence,
+                window: xev.window as xproto::Window,
+                atom: xev.atom as xproto::Atom,
+                time: xev.time as xproto::Timestamp,
+                state: (xev.state as u8).into(),
+            })
+        },
+        xlib::CreateNotify if kinds.substructure => {
+            let xev: &XCreateWindowEvent = xev.as_ref();
+            RawEvent::CreateNotify(xproto::CreateNotifyEvent {
+                response_type: xproto::CREATE_NOTIFY_EVENT | sent,
+                sequence,
+                parent: xev.parent as xproto::Window,
+                window: xev.window as xproto::Window,
+                x: xev.x as i16,
+                y: xev.y as i16,
+                width: xev.width as u16,
+                height: xev.height as u16,
+                border_width: xev.border_width as u16,
+                override_redirect: xev.override_redirect != 0,
+            })
+        },
+        xlib::ConfigureNotify => {
+            let xev: &XConfigureEvent = xev.as_ref();
+            if !wanted(xev.event, xev.window) {
+                return None;
+            }
+            RawEvent::ConfigureNotify(xproto::ConfigureNotifyEvent {
+                response_type: xproto::CONFIGURE_NOTIFY_EVENT | sent,
+                sequence,
+                event: xev.event as xproto::Window,
+                window: xev.window as xproto::Window,
+                above_sibling: xev.above as xproto::Window,
+                x: xev.x as i16,
+                y: xev.y as i16,
+                width: xev.width as u16,
+                height: xev.height as u16,
+                border_width: xev.border_width as u16,
+                override_redirect: xev.override_redirect != 0,
+            })
+        },
+        xlib::MapNotify => {
+            let xev: &XMapEvent = xev.as_ref();
+            if !wanted(xev.event, xev.window) {
+                return None;
+            }
+            RawEvent::MapNotify(xproto::MapNotifyEvent {
+                response_type: xproto::MAP_NOTIFY_EVENT | sent,
+                sequence,
+                event: xev.event as xproto::Window,
+                window: xev.window as xproto::Window,
+                override_redirect: xev.override_redirect != 0,
+            })
+        },
+        xlib::UnmapNotify => {
+            let xev: &XUnmapEvent = xev.as_ref();
+            if !wanted(xev.event, xev.window) {
+                return None;
+            }
+            RawEvent::UnmapNotify(xproto::UnmapNotifyEvent {
+                response_type: xproto::UNMAP_NOTIFY_EVENT | sent,
+                sequence,
+                event: xev.event as xproto::Window,
+                window: xev.window as xproto::Window,
+                from_configure: xev.from_configure != 0,
+            })
+        },
+        xlib::ReparentNotify => {
+            let xev: &XReparentEvent = xev.as_ref();
+            if !wanted(xev.event, xev.window) {
+                return None;
+            }
+            RawEvent::ReparentNotify(xproto::ReparentNotifyEvent {
+                response_type: xproto::REPARENT_NOTIFY_EVENT | sent,
+                sequence,
+                event: xev.event as xproto::Window,
+                window: xev.window as xproto::Window,
+                parent: xev.parent as xproto::Window,
+                x: xev.x as i16,
+                y: xev.y as i16,
+                override_redirect: xev.override_redirect != 0,
+            })
+        },
+        xlib::DestroyNotify => {
+            let xev: &XDestroyWindowEvent = xev.as_ref();
+            if !wanted(xev.event, xev.window) {
+                return None;
+            }
+            RawEvent::DestroyNotify(xproto::DestroyNotifyEvent {
+                response_type: xproto::DESTROY_NOTIFY_EVENT | sent,
+                sequence,
+                event: xev.event as xproto::Window,
+                window: xev.window as xproto::Window,
+            })
+        },
+        _ => return None,
+    };
+
+    Some(event)
+}
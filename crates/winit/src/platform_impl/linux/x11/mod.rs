@@ -29,6 +29,7 @@ use crate::error::{EventLoopError, OsError as RootOsError};
 use crate::event::{Event, StartCause, WindowEvent};
 use crate::event_loop::{ActiveEventLoop as RootAEL, ControlFlow, DeviceEvents, EventLoopClosed};
 use crate::platform::pump_events::PumpStatus;
+use crate::platform::x11::{ForeignWindowOptions, X11EventKinds};
 use crate::platform_impl::common::xkb::Context;
 use crate::platform_impl::platform::{min_timeout, WindowId};
 use crate::platform_impl::{
@@ -57,6 +58,7 @@ use ime::{Ime, ImeCreationError, ImeReceiver, ImeRequest, ImeSender};
 pub(crate) use monitor::{MonitorHandle, VideoModeHandle};
 use window::UnownedWindow;
 pub(crate) use xdisplay::{XConnection, XError, XNotSupported};
+pub(crate) use xsettings::parse_xsettings;
 
 // Xinput constants not defined in x11rb
 const ALL_DEVICES: u16 = 0;
@@ -311,6 +313,7 @@ impl<T: 'static> EventLoop<T> {
         let root_window_target =
             RootAEL { p: PlatformActiveEventLoop::X(window_target), _marker: PhantomData };
 
+        let theme = EventProcessor::window_target(&root_window_target).xconn.xsettings_theme();
         let event_processor = EventProcessor {
             target: root_window_target,
             dnd,
@@ -329,6 +332,7 @@ impl<T: 'static> EventLoop<T> {
             active_window: None,
             modifiers: Default::default(),
             is_composing: false,
+            theme,
         };
 
         // Register for device hotplug events
@@ -652,6 +656,36 @@ impl ActiveEventLoop {
         self.device_events.set(allowed);
     }
 
+    pub(crate) fn listen_x11_events(
+        &self,
+        xwindow: xproto::Window,
+        kinds: X11EventKinds,
+    ) -> Result<(), RootOsError> {
+        let mut listeners = self.xconn.x11_event_listeners.lock().unwrap();
+
+        if kinds == X11EventKinds::default() {
+            listeners.remove(&xwindow);
+            return Ok(());
+        }
+
+        let conn = self.xconn.xcb_connection();
+        let select = || -> Result<(), X11Error> {
+            // Keep whatever winit or an adopted window already selected.
+            let selected = conn.get_window_attributes(xwindow)?.reply()?.your_event_mask;
+            conn.change_window_attributes(
+                xwindow,
+                &xproto::ChangeWindowAttributesAux::new()
+                    .event_mask(selected | x11_event_mask(kinds)),
+            )?
+            .check()?;
+            Ok(())
+        };
+        select().map_err(|err| os_error!(OsError::XError(err.into())))?;
+
+        listeners.insert(xwindow, kinds);
+        Ok(())
+    }
+
     /// Update the device event based on window focus.
     pub fn update_listen_device_events(&self, focus: bool) {
         let device_events = self.device_events.get() == DeviceEvents::Always
@@ -776,7 +810,7 @@ impl DeviceId {
     }
 }
 
-pub struct Window(Arc<UnownedWindow>);
+pub(crate) struct Window(Arc<UnownedWindow>);
 
 impl Deref for Window {
     type Target = UnownedWindow;
@@ -788,12 +822,21 @@ impl Deref for Window {
 }
 
 impl Window {
-    pub fn new(
+    pub(crate) fn new(
         event_loop: &ActiveEventLoop,
         attribs: WindowAttributes,
     ) -> Result<Self, RootOsError> {
         let window = Arc::new(UnownedWindow::new(event_loop, attribs)?);
-        println!("=====================> {:?}", window.id());
+        event_loop.windows.borrow_mut().insert(window.id(), Arc::downgrade(&window));
+        Ok(Window(window))
+    }
+
+    pub(crate) fn adopt(
+        event_loop: &ActiveEventLoop,
+        xwindow: xproto::Window,
+        options: ForeignWindowOptions,
+    ) -> Result<Self, RootOsError> {
+        let window = Arc::new(UnownedWindow::adopt(event_loop, xwindow, options)?);
         event_loop.windows.borrow_mut().insert(window.id(), Arc::downgrade(&window));
         Ok(Window(window))
     }
@@ -804,12 +847,35 @@ impl Drop for Window {
         let window = self.deref();
         let xconn = &window.xconn;
 
+        window.destroy_dnd_proxy();
+
+        // Adopted windows belong to another client, which stays responsible for them.
+        if window.is_foreign() {
+            window.release_foreign();
+            return;
+        }
+
         if let Ok(c) = xconn.xcb_connection().destroy_window(window.id().0 as xproto::Window) {
             c.ignore_error();
         }
     }
 }
 
+/// The core event mask that selects `kinds`.
+pub(crate) fn x11_event_mask(kinds: X11EventKinds) -> xproto::EventMask {
+    let mut mask = xproto::EventMask::NO_EVENT;
+    if kinds.property {
+        mask |= xproto::EventMask::PROPERTY_CHANGE;
+    }
+    if kinds.structure {
+        mask |= xproto::EventMask::STRUCTURE_NOTIFY;
+    }
+    if kinds.substructure {
+        mask |= xproto::EventMask::SUBSTRUCTURE_NOTIFY;
+    }
+    mask
+}
+
 /// Generic sum error type for X11 errors.
 #[derive(Debug)]
 pub enum X11Error {
@@ -843,6 +909,9 @@ pub enum X11Error {
     /// Unable to parse xsettings.
     XsettingsParse(xsettings::ParserError),
 
+    /// Another client holds an exclusive event selection on a foreign window.
+    EventSelectionConflict { window: xproto::Window, events: &'static str },
+
     /// Failed to get property.
     GetProperty(util::GetPropertyError),
 }
@@ -869,6 +938,11 @@ impl fmt::Display for X11Error {
             X11Error::XsettingsParse(err) => {
                 write!(f, "Failed to parse xsettings: {:?}", err)
             },
+            X11Error::EventSelectionConflict { window, events } => write!(
+                f,
+                "Another client already selects {events} events on window {window:#x}, and only \
+                 one client may select them at a time"
+            ),
         }
     }
 }
@@ -8,17 +8,17 @@ use std::{cmp, env};
 use tracing::{debug, info, warn};
 use x11rb::connection::Connection;
 use x11rb::properties::{WmHints, WmSizeHints, WmSizeHintsSpecification};
-use x11rb::protocol::randr;
 use x11rb::protocol::shape::SK;
 use x11rb::protocol::xfixes::{ConnectionExt, RegionWrapper};
-use x11rb::protocol::xproto::{self, ConnectionExt as _, Rectangle, StackMode};
+use x11rb::protocol::xproto::{self, ConnectionExt as _, Rectangle};
+use x11rb::protocol::{randr, xinput};
 
 use crate::cursor::{Cursor, CustomCursor as RootCustomCursor};
 use crate::dpi::{PhysicalPosition, PhysicalSize, Position, Size};
 use crate::error::{ExternalError, NotSupportedError, OsError as RootOsError};
 use crate::event::{Event, InnerSizeWriter, WindowEvent};
 use crate::event_loop::AsyncRequestSerial;
-use crate::platform::x11::WindowType;
+use crate::platform::x11::{ForeignWindowOptions, WindowType};
 use crate::platform_impl::x11::atoms::*;
 use crate::platform_impl::x11::{
     xinput_fp1616_to_float, MonitorHandle as X11MonitorHandle, WakeSender, X11Error,
@@ -122,6 +122,12 @@ pub struct UnownedWindow {
     #[allow(clippy::mutex_atomic)]
     cursor_visible: Mutex<bool>,
     ime_sender: Mutex<ImeSender>,
+    // Input-only window that receives XDND messages on behalf of `xwindow`.
+    dnd_proxy: Option<xproto::Window>, // never changes
+    // Whether `xwindow` belongs to another client, see `adopt`.
+    foreign: bool, // never changes
+    // The events this connection selected on a foreign window before adopting it.
+    prior_event_mask: xproto::EventMask, // never changes
     pub shared_state: Mutex<SharedState>,
     redraw_sender: WakeSender<WindowId>,
     activation_sender: WakeSender<super::ActivationToken>,
@@ -303,36 +309,26 @@ impl UnownedWindow {
         };
 
         // Figure out the window's parent.
-        // let parent = window_attrs.platform_specific.x11.embed_window.unwrap_or(root);
+        let parent = window_attrs.platform_specific.x11.embed_window.unwrap_or(root);
 
         // finally creating the window
         let xwindow = {
-            // let (x, y) = position.map_or((0, 0), Into::into);
-            let wid = screen.root;
-            // let cid = leap!(xconn.xcb_connection().generate_id());
-            // let result = xconn.xcb_connection().configure_window(
-            //     wid,
-            //     &xproto::ConfigureWindowAux::new()
-            //         // .x(x)
-            //         // .y(y)
-            //         // .width::<u32>(dimensions.0.try_into().unwrap())
-            //         // .height::<u32>(dimensions.1.try_into().unwrap())
-            //         .stack_mode(xproto::StackMode::ABOVE),
-            // );
-            // let result = xconn.xcb_connection().create_window(
-            // depth,
-            // wid,
-            // parent,
-            // x,
-            // y,
-            // dimensions.0.try_into().unwrap(),
-            // dimensions.1.try_into().unwrap(),
-            // 0,
-            // xproto::WindowClass::INPUT_OUTPUT,
-            // visual,
-            // &window_attributes,
-            // );
-            // leap!(leap!(result).check());
+            let (x, y) = position.map_or((0, 0), Into::into);
+            let wid = leap!(xconn.xcb_connection().generate_id());
+            let result = xconn.xcb_connection().create_window(
+                depth,
+                wid,
+                parent,
+                x,
+                y,
+                dimensions.0.try_into().unwrap(),
+                dimensions.1.try_into().unwrap(),
+                0,
+                xproto::WindowClass::INPUT_OUTPUT,
+                visual,
+                &window_attributes,
+            );
+            leap!(leap!(result).check());
 
             wid
         };
@@ -349,7 +345,7 @@ impl UnownedWindow {
         }
 
         #[allow(clippy::mutex_atomic)]
-        let window = UnownedWindow {
+        let mut window = UnownedWindow {
             xconn: Arc::clone(xconn),
             xwindow: xwindow as xproto::Window,
             visual,
@@ -359,213 +355,216 @@ impl UnownedWindow {
             cursor_grabbed_mode: Mutex::new(CursorGrabMode::None),
             cursor_visible: Mutex::new(true),
             ime_sender: Mutex::new(event_loop.ime_sender.clone()),
+            dnd_proxy: None,
+            foreign: false,
+            prior_event_mask: xproto::EventMask::NO_EVENT,
             shared_state: SharedState::new(guessed_monitor, &window_attrs),
             redraw_sender: event_loop.redraw_sender.clone(),
             activation_sender: event_loop.activation_sender.clone(),
         };
 
-        // // Title must be set before mapping. Some tiling window managers (i.e. i3) use the window
-        // // title to determine placement/etc., so doing this after mapping would cause the WM to
-        // // act on the wrong title state.
-        // leap!(window.set_title_inner(&window_attrs.title)).ignore_error();
-        // leap!(window.set_decorations_inner(window_attrs.decorations)).ignore_error();
-        //
-        // if let Some(theme) = window_attrs.preferred_theme {
-        //     leap!(window.set_theme_inner(Some(theme))).ignore_error();
-        // }
-
-        // // Embed the window if needed.
-        // if window_attrs.platform_specific.x11.embed_window.is_some() {
-        //     window.embed_window()?;
-        // }
-
-        // {
-        // // Enable drag and drop (TODO: extend API to make this toggleable)
-        // {
-        //     let dnd_aware_atom = atoms[XdndAware];
-        //     let version = &[5u32]; // Latest version; hasn't changed since 2002
-        //     leap!(xconn.change_property(
-        //         window.xwindow,
-        //         dnd_aware_atom,
-        //         u32::from(xproto::AtomEnum::ATOM),
-        //         xproto::PropMode::REPLACE,
-        //         version,
-        //     ))
-        //     .ignore_error();
-        // }
-
-        // // WM_CLASS must be set *before* mapping the window, as per ICCCM!
-        // {
-        //     let (instance, class) = if let Some(name) = window_attrs.platform_specific.name {
-        //         (name.instance, name.general)
-        //     } else {
-        //         let class = env::args_os()
-        //             .next()
-        //             .as_ref()
-        //             // Default to the name of the binary (via argv[0])
-        //             .and_then(|path| Path::new(path).file_name())
-        //             .and_then(|bin_name| bin_name.to_str())
-        //             .map(|bin_name| bin_name.to_owned())
-        //             .unwrap_or_else(|| window_attrs.title.clone());
-        //         // This environment variable is extraordinarily unlikely to actually be used...
-        //         let instance = env::var("RESOURCE_NAME").ok().unwrap_or_else(|| class.clone());
-        //         (instance, class)
-        //     };
-        //
-        //     let class = format!("{instance}\0{class}\0");
-        //     leap!(xconn.change_property(
-        //         window.xwindow,
-        //         xproto::Atom::from(xproto::AtomEnum::WM_CLASS),
-        //         xproto::Atom::from(xproto::AtomEnum::STRING),
-        //         xproto::PropMode::REPLACE,
-        //         class.as_bytes(),
-        //     ))
-        //     .ignore_error();
-        // }
-
-        if let Some(flusher) = leap!(window.set_pid()) {
-            flusher.ignore_error()
+        // Title must be set before mapping. Some tiling window managers (i.e. i3) use the window
+        // title to determine placement/etc., so doing this after mapping would cause the WM to
+        // act on the wrong title state.
+        leap!(window.set_title_inner(&window_attrs.title)).ignore_error();
+        leap!(window.set_decorations_inner(window_attrs.decorations)).ignore_error();
+
+        if let Some(theme) = window_attrs.preferred_theme {
+            leap!(window.set_theme_inner(Some(theme))).ignore_error();
+        }
+
+        // Embed the window if needed.
+        if window_attrs.platform_specific.x11.embed_window.is_some() {
+            window.embed_window()?;
         }
 
-        // leap!(window.set_window_types(window_attrs.platform_specific.x11.x11_window_types))
-        //     .ignore_error();
-
-        //     // Set size hints.
-        //     let mut min_inner_size =
-        //         window_attrs.min_inner_size.map(|size| size.to_physical::<u32>(scale_factor));
-        //     let mut max_inner_size =
-        //         window_attrs.max_inner_size.map(|size| size.to_physical::<u32>(scale_factor));
-        //
-        //     if !window_attrs.resizable {
-        //         if util::wm_name_is_one_of(&["Xfwm4"]) {
-        //             warn!("To avoid a WM bug, disabling resizing has no effect on Xfwm4");
-        //         } else {
-        //             max_inner_size = Some(dimensions.into());
-        //             min_inner_size = Some(dimensions.into());
-        //         }
-        //     }
-        //
-        //     let shared_state = window.shared_state.get_mut().unwrap();
-        //     shared_state.min_inner_size = min_inner_size.map(Into::into);
-        //     shared_state.max_inner_size = max_inner_size.map(Into::into);
-        //     shared_state.resize_increments = window_attrs.resize_increments;
-        //     shared_state.base_size = window_attrs.platform_specific.x11.base_size;
-        //
-        //     let normal_hints = WmSizeHints {
-        //         position: position.map(|PhysicalPosition { x, y }| {
-        //             (WmSizeHintsSpecification::UserSpecified, x, y)
-        //         }),
-        //         size: Some((
-        //             WmSizeHintsSpecification::UserSpecified,
-        //             cast_dimension_to_hint(dimensions.0),
-        //             cast_dimension_to_hint(dimensions.1),
-        //         )),
-        //         max_size: max_inner_size.map(cast_physical_size_to_hint),
-        //         min_size: min_inner_size.map(cast_physical_size_to_hint),
-        //         size_increment: window_attrs
-        //             .resize_increments
-        //             .map(|size| cast_size_to_hint(size, scale_factor)),
-        //         base_size: window_attrs
-        //             .platform_specific
-        //             .x11
-        //             .base_size
-        //             .map(|size| cast_size_to_hint(size, scale_factor)),
-        //         aspect: None,
-        //         win_gravity: None,
-        //     };
-        //     leap!(leap!(normal_hints.set(
-        //         xconn.xcb_connection(),
-        //         window.xwindow as xproto::Window,
-        //         xproto::AtomEnum::WM_NORMAL_HINTS,
-        //     ))
-        //     .check());
-        //
-        //     // Set window icons
-        //     if let Some(icon) = window_attrs.window_icon {
-        //         leap!(window.set_icon_inner(icon.inner)).ignore_error();
-        //     }
-        //
-        //     // Opt into handling window close
-        //     let result = xconn.xcb_connection().change_property(
-        //         xproto::PropMode::REPLACE,
-        //         window.xwindow,
-        //         atoms[WM_PROTOCOLS],
-        //         xproto::AtomEnum::ATOM,
-        //         32,
-        //         2,
-        //         bytemuck::cast_slice::<xproto::Atom, u8>(&[
-        //             atoms[WM_DELETE_WINDOW],
-        //             atoms[_NET_WM_PING],
-        //         ]),
-        //     );
-        //     leap!(result).ignore_error();
-        //
-        //     // Select XInput2 events
-        //     let mask = xinput::XIEventMask::MOTION
-        //         | xinput::XIEventMask::BUTTON_PRESS
-        //         | xinput::XIEventMask::BUTTON_RELEASE
-        //         | xinput::XIEventMask::ENTER
-        //         | xinput::XIEventMask::LEAVE
-        //         | xinput::XIEventMask::FOCUS_IN
-        //         | xinput::XIEventMask::FOCUS_OUT
-        //         | xinput::XIEventMask::TOUCH_BEGIN
-        //         | xinput::XIEventMask::TOUCH_UPDATE
-        //         | xinput::XIEventMask::TOUCH_END;
-        //     leap!(xconn.select_xinput_events(window.xwindow, super::ALL_MASTER_DEVICES, mask))
-        //         .ignore_error();
-        //
-        //     // Set visibility (map window)
-        //     if window_attrs.visible {
-        //         leap!(xconn.xcb_connection().map_window(window.xwindow)).ignore_error();
-        //         leap!(xconn.xcb_connection().configure_window(
-        //             xwindow,
-        //             &xproto::ConfigureWindowAux::new().stack_mode(xproto::StackMode::ABOVE)
-        //         ))
-        //         .ignore_error();
-        //     }
-        //
-        //     // Attempt to make keyboard input repeat detectable
-        //     unsafe {
-        //         let mut supported_ptr = ffi::False;
-        //         (xconn.xlib.XkbSetDetectableAutoRepeat)(
-        //             xconn.display,
-        //             ffi::True,
-        //             &mut supported_ptr,
-        //         );
-        //         if supported_ptr == ffi::False {
-        //             return Err(os_error!(OsError::Misc("`XkbSetDetectableAutoRepeat` failed")));
-        //         }
-        //     }
-        //
-        //     // Try to create input context for the window.
-        //     if let Some(ime) = event_loop.ime.as_ref() {
-        //         let result = ime.borrow_mut().create_context(window.xwindow as ffi::Window,
-        // false);         leap!(result);
-        //     }
-        //
-        //     // These properties must be set after mapping
-        //     if window_attrs.maximized {
-        //         leap!(window.set_maximized_inner(window_attrs.maximized)).ignore_error();
-        //     }
-        //     if window_attrs.fullscreen.is_some() {
-        //         if let Some(flusher) =
-        //             leap!(window
-        //                 .set_fullscreen_inner(window_attrs.fullscreen.clone().map(Into::into)))
-        //         {
-        //             flusher.ignore_error()
-        //         }
-        //
-        //         if let Some(PhysicalPosition { x, y }) = position {
-        //             let shared_state = window.shared_state.get_mut().unwrap();
-        //
-        //             shared_state.restore_position = Some((x, y));
-        //         }
-        //     }
-        //
-        //     leap!(window.set_window_level_inner(window_attrs.window_level)).ignore_error();
-        // }
-        //
-        // window.set_cursor(window_attrs.cursor);
+        {
+            // Enable drag and drop (TODO: extend API to make this toggleable)
+            {
+                let dnd_aware_atom = atoms[XdndAware];
+                let version = &[5u32]; // Latest version; hasn't changed since 2002
+                leap!(xconn.change_property(
+                    window.xwindow,
+                    dnd_aware_atom,
+                    u32::from(xproto::AtomEnum::ATOM),
+                    xproto::PropMode::REPLACE,
+                    version,
+                ))
+                .ignore_error();
+            }
+
+            // WM_CLASS must be set *before* mapping the window, as per ICCCM!
+            {
+                let (instance, class) = if let Some(name) = window_attrs.platform_specific.name {
+                    (name.instance, name.general)
+                } else {
+                    let class = env::args_os()
+                        .next()
+                        .as_ref()
+                        // Default to the name of the binary (via argv[0])
+                        .and_then(|path| Path::new(path).file_name())
+                        .and_then(|bin_name| bin_name.to_str())
+                        .map(|bin_name| bin_name.to_owned())
+                        .unwrap_or_else(|| window_attrs.title.clone());
+                    // This environment variable is extraordinarily unlikely to actually be used...
+                    let instance = env::var("RESOURCE_NAME").ok().unwrap_or_else(|| class.clone());
+                    (instance, class)
+                };
+
+                let class = format!("{instance}\0{class}\0");
+                leap!(xconn.change_property(
+                    window.xwindow,
+                    xproto::Atom::from(xproto::AtomEnum::WM_CLASS),
+                    xproto::Atom::from(xproto::AtomEnum::STRING),
+                    xproto::PropMode::REPLACE,
+                    class.as_bytes(),
+                ))
+                .ignore_error();
+            }
+
+            if let Some(flusher) = leap!(window.set_pid()) {
+                flusher.ignore_error()
+            }
+
+            leap!(window.set_window_types(window_attrs.platform_specific.x11.x11_window_types))
+                .ignore_error();
+
+            // Set size hints.
+            let mut min_inner_size =
+                window_attrs.min_inner_size.map(|size| size.to_physical::<u32>(scale_factor));
+            let mut max_inner_size =
+                window_attrs.max_inner_size.map(|size| size.to_physical::<u32>(scale_factor));
+
+            if !window_attrs.resizable {
+                if util::wm_name_is_one_of(&["Xfwm4"]) {
+                    warn!("To avoid a WM bug, disabling resizing has no effect on Xfwm4");
+                } else {
+                    max_inner_size = Some(dimensions.into());
+                    min_inner_size = Some(dimensions.into());
+                }
+            }
+
+            let shared_state = window.shared_state.get_mut().unwrap();
+            shared_state.min_inner_size = min_inner_size.map(Into::into);
+            shared_state.max_inner_size = max_inner_size.map(Into::into);
+            shared_state.resize_increments = window_attrs.resize_increments;
+            shared_state.base_size = window_attrs.platform_specific.x11.base_size;
+
+            let normal_hints = WmSizeHints {
+                position: position.map(|PhysicalPosition { x, y }| {
+                    (WmSizeHintsSpecification::UserSpecified, x, y)
+                }),
+                size: Some((
+                    WmSizeHintsSpecification::UserSpecified,
+                    cast_dimension_to_hint(dimensions.0),
+                    cast_dimension_to_hint(dimensions.1),
+                )),
+                max_size: max_inner_size.map(cast_physical_size_to_hint),
+                min_size: min_inner_size.map(cast_physical_size_to_hint),
+                size_increment: window_attrs
+                    .resize_increments
+                    .map(|size| cast_size_to_hint(size, scale_factor)),
+                base_size: window_attrs
+                    .platform_specific
+                    .x11
+                    .base_size
+                    .map(|size| cast_size_to_hint(size, scale_factor)),
+                aspect: None,
+                win_gravity: None,
+            };
+            leap!(leap!(normal_hints.set(
+                xconn.xcb_connection(),
+                window.xwindow as xproto::Window,
+                xproto::AtomEnum::WM_NORMAL_HINTS,
+            ))
+            .check());
+
+            // Set window icons
+            if let Some(icon) = window_attrs.window_icon {
+                leap!(window.set_icon_inner(icon.inner)).ignore_error();
+            }
+
+            // Opt into handling window close
+            let result = xconn.xcb_connection().change_property(
+                xproto::PropMode::REPLACE,
+                window.xwindow,
+                atoms[WM_PROTOCOLS],
+                xproto::AtomEnum::ATOM,
+                32,
+                2,
+                bytemuck::cast_slice::<xproto::Atom, u8>(&[
+                    atoms[WM_DELETE_WINDOW],
+                    atoms[_NET_WM_PING],
+                ]),
+            );
+            leap!(result).ignore_error();
+
+            // Select XInput2 events
+            let mask = xinput::XIEventMask::MOTION
+                | xinput::XIEventMask::BUTTON_PRESS
+                | xinput::XIEventMask::BUTTON_RELEASE
+                | xinput::XIEventMask::ENTER
+                | xinput::XIEventMask::LEAVE
+                | xinput::XIEventMask::FOCUS_IN
+                | xinput::XIEventMask::FOCUS_OUT
+                | xinput::XIEventMask::TOUCH_BEGIN
+                | xinput::XIEventMask::TOUCH_UPDATE
+                | xinput::XIEventMask::TOUCH_END;
+            leap!(xconn.select_xinput_events(window.xwindow, super::ALL_MASTER_DEVICES, mask))
+                .ignore_error();
+
+            // Set visibility (map window)
+            if window_attrs.visible {
+                leap!(xconn.xcb_connection().map_window(window.xwindow)).ignore_error();
+                leap!(xconn.xcb_connection().configure_window(
+                    xwindow,
+                    &xproto::ConfigureWindowAux::new().stack_mode(xproto::StackMode::ABOVE)
+                ))
+                .ignore_error();
+            }
+
+            // Attempt to make keyboard input repeat detectable
+            unsafe {
+                let mut supported_ptr = ffi::False;
+                (xconn.xlib.XkbSetDetectableAutoRepeat)(
+                    xconn.display,
+                    ffi::True,
+                    &mut supported_ptr,
+                );
+                if supported_ptr == ffi::False {
+                    return Err(os_error!(OsError::Misc("`XkbSetDetectableAutoRepeat` failed")));
+                }
+            }
+
+            // Try to create input context for the window.
+            if let Some(ime) = event_loop.ime.as_ref() {
+                let result = ime.borrow_mut().create_context(window.xwindow as ffi::Window, false);
+                leap!(result);
+            }
+
+            // These properties must be set after mapping
+            if window_attrs.maximized {
+                leap!(window.set_maximized_inner(window_attrs.maximized)).ignore_error();
+            }
+            if window_attrs.fullscreen.is_some() {
+                if let Some(flusher) =
+                    leap!(window
+                        .set_fullscreen_inner(window_attrs.fullscreen.clone().map(Into::into)))
+                {
+                    flusher.ignore_error()
+                }
+
+                if let Some(PhysicalPosition { x, y }) = position {
+                    let shared_state = window.shared_state.get_mut().unwrap();
+
+                    shared_state.restore_position = Some((x, y));
+                }
+            }
+
+            leap!(window.set_window_level_inner(window_attrs.window_level)).ignore_error();
+        }
+
+        window.set_cursor(window_attrs.cursor);
 
         // Remove the startup notification if we have one.
         if let Some(startup) = window_attrs.platform_specific.activation_token.as_ref() {
@@ -578,6 +577,202 @@ impl UnownedWindow {
         Ok(window)
     }
 
+    /// Wrap a window created by another client, such as the root window.
+    ///
+    /// Unlike [`UnownedWindow::new`], this doesn't touch any of the properties a window manager
+    /// looks at and never maps, unmaps or destroys the window. Only the events enabled in
+    /// `options` are selected, and selecting ones another client already holds exclusively is
+    /// reported as [`X11Error::EventSelectionConflict`].
+    pub(crate) fn adopt(
+        event_loop: &ActiveEventLoop,
+        xwindow: xproto::Window,
+        options: ForeignWindowOptions,
+    ) -> Result<UnownedWindow, RootOsError> {
+        use xproto::EventMask;
+
+        let xconn = &event_loop.xconn;
+        let atoms = xconn.atoms();
+
+        let geometry = leap!(leap!(xconn.xcb_connection().get_geometry(xwindow)).reply());
+        let attributes =
+            leap!(leap!(xconn.xcb_connection().get_window_attributes(xwindow)).reply());
+        let root = geometry.root;
+        let screen_id = xconn
+            .xcb_connection()
+            .setup()
+            .roots
+            .iter()
+            .position(|screen| screen.root == root)
+            .unwrap_or_else(|| xconn.default_screen_index()) as c_int;
+
+        // The window doesn't move on its own, so pick the monitor under its top left corner.
+        let mut monitors = leap!(xconn.available_monitors());
+        let guessed_monitor = monitors
+            .iter()
+            .position(|monitor| monitor.rect.contains_point(geometry.x.into(), geometry.y.into()))
+            .map(|i| monitors.swap_remove(i))
+            .or_else(|| monitors.pop())
+            .unwrap_or_else(X11MonitorHandle::dummy);
+
+        let window_attrs = WindowAttributes::default()
+            .with_inner_size(PhysicalSize::new(geometry.width, geometry.height))
+            .with_decorations(false)
+            .with_resizable(false);
+
+        // Only core events anyone may select. `BUTTON_PRESS` and the redirect masks can only be
+        // held by one client, and for a root window that is usually the window manager.
+        let mut event_mask = EventMask::EXPOSURE
+            | EventMask::STRUCTURE_NOTIFY
+            | EventMask::VISIBILITY_CHANGE
+            | EventMask::PROPERTY_CHANGE;
+        if options.keyboard {
+            event_mask |= EventMask::KEY_PRESS | EventMask::KEY_RELEASE | EventMask::KEYMAP_STATE;
+        }
+
+        // Keep the events the window already selected through this connection.
+        let event_mask = event_mask | attributes.your_event_mask;
+        Self::select_foreign_events(
+            xwindow,
+            "core",
+            xconn.xcb_connection().change_window_attributes(
+                xwindow,
+                &xproto::ChangeWindowAttributesAux::new().event_mask(event_mask),
+            ),
+        )?;
+
+        let mut xinput_mask = xinput::XIEventMask::from(0u32);
+        if options.pointer_motion {
+            xinput_mask |= xinput::XIEventMask::MOTION
+                | xinput::XIEventMask::ENTER
+                | xinput::XIEventMask::LEAVE;
+        }
+        if options.pointer_buttons {
+            xinput_mask |= xinput::XIEventMask::BUTTON_PRESS
+                | xinput::XIEventMask::BUTTON_RELEASE
+                | xinput::XIEventMask::TOUCH_BEGIN
+                | xinput::XIEventMask::TOUCH_UPDATE
+                | xinput::XIEventMask::TOUCH_END;
+        }
+        if options.keyboard {
+            xinput_mask |= xinput::XIEventMask::FOCUS_IN | xinput::XIEventMask::FOCUS_OUT;
+        }
+        if u32::from(xinput_mask) != 0 {
+            Self::select_foreign_events(
+                xwindow,
+                "XInput pointer button",
+                xconn.select_xinput_events(xwindow, super::ALL_MASTER_DEVICES, xinput_mask),
+            )?;
+        }
+
+        // Drag sources deliver XDND client messages with an empty event mask, which only reaches
+        // the client that created the target window, so route the protocol through a proxy.
+        let dnd_proxy = if options.drag_and_drop {
+            Some(leap!(Self::create_dnd_proxy(xconn, xwindow)))
+        } else {
+            None
+        };
+
+        #[allow(clippy::mutex_atomic)]
+        let window = UnownedWindow {
+            xconn: Arc::clone(xconn),
+            xwindow,
+            visual: attributes.visual,
+            root,
+            screen_id,
+            selected_cursor: Default::default(),
+            cursor_grabbed_mode: Mutex::new(CursorGrabMode::None),
+            cursor_visible: Mutex::new(true),
+            ime_sender: Mutex::new(event_loop.ime_sender.clone()),
+            dnd_proxy,
+            foreign: true,
+            prior_event_mask: attributes.your_event_mask,
+            shared_state: SharedState::new(guessed_monitor, &window_attrs),
+            redraw_sender: event_loop.redraw_sender.clone(),
+            activation_sender: event_loop.activation_sender.clone(),
+        };
+
+        if let Some(proxy) = dnd_proxy {
+            let version = &[5u32]; // Latest version; hasn't changed since 2002
+            leap!(xconn.change_property(
+                proxy,
+                atoms[XdndAware],
+                u32::from(xproto::AtomEnum::ATOM),
+                xproto::PropMode::REPLACE,
+                version,
+            ))
+            .ignore_error();
+
+            // The XDND spec requires `XdndProxy` on both the window the source sees and on the
+            // proxy itself, so sources can detect a stale property left behind by a crash.
+            for target in [xwindow, proxy] {
+                leap!(xconn.change_property(
+                    target,
+                    atoms[XdndProxy],
+                    u32::from(xproto::AtomEnum::WINDOW),
+                    xproto::PropMode::REPLACE,
+                    &[proxy],
+                ))
+                .ignore_error();
+            }
+        }
+
+        let window = leap!(xconn.sync_with_server().map(|_| window));
+
+        Ok(window)
+    }
+
+    /// Check an event selection request on a foreign window, turning `BadAccess` into a
+    /// readable error.
+    fn select_foreign_events(
+        xwindow: xproto::Window,
+        events: &'static str,
+        request: Result<VoidCookie<'_>, impl Into<X11Error>>,
+    ) -> Result<(), RootOsError> {
+        let result = request.map_err(Into::into).and_then(|cookie| Ok(cookie.check()?));
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(X11Error::X11(err)) if err.error_kind == x11rb::protocol::ErrorKind::Access => {
+                Err(os_error!(OsError::XError(
+                    X11Error::EventSelectionConflict { window: xwindow, events }.into()
+                )))
+            },
+            Err(err) => Err(os_error!(OsError::XError(err.into()))),
+        }
+    }
+
+    /// Whether this window was adopted from another client rather than created by winit.
+    #[inline]
+    pub(crate) fn is_foreign(&self) -> bool {
+        self.foreign
+    }
+
+    /// Undo the event selections made by [`UnownedWindow::adopt`], leaving the window itself
+    /// alone.
+    ///
+    /// The events selected before adopting the window, and the ones still listened to through
+    /// [`ActiveEventLoop::listen_x11_events`], stay selected.
+    pub(super) fn release_foreign(&self) {
+        let conn = self.xconn.xcb_connection();
+        let listened = self.xconn.x11_event_listeners.lock().unwrap().get(&self.xwindow).copied();
+        let event_mask = self.prior_event_mask
+            | listened.map_or(xproto::EventMask::NO_EVENT, super::x11_event_mask);
+
+        if let Ok(c) = conn.change_window_attributes(
+            self.xwindow,
+            &xproto::ChangeWindowAttributesAux::new().event_mask(event_mask),
+        ) {
+            c.ignore_error();
+        }
+        if let Ok(c) = self.xconn.select_xinput_events(
+            self.xwindow,
+            super::ALL_MASTER_DEVICES,
+            xinput::XIEventMask::from(0u32),
+        ) {
+            c.ignore_error();
+        }
+    }
+
     /// Embed this window into a parent window.
     pub(super) fn embed_window(&self) -> Result<(), RootOsError> {
         let atoms = self.xconn.atoms();
@@ -593,6 +788,53 @@ impl UnownedWindow {
         Ok(())
     }
 
+    /// Create the unmapped input-only window that stands in for `parent` during drag and drop.
+    fn create_dnd_proxy(
+        xconn: &XConnection,
+        parent: xproto::Window,
+    ) -> Result<xproto::Window, X11Error> {
+        let proxy = xconn.xcb_connection().generate_id()?;
+        xconn
+            .xcb_connection()
+            .create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                proxy,
+                parent,
+                -1,
+                -1,
+                1,
+                1,
+                0,
+                xproto::WindowClass::INPUT_ONLY,
+                x11rb::COPY_FROM_PARENT,
+                &xproto::CreateWindowAux::new(),
+            )?
+            .check()?;
+        Ok(proxy)
+    }
+
+    /// The window that receives XDND messages for this window, if it isn't the window itself.
+    #[inline]
+    pub(crate) fn dnd_proxy(&self) -> Option<xproto::Window> {
+        self.dnd_proxy
+    }
+
+    /// Remove the drag and drop proxy, if any, so sources stop targeting a dead window.
+    pub(super) fn destroy_dnd_proxy(&self) {
+        let Some(proxy) = self.dnd_proxy else {
+            return;
+        };
+
+        let atoms = self.xconn.atoms();
+        if let Ok(c) = self.xconn.xcb_connection().delete_property(self.xwindow, atoms[XdndProxy])
+        {
+            c.ignore_error();
+        }
+        if let Ok(c) = self.xconn.xcb_connection().destroy_window(proxy) {
+            c.ignore_error();
+        }
+    }
+
     pub(super) fn shared_state_lock(&self) -> MutexGuard<'_, SharedState> {
         self.shared_state.lock().unwrap()
     }
@@ -1132,6 +1374,11 @@ impl UnownedWindow {
 
     #[inline]
     pub fn set_visible(&self, visible: bool) {
+        // Hiding a window we don't own, such as the root window, is never what the caller wants.
+        if self.foreign {
+            return;
+        }
+
         let mut shared_state = self.shared_state_lock();
 
         match (visible, shared_state.visibility) {
@@ -1898,7 +2145,7 @@ impl UnownedWindow {
 
     #[inline]
     pub fn theme(&self) -> Option<Theme> {
-        None
+        self.xconn.xsettings_theme()
     }
 
     pub fn set_content_protected(&self, _protected: bool) {}
@@ -4,6 +4,7 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
 use std::{fmt, ptr};
 
+use crate::platform::x11::X11EventKinds;
 use crate::window::CursorIcon;
 
 use super::atoms::Atoms;
@@ -57,6 +58,9 @@ pub struct XConnection {
 
     pub latest_error: Mutex<Option<XError>>,
     pub cursor_cache: Mutex<HashMap<Option<CursorIcon>, ffi::Cursor>>,
+
+    /// Windows whose raw events are forwarded to the X event hooks.
+    pub x11_event_listeners: Mutex<HashMap<xproto::Window, X11EventKinds>>,
 }
 
 unsafe impl Send for XConnection {}
@@ -137,6 +141,7 @@ impl XConnection {
             monitor_handles: Mutex::new(None),
             database: RwLock::new(database),
             cursor_cache: Default::default(),
+            x11_event_listeners: Default::default(),
             randr_version: (randr_version.major_version, randr_version.minor_version),
             xsettings_screen,
         })
@@ -11,6 +11,8 @@ use x11rb::protocol::xproto::{self, ConnectionExt};
 
 use super::atoms::*;
 use super::XConnection;
+use crate::platform::x11::{XSettingValue, XSettings};
+use crate::window::Theme;
 
 type Result<T> = core::result::Result<T, ParserError>;
 
@@ -56,6 +58,49 @@ impl XConnection {
     }
 }
 
+impl XConnection {
+    /// Get every setting from XSettings, or `None` if there is no XSettings manager.
+    pub(crate) fn xsettings(&self) -> core::result::Result<Option<XSettings>, super::X11Error> {
+        let atoms = self.atoms();
+        let Some(xsettings_screen) = self.xsettings_screen() else {
+            return Ok(None);
+        };
+        let owner = self.xcb_connection().get_selection_owner(xsettings_screen)?.reply()?;
+        let data: Vec<u8> =
+            self.get_property(owner.owner, atoms[_XSETTINGS_SETTINGS], atoms[_XSETTINGS_SETTINGS])?;
+
+        Ok(Some(parse_xsettings(&data)?))
+    }
+
+    /// Get the theme the desktop prefers from XSettings.
+    pub(crate) fn xsettings_theme(&self) -> Option<Theme> {
+        self.xsettings().ok()??.theme()
+    }
+}
+
+/// Parse every setting in the block of data.
+pub(crate) fn parse_xsettings(data: &[u8]) -> Result<XSettings> {
+    read_settings(data)?
+        .map(|setting| {
+            let setting = setting?;
+            let value = match setting.data {
+                SettingData::Integer(value) => XSettingValue::Integer(value),
+                SettingData::String(value) => {
+                    XSettingValue::String(String::from_utf8_lossy(value).into_owned())
+                },
+                SettingData::Color([red, blue, green, alpha]) => XSettingValue::Color {
+                    red: red as u16,
+                    green: green as u16,
+                    blue: blue as u16,
+                    alpha: alpha as u16,
+                },
+            };
+
+            Ok((String::from_utf8_lossy(setting.name).into_owned(), value))
+        })
+        .collect()
+}
+
 /// Read over the settings in the block of data.
 fn read_settings(data: &[u8]) -> Result<impl Iterator<Item = Result<Setting<'_>>> + '_> {
     // Create a parser. This automatically parses the first 8 bytes for metadata.
@@ -81,8 +126,8 @@ struct Setting<'a> {
 /// The data contained in a setting.
 enum SettingData<'a> {
     Integer(i32),
-    String(#[allow(dead_code)] &'a [u8]),
-    Color(#[allow(dead_code)] [i16; 4]),
+    String(&'a [u8]),
+    Color([i16; 4]),
 }
 
 impl<'a> Setting<'a> {
@@ -309,6 +354,24 @@ mod tests {
         assert_string(&lcd.data, "lcddefault");
     }
 
+    #[test]
+    fn parse_all_settings() {
+        let data = XSETTINGS
+            .trim()
+            .split(',')
+            .map(|tok| u8::from_str_radix(tok.strip_prefix("0x").unwrap(), 16).unwrap())
+            .collect::<Vec<_>>();
+
+        let settings = super::parse_xsettings(&data).unwrap();
+
+        assert_eq!(settings.integer("Xft/DPI"), Some(96 * 1024));
+        assert_eq!(settings.string("Net/ThemeName"), Some("Greybird"));
+        assert_eq!(settings.string("Gtk/CursorThemeName"), Some("DMZ-White"));
+        assert_eq!(settings.string("Net/IconThemeName"), Some("elementary-xfce-dark"));
+        assert_eq!(settings.integer("Net/ThemeName"), None);
+        assert_eq!(settings.theme(), Some(Theme::Light));
+    }
+
     fn assert_string(dat: &SettingData<'_>, s: &str) {
         match dat {
             SettingData::String(left) => assert_eq!(*left, s.as_bytes()),